@@ -0,0 +1,9 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" tiledversion="1.10.2" name="props" tilewidth="16" tileheight="16" tilecount="1" columns="1">
+ <image source="props.png" width="16" height="16"/>
+ <tile id="0">
+  <properties>
+   <property name="cost" type="int" value="255"/>
+  </properties>
+ </tile>
+</tileset>
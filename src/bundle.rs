@@ -21,6 +21,11 @@ pub struct FlowFieldTilesBundle {
 	pub route_cache: RouteCache,
 	/// Cache of [FlowField]s that can be queried in a steering pipeline
 	pub flow_field_cache: FlowFieldCache,
+	/// Temporary modifiers applied on top of the [CostField]s
+	pub sector_cost_modifiers: SectorCostModifiers,
+	/// Height of the terrain at each [FieldCell], empty (flat) unless set with [FlowFieldTilesBundle::with_elevation]
+	#[cfg(feature = "3d")]
+	pub elevation_field: ElevationField,
 }
 
 impl FlowFieldTilesBundle {
@@ -56,6 +61,45 @@ impl FlowFieldTilesBundle {
 	pub fn get_flowfield_cache_mut(&mut self) -> &mut FlowFieldCache {
 		&mut self.flow_field_cache
 	}
+	/// Get a reference to the [SectorCostModifiers]
+	pub fn get_sector_cost_modifiers(&self) -> &SectorCostModifiers {
+		&self.sector_cost_modifiers
+	}
+	/// Get a reference to the [ElevationField]
+	#[cfg(feature = "3d")]
+	pub fn get_elevation_field(&self) -> &ElevationField {
+		&self.elevation_field
+	}
+	/// Give the map an [ElevationField] so that 3d positions and directions follow the height of the terrain. The [CostField]s are unaffected, see [FlowFieldTilesBundle::from_elevation] to also derive them from the slope of the terrain
+	#[cfg(feature = "3d")]
+	pub fn with_elevation(mut self, elevation_field: ElevationField) -> Self {
+		self.elevation_field = elevation_field;
+		self
+	}
+	/// Add another layer of sectors to the map, i.e the next floor of a building. `layer_cost_fields` can be built with any of the [SectorCostFields] constructors for the same [MapDimensions] and become the layer after the highest existing one, see [SectorCostFields::get_sector_layer_count]. Use [FlowFieldTilesBundle::with_layer_connection] to join the layers together
+	pub fn with_layer(mut self, layer_cost_fields: SectorCostFields) -> Self {
+		let layer = self.sector_cost_fields.get_sector_layer_count();
+		self.sector_cost_fields
+			.insert_sector_layer(layer, &layer_cost_fields);
+		self.sector_portals
+			.add_sector_layer(layer, &self.sector_cost_fields, &self.map_dimensions);
+		self.portal_graph = PortalGraph::new_with_connections(
+			&self.sector_portals,
+			&self.sector_cost_fields,
+			&self.map_dimensions,
+			self.portal_graph.get_layer_connections().clone(),
+		);
+		self
+	}
+	/// Join two sectors on different layers with a [LayerConnection], i.e stairs, a ramp or an elevator
+	pub fn with_layer_connection(mut self, connection: LayerConnection) -> Self {
+		self.portal_graph.add_layer_connection(
+			connection,
+			&self.sector_portals,
+			&self.sector_cost_fields,
+		);
+		self
+	}
 	/// Create a new instance of [FlowFieldTilesBundle] based on map dimensions
 	pub fn new(map_length: u32, map_depth: u32, sector_resolution: u32, actor_size: f32) -> Self {
 		let map_dimensions =
@@ -76,6 +120,9 @@ impl FlowFieldTilesBundle {
 			map_dimensions,
 			route_cache,
 			flow_field_cache: cache,
+			sector_cost_modifiers: SectorCostModifiers::default(),
+			#[cfg(feature = "3d")]
+			elevation_field: ElevationField::default(),
 		}
 	}
 	/// Create a new instance of [FlowFieldTilesBundle] based on map dimensions where the [SectorCostFields] are derived from a `.ron` file
@@ -110,6 +157,9 @@ impl FlowFieldTilesBundle {
 			map_dimensions,
 			route_cache,
 			flow_field_cache: cache,
+			sector_cost_modifiers: SectorCostModifiers::default(),
+			#[cfg(feature = "3d")]
+			elevation_field: ElevationField::default(),
 		}
 	}
 	/// Create a new instance of [FlowFieldTilesBundle] from a directory containing CSV [CostField] files
@@ -140,6 +190,76 @@ impl FlowFieldTilesBundle {
 			map_dimensions,
 			route_cache,
 			flow_field_cache: cache,
+			sector_cost_modifiers: SectorCostModifiers::default(),
+			#[cfg(feature = "3d")]
+			elevation_field: ElevationField::default(),
+		}
+	}
+	/// Create a new instance of [FlowFieldTilesBundle] from a single CSV file containing the [CostField] value of every [FieldCell] of the map
+	#[cfg(not(tarpaulin_include))]
+	#[cfg(feature = "csv")]
+	pub fn from_csv_file(
+		map_length: u32,
+		map_depth: u32,
+		sector_resolution: u32,
+		actor_size: f32,
+		file_path: &str,
+	) -> Self {
+		let map_dimensions =
+			MapDimensions::new(map_length, map_depth, sector_resolution, actor_size);
+		let cost_fields = SectorCostFields::from_csv(&map_dimensions, file_path.to_string());
+		let mut portals = SectorPortals::new(map_length, map_depth, sector_resolution);
+		// update default portals for cost fields
+		for sector_id in cost_fields.get_scaled().keys() {
+			portals.update_portals(*sector_id, &cost_fields, &map_dimensions);
+		}
+		let graph = PortalGraph::new(&portals, &cost_fields, &map_dimensions);
+		let route_cache = RouteCache::default();
+		let cache = FlowFieldCache::default();
+		FlowFieldTilesBundle {
+			sector_cost_fields: cost_fields,
+			sector_portals: portals,
+			portal_graph: graph,
+			map_dimensions,
+			route_cache,
+			flow_field_cache: cache,
+			sector_cost_modifiers: SectorCostModifiers::default(),
+			#[cfg(feature = "3d")]
+			elevation_field: ElevationField::default(),
+		}
+	}
+	/// Create a new instance of [FlowFieldTilesBundle] from a plain text map where each character represents a [FieldCell] and `legend` determines the [CostField] value of each character
+	#[cfg(not(tarpaulin_include))]
+	pub fn from_ascii(
+		map_length: u32,
+		map_depth: u32,
+		sector_resolution: u32,
+		actor_size: f32,
+		file_path: &str,
+		legend: &AsciiLegend,
+	) -> Self {
+		let map_dimensions =
+			MapDimensions::new(map_length, map_depth, sector_resolution, actor_size);
+		let cost_fields =
+			SectorCostFields::from_ascii(&map_dimensions, file_path.to_string(), legend);
+		let mut portals = SectorPortals::new(map_length, map_depth, sector_resolution);
+		// update default portals for cost fields
+		for sector_id in cost_fields.get_scaled().keys() {
+			portals.update_portals(*sector_id, &cost_fields, &map_dimensions);
+		}
+		let graph = PortalGraph::new(&portals, &cost_fields, &map_dimensions);
+		let route_cache = RouteCache::default();
+		let cache = FlowFieldCache::default();
+		FlowFieldTilesBundle {
+			sector_cost_fields: cost_fields,
+			sector_portals: portals,
+			portal_graph: graph,
+			map_dimensions,
+			route_cache,
+			flow_field_cache: cache,
+			sector_cost_modifiers: SectorCostModifiers::default(),
+			#[cfg(feature = "3d")]
+			elevation_field: ElevationField::default(),
 		}
 	}
 	/// From a greyscale heightmap image initialise a bundle where the
@@ -171,6 +291,185 @@ impl FlowFieldTilesBundle {
 			map_dimensions,
 			route_cache,
 			flow_field_cache: cache,
+			sector_cost_modifiers: SectorCostModifiers::default(),
+			#[cfg(feature = "3d")]
+			elevation_field: ElevationField::default(),
+		}
+	}
+	/// From a heightmap image initialise a bundle where `costs` determines how the colour of each pixel is translated into a [CostField] value, such as a palette of terrain colours
+	#[cfg(not(tarpaulin_include))]
+	#[cfg(feature = "heightmap")]
+	pub fn from_heightmap_with_costs(
+		map_length: u32,
+		map_depth: u32,
+		sector_resolution: u32,
+		actor_size: f32,
+		file_path: &str,
+		costs: &HeightmapCosts,
+	) -> Self {
+		let map_dimensions =
+			MapDimensions::new(map_length, map_depth, sector_resolution, actor_size);
+		let cost_fields = SectorCostFields::from_heightmap_with_costs(
+			&map_dimensions,
+			file_path.to_string(),
+			costs,
+		);
+		let mut portals = SectorPortals::new(map_length, map_depth, sector_resolution);
+		// update default portals for cost fields
+		for sector_id in cost_fields.get_scaled().keys() {
+			portals.update_portals(*sector_id, &cost_fields, &map_dimensions);
+		}
+		let graph = PortalGraph::new(&portals, &cost_fields, &map_dimensions);
+		let route_cache = RouteCache::default();
+		let cache = FlowFieldCache::default();
+		FlowFieldTilesBundle {
+			sector_cost_fields: cost_fields,
+			sector_portals: portals,
+			portal_graph: graph,
+			map_dimensions,
+			route_cache,
+			flow_field_cache: cache,
+			sector_cost_modifiers: SectorCostModifiers::default(),
+			#[cfg(feature = "3d")]
+			elevation_field: ElevationField::default(),
+		}
+	}
+	/// From a [Tiled](https://www.mapeditor.org/) map (`.tmx` or `.tmj`) initialise a bundle where each tile represents a [FieldCell] and `costs` determines the [CostField] value of each tile
+	#[cfg(not(tarpaulin_include))]
+	#[cfg(feature = "tiled")]
+	pub fn from_tiled(
+		map_length: u32,
+		map_depth: u32,
+		sector_resolution: u32,
+		actor_size: f32,
+		file_path: &str,
+		costs: &TiledCosts,
+	) -> Self {
+		let map_dimensions =
+			MapDimensions::new(map_length, map_depth, sector_resolution, actor_size);
+		let cost_fields =
+			SectorCostFields::from_tiled(&map_dimensions, file_path.to_string(), costs);
+		let mut portals = SectorPortals::new(map_length, map_depth, sector_resolution);
+		// update default portals for cost fields
+		for sector_id in cost_fields.get_scaled().keys() {
+			portals.update_portals(*sector_id, &cost_fields, &map_dimensions);
+		}
+		let graph = PortalGraph::new(&portals, &cost_fields, &map_dimensions);
+		let route_cache = RouteCache::default();
+		let cache = FlowFieldCache::default();
+		FlowFieldTilesBundle {
+			sector_cost_fields: cost_fields,
+			sector_portals: portals,
+			portal_graph: graph,
+			map_dimensions,
+			route_cache,
+			flow_field_cache: cache,
+			sector_cost_modifiers: SectorCostModifiers::default(),
+			#[cfg(feature = "3d")]
+			elevation_field: ElevationField::default(),
+		}
+	}
+	/// From an IntGrid layer of an [LDtk](https://ldtk.io/) project initialise a bundle where each cell represents a [FieldCell] and `costs` determines the [CostField] value of each IntGrid value. An [LdtkError] is returned if the project can't be read or doesn't match the size of the map
+	#[cfg(not(tarpaulin_include))]
+	#[cfg(feature = "ldtk")]
+	pub fn from_ldtk(
+		map_length: u32,
+		map_depth: u32,
+		sector_resolution: u32,
+		actor_size: f32,
+		file_path: &str,
+		costs: &LdtkCosts,
+	) -> Result<Self, LdtkError> {
+		let map_dimensions =
+			MapDimensions::new(map_length, map_depth, sector_resolution, actor_size);
+		let cost_fields =
+			SectorCostFields::from_ldtk(&map_dimensions, file_path.to_string(), costs)?;
+		let mut portals = SectorPortals::new(map_length, map_depth, sector_resolution);
+		// update default portals for cost fields
+		for sector_id in cost_fields.get_scaled().keys() {
+			portals.update_portals(*sector_id, &cost_fields, &map_dimensions);
+		}
+		let graph = PortalGraph::new(&portals, &cost_fields, &map_dimensions);
+		let route_cache = RouteCache::default();
+		let cache = FlowFieldCache::default();
+		Ok(FlowFieldTilesBundle {
+			sector_cost_fields: cost_fields,
+			sector_portals: portals,
+			portal_graph: graph,
+			map_dimensions,
+			route_cache,
+			flow_field_cache: cache,
+			sector_cost_modifiers: SectorCostModifiers::default(),
+			#[cfg(feature = "3d")]
+			elevation_field: ElevationField::default(),
+		})
+	}
+	/// From a list of 3d meshes and their [Transform] initialise a bundle where the geometry is rasterised onto the `x-z` grid of [FieldCell]s and `settings` determine which cells can be walked on
+	#[cfg(not(tarpaulin_include))]
+	#[cfg(feature = "3d")]
+	pub fn from_bevy_3d_meshes(
+		meshes: &[(&Mesh, Transform)],
+		map_length: u32,
+		map_depth: u32,
+		sector_resolution: u32,
+		actor_size: f32,
+		settings: &MeshCostSettings,
+	) -> Self {
+		let map_dimensions =
+			MapDimensions::new(map_length, map_depth, sector_resolution, actor_size);
+		let cost_fields = SectorCostFields::from_bevy_3d_meshes(&map_dimensions, meshes, settings);
+		let mut portals = SectorPortals::new(map_length, map_depth, sector_resolution);
+		// update default portals for cost fields
+		for sector_id in cost_fields.get_scaled().keys() {
+			portals.update_portals(*sector_id, &cost_fields, &map_dimensions);
+		}
+		let graph = PortalGraph::new(&portals, &cost_fields, &map_dimensions);
+		let route_cache = RouteCache::default();
+		let cache = FlowFieldCache::default();
+		FlowFieldTilesBundle {
+			sector_cost_fields: cost_fields,
+			sector_portals: portals,
+			portal_graph: graph,
+			map_dimensions,
+			route_cache,
+			flow_field_cache: cache,
+			sector_cost_modifiers: SectorCostModifiers::default(),
+			#[cfg(feature = "3d")]
+			elevation_field: ElevationField::default(),
+		}
+	}
+	/// Initialise a bundle from the height of 3d terrain where the [SectorCostFields] are derived from the slope between neighbouring [FieldCell]s by `costs` and the [ElevationField] is kept so that positions and directions follow the terrain
+	#[cfg(not(tarpaulin_include))]
+	#[cfg(feature = "3d")]
+	pub fn from_elevation(
+		map_length: u32,
+		map_depth: u32,
+		sector_resolution: u32,
+		actor_size: f32,
+		elevation_field: ElevationField,
+		costs: &SlopeCosts,
+	) -> Self {
+		let map_dimensions =
+			MapDimensions::new(map_length, map_depth, sector_resolution, actor_size);
+		let cost_fields =
+			SectorCostFields::from_elevation(&map_dimensions, &elevation_field, costs);
+		let mut portals = SectorPortals::new(map_length, map_depth, sector_resolution);
+		// update default portals for cost fields
+		for sector_id in cost_fields.get_scaled().keys() {
+			portals.update_portals(*sector_id, &cost_fields, &map_dimensions);
+		}
+		let graph = PortalGraph::new(&portals, &cost_fields, &map_dimensions);
+		let route_cache = RouteCache::default();
+		let cache = FlowFieldCache::default();
+		FlowFieldTilesBundle {
+			sector_cost_fields: cost_fields,
+			sector_portals: portals,
+			portal_graph: graph,
+			map_dimensions,
+			route_cache,
+			flow_field_cache: cache,
+			sector_cost_modifiers: SectorCostModifiers::default(),
+			elevation_field,
 		}
 	}
 	/// From a list of 2d meshes and their translation initialise a bundle. The vertex points of the meshes must be within the `map_length` and `map_depth` of the world.
@@ -216,6 +515,9 @@ impl FlowFieldTilesBundle {
 			map_dimensions,
 			route_cache,
 			flow_field_cache: cache,
+			sector_cost_modifiers: SectorCostModifiers::default(),
+			#[cfg(feature = "3d")]
+			elevation_field: ElevationField::default(),
 		}
 	}
 }
@@ -42,7 +42,10 @@
 //!
 
 use bevy::reflect::Reflect;
-use std::collections::HashSet;
+use std::{
+	cmp::Reverse,
+	collections::{BinaryHeap, HashMap, HashSet},
+};
 
 use crate::prelude::*;
 
@@ -79,6 +82,30 @@ impl CostField {
 	pub fn new_with_cost(cost: u8) -> Self {
 		CostField([[cost; FIELD_RESOLUTION]; FIELD_RESOLUTION])
 	}
+	/// Compare `self` with `other` and return every [FieldCell] whose value differs
+	pub fn get_changed_cells(&self, other: &CostField) -> Vec<FieldCell> {
+		let mut cells = Vec::new();
+		for (column, (a, b)) in self.0.iter().zip(other.0.iter()).enumerate() {
+			for row in 0..FIELD_RESOLUTION {
+				if a[row] != b[row] {
+					cells.push(FieldCell::new(column, row));
+				}
+			}
+		}
+		cells
+	}
+	/// Compare `self` with `other` and return every [FieldCell] which is cheaper in `other`
+	pub fn get_lowered_cells(&self, other: &CostField) -> Vec<FieldCell> {
+		let mut cells = Vec::new();
+		for (column, (a, b)) in self.0.iter().zip(other.0.iter()).enumerate() {
+			for row in 0..FIELD_RESOLUTION {
+				if b[row] < a[row] {
+					cells.push(FieldCell::new(column, row));
+				}
+			}
+		}
+		cells
+	}
 	/// Tests whether two cells can see each other within a sector (one might be boxed in by impassable cost field values)
 	pub fn is_cell_pair_reachable(&self, source: FieldCell, target: FieldCell) -> bool {
 		// instance of corner cells overlapping
@@ -104,6 +131,81 @@ impl CostField {
 		let visited = HashSet::new();
 		process_neighbours_distance(target, queue, visited, self, vec![0])
 	}
+	/// Find the cheapest series of [FieldCell]s leading from `source` to `target` within the sector, the returned path includes both the `source` and `target`. Movement is permitted orthogonally and diagonally, a diagonal step is only allowed if it does not cut the corner of an impassable cell. Returns [None] if the `target` cannot be reached
+	pub fn find_cell_path(&self, source: FieldCell, target: FieldCell) -> Option<Vec<FieldCell>> {
+		if self.get_field_cell_value(source) == 255 || self.get_field_cell_value(target) == 255 {
+			return None;
+		}
+		if source == target {
+			return Some(vec![source]);
+		}
+		// orthogonal steps cost 10x the cell cost, diagonal steps 14x (approx. 10 * sqrt(2))
+		let heuristic = |cell: FieldCell| -> u32 {
+			let dx = cell.get_column().abs_diff(target.get_column()) as u32;
+			let dy = cell.get_row().abs_diff(target.get_row()) as u32;
+			10 * dx.max(dy) + 4 * dx.min(dy)
+		};
+		let mut came_from: HashMap<FieldCell, FieldCell> = HashMap::new();
+		let mut best_costs: HashMap<FieldCell, u32> = HashMap::new();
+		best_costs.insert(source, 0);
+		// of form (estimated total, cost so far, cell), ordered by smallest estimate first
+		let mut queue = BinaryHeap::new();
+		queue.push(Reverse((heuristic(source), 0, source)));
+		while let Some(Reverse((_, cost_so_far, cell))) = queue.pop() {
+			if cell == target {
+				// walk backwards to construct the path
+				let mut path = vec![target];
+				let mut current = target;
+				while let Some(previous) = came_from.get(&current) {
+					path.push(*previous);
+					current = *previous;
+				}
+				path.reverse();
+				return Some(path);
+			}
+			// a better route to this cell has already been processed
+			if cost_so_far > *best_costs.get(&cell).unwrap_or(&u32::MAX) {
+				continue;
+			}
+			for neighbour in Ordinal::get_all_cell_neighbours(cell) {
+				let neighbour_cost = self.get_field_cell_value(neighbour);
+				if neighbour_cost == 255 {
+					continue;
+				}
+				let is_diagonal = neighbour.get_column() != cell.get_column()
+					&& neighbour.get_row() != cell.get_row();
+				if is_diagonal {
+					// prevent squeezing between two impassable cells
+					let corner_a = FieldCell::new(neighbour.get_column(), cell.get_row());
+					let corner_b = FieldCell::new(cell.get_column(), neighbour.get_row());
+					if self.get_field_cell_value(corner_a) == 255
+						|| self.get_field_cell_value(corner_b) == 255
+					{
+						continue;
+					}
+				}
+				let step = if is_diagonal { 14 } else { 10 };
+				let cost = cost_so_far + step * neighbour_cost as u32;
+				if cost < *best_costs.get(&neighbour).unwrap_or(&u32::MAX) {
+					best_costs.insert(neighbour, cost);
+					came_from.insert(neighbour, cell);
+					queue.push(Reverse((cost + heuristic(neighbour), cost, neighbour)));
+				}
+			}
+		}
+		None
+	}
+	/// Measure how expensive a `path` from [CostField::find_cell_path] is to follow, each orthogonal step costs 10x the cost of the cell moved onto and each diagonal step 14x
+	pub fn get_cell_path_cost(&self, path: &[FieldCell]) -> u32 {
+		let mut cost = 0;
+		for pair in path.windows(2) {
+			let is_diagonal = pair[0].get_column() != pair[1].get_column()
+				&& pair[0].get_row() != pair[1].get_row();
+			let step = if is_diagonal { 14 } else { 10 };
+			cost += step * self.get_field_cell_value(pair[1]) as u32;
+		}
+		cost
+	}
 	/// From a `ron` file generate the [CostField]
 	#[cfg(feature = "ron")]
 	pub fn from_ron(path: String) -> Self {
@@ -307,4 +409,86 @@ mod tests {
 		let result = cost_field.get_distance_between_cells(&source, &target);
 		assert!(result.is_none())
 	}
+	#[test]
+	fn changed_cells() {
+		let original = CostField::default();
+		assert!(original.get_changed_cells(&CostField::default()).is_empty());
+		let mut edited = CostField::default();
+		edited.set_field_cell_value(255, FieldCell::new(9, 0));
+		edited.set_field_cell_value(7, FieldCell::new(4, 4));
+		let actual = vec![FieldCell::new(4, 4), FieldCell::new(9, 0)];
+		assert_eq!(actual, original.get_changed_cells(&edited));
+	}
+	#[test]
+	fn lowered_cells() {
+		let mut original = CostField::default();
+		original.set_field_cell_value(255, FieldCell::new(2, 3));
+		original.set_field_cell_value(50, FieldCell::new(6, 6));
+		let mut edited = original.clone();
+		edited.set_field_cell_value(1, FieldCell::new(2, 3));
+		edited.set_field_cell_value(255, FieldCell::new(6, 6));
+		edited.set_field_cell_value(7, FieldCell::new(4, 4));
+		let actual = vec![FieldCell::new(2, 3)];
+		assert_eq!(actual, original.get_lowered_cells(&edited));
+	}
+	#[test]
+	fn cell_path_cost() {
+		let mut cost_field = CostField::default();
+		cost_field.set_field_cell_value(5, FieldCell::new(1, 0));
+		let path = vec![
+			FieldCell::new(0, 0),
+			FieldCell::new(1, 0),
+			FieldCell::new(2, 1),
+		];
+		assert_eq!(10 * 5 + 14, cost_field.get_cell_path_cost(&path));
+		assert_eq!(0, cost_field.get_cell_path_cost(&path[..1]));
+	}
+	#[test]
+	fn cell_path_around_wall() {
+		//  _____________________________
+		// |__|__|__|__|__|__|__|__|__|__|
+		// |__|__|__|__|__|__|__|__|__|__|
+		// |__|__|__|__|__|__|__|__|__|__|
+		// |__|__|__|__|__|__|__|__|__|__|
+		// |__|__|__|__|__|x_|__|__|__|__|
+		// |__|__|__|__|__|x_|__|__|__|__|
+		// |__|__|__|__|__|x_|__|__|__|__|
+		// |__|__|__|__|__|x_|__|__|__|__|
+		// |__|__|__|__|__|x_|__|__|__|__|
+		// |__|__|__|__|S_|x_|T_|__|__|__|
+		let mut cost_field = CostField::default();
+		for row in 4..10 {
+			cost_field.set_field_cell_value(255, FieldCell::new(5, row));
+		}
+		let source = FieldCell::new(4, 9);
+		let target = FieldCell::new(6, 9);
+
+		let result = cost_field.find_cell_path(source, target).unwrap();
+		assert_eq!(source, result[0]);
+		assert_eq!(target, *result.last().unwrap());
+		// climbs the wall to row 3 and back down
+		assert!(result.contains(&FieldCell::new(5, 3)));
+		for cell in result.iter() {
+			assert_ne!(255, cost_field.get_field_cell_value(*cell));
+		}
+		// every step moves to an adjacent cell
+		for pair in result.windows(2) {
+			assert!(pair[0].get_column().abs_diff(pair[1].get_column()) <= 1);
+			assert!(pair[0].get_row().abs_diff(pair[1].get_row()) <= 1);
+		}
+	}
+	#[test]
+	fn cell_path_none() {
+		let mut cost_field = CostField::default();
+		cost_field.set_field_cell_value(255, FieldCell::new(5, 9));
+		cost_field.set_field_cell_value(255, FieldCell::new(5, 8));
+		cost_field.set_field_cell_value(255, FieldCell::new(6, 8));
+		cost_field.set_field_cell_value(255, FieldCell::new(7, 8));
+		cost_field.set_field_cell_value(255, FieldCell::new(7, 9));
+		let source = FieldCell::new(0, 4);
+		let target = FieldCell::new(6, 9);
+
+		let result = cost_field.find_cell_path(source, target);
+		assert!(result.is_none())
+	}
 }
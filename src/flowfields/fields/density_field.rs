@@ -0,0 +1,96 @@
+//! The DensityField contains a 2D array of 8-bit values describing how congested each cell of a sector is. Unlike a [CostField] it is a dynamic overlay, it is accumulated from the positions of actors and decays over time, and it is added on top of the [CostField] when an [IntegrationField] is calculated.
+//!
+//! This encourages actors to spread across parallel corridors rather than all queueing through the single cheapest one. As it only influences the [IntegrationField] a change in density never causes the Portals or the [PortalGraph] to be rebuilt.
+//!
+
+use bevy::reflect::Reflect;
+
+use crate::prelude::*;
+
+/// Dynamic congestion cost of each cell in a sector
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Reflect)]
+pub struct DensityField([[u8; FIELD_RESOLUTION]; FIELD_RESOLUTION]);
+
+impl Field<u8> for DensityField {
+	/// Get a reference to the field array
+	fn get(&self) -> &[[u8; FIELD_RESOLUTION]; FIELD_RESOLUTION] {
+		&self.0
+	}
+	/// Retrieve a field cell value
+	///
+	/// NB: This will panic if out of bounds
+	fn get_field_cell_value(&self, field_cell: FieldCell) -> u8 {
+		self.0[field_cell.get_column()][field_cell.get_row()]
+	}
+	/// Set a field cell to a value
+	///
+	/// NB: This will panic if out of bounds
+	fn set_field_cell_value(&mut self, value: u8, field_cell: FieldCell) {
+		self.0[field_cell.get_column()][field_cell.get_row()] = value;
+	}
+}
+
+impl DensityField {
+	/// Increase the density of a field cell by `amount` without exceeding `max`
+	pub fn add_density(&mut self, field_cell: FieldCell, amount: u8, max: u8) {
+		let value = self.get_field_cell_value(field_cell);
+		self.set_field_cell_value(value.saturating_add(amount).min(max), field_cell);
+	}
+	/// Reduce the density of every field cell by `amount`
+	pub fn decay(&mut self, amount: u8) {
+		for column in self.0.iter_mut() {
+			for value in column.iter_mut() {
+				*value = value.saturating_sub(amount);
+			}
+		}
+	}
+	/// Whether every field cell has no density
+	pub fn is_empty(&self) -> bool {
+		self.0.iter().all(|column| column.iter().all(|v| *v == 0))
+	}
+	/// Get the largest difference in density of any field cell between this and `other`
+	pub fn get_max_difference(&self, other: &DensityField) -> u8 {
+		let mut max = 0;
+		for (column, other_column) in self.0.iter().zip(other.0.iter()) {
+			for (value, other_value) in column.iter().zip(other_column.iter()) {
+				max = max.max(value.abs_diff(*other_value));
+			}
+		}
+		max
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn add_density_capped() {
+		let mut density_field = DensityField::default();
+		let field_cell = FieldCell::new(2, 3);
+		density_field.add_density(field_cell, 6, 10);
+		density_field.add_density(field_cell, 6, 10);
+		let result = density_field.get_field_cell_value(field_cell);
+		assert_eq!(10, result);
+	}
+	#[test]
+	fn decay_to_empty() {
+		let mut density_field = DensityField::default();
+		density_field.add_density(FieldCell::new(2, 3), 6, 10);
+		density_field.add_density(FieldCell::new(7, 1), 2, 10);
+		density_field.decay(3);
+		assert_eq!(3, density_field.get_field_cell_value(FieldCell::new(2, 3)));
+		assert_eq!(0, density_field.get_field_cell_value(FieldCell::new(7, 1)));
+		density_field.decay(3);
+		assert!(density_field.is_empty());
+	}
+	#[test]
+	fn max_difference() {
+		let mut density_field = DensityField::default();
+		density_field.add_density(FieldCell::new(2, 3), 6, 10);
+		let mut other = DensityField::default();
+		other.add_density(FieldCell::new(2, 3), 2, 10);
+		other.add_density(FieldCell::new(7, 1), 3, 10);
+		assert_eq!(4, density_field.get_max_difference(&other));
+	}
+}
@@ -116,7 +116,21 @@ impl FlowField {
 			self.set_field_cell_value(goal_value, goals[0]);
 			self.calculate_line_of_sight(goals, integration_field);
 		}
-
+		self.calculate_directions(integration_field);
+	}
+	/// Calculate the [FlowField] of a sector whose route continues onto another layer through a [LayerConnection] at the `goals`. The goals have no direction of their own as the connection (i.e stairs or an elevator) is what moves an actor onto the next layer, see [is_layer_connection_goal]
+	pub fn calculate_to_layer_connection(
+		&mut self,
+		goals: &[FieldCell],
+		integration_field: &IntegrationField,
+	) {
+		for goal in goals.iter() {
+			self.set_field_cell_value(BITS_PORTAL_GOAL | BITS_PATHABLE, *goal);
+		}
+		self.calculate_directions(integration_field);
+	}
+	/// Point every cell which hasn't been set yet towards its cheapest neighbour in the [IntegrationField]
+	fn calculate_directions(&mut self, integration_field: &IntegrationField) {
 		for (i, column) in integration_field.get().iter().enumerate() {
 			for (j, _row) in column.iter().enumerate() {
 				let field_cell = FieldCell::new(i, j);
@@ -163,6 +177,15 @@ impl FlowField {
 			}
 		}
 	}
+	/// Could a change to the cost of any of `field_cells` alter this [FlowField]. A cell which was reachable from the goals, or is orthogonally adjacent to one which was, can change the [IntegrationField] the field was built from. A cell sealed off from the goals (i.e behind a wall) cannot
+	pub fn is_affected_by(&self, field_cells: &[FieldCell]) -> bool {
+		field_cells.iter().any(|cell| {
+			self.get_field_cell_value(*cell) != BITS_ZERO
+				|| Ordinal::get_orthogonal_cell_neighbours(*cell)
+					.iter()
+					.any(|n| self.get_field_cell_value(*n) != BITS_ZERO)
+		})
+	}
 
 	// /// Calculate the [FlowField] from an [IntegrationField], additionally for a sector in a chain of sectors along a path this will peak into the previous sectors [IntegrationField] to apply a directional optimisation to this sector's [FlowField]
 	// pub fn calculate_arc(
@@ -649,6 +672,11 @@ pub fn is_portal_goal(cell_value: u8) -> bool {
 	cell_value & BITS_PORTAL_GOAL == BITS_PORTAL_GOAL
 }
 
+/// Indicates that a cell is the end of a [LayerConnection] which leads onto the next layer of the route. It's up to the actor to cross the connection, i.e climb the stairs or ride the elevator, before following the [FlowField] of the sector on the other layer
+pub fn is_layer_connection_goal(cell_value: u8) -> bool {
+	cell_value & (BITS_PORTAL_GOAL | BITS_DEFAULT) == BITS_PORTAL_GOAL
+}
+
 /// If a cell has direct vision to the goal then the [FlowField] should be
 /// disregarded as the actor can move in a stright line to the goal
 pub fn has_line_of_sight(cell_value: u8) -> bool {
@@ -715,6 +743,26 @@ pub fn get_3d_direction_unit_vector_from_bits(cell_value: u8) -> Vec3 {
 	}
 }
 
+/// Reading the directional bits of a [FlowField] field cell within `sector` obtain a unit vector in 3d space which follows the slope of the terrain described by an [ElevationField]. The rise of the vector comes from the height one cell ahead of the centre of `field` in the direction of the bits, so an actor climbing a hill is pointed up it rather than into it. A flat or empty [ElevationField] produces the same direction as [get_3d_direction_unit_vector_from_bits] normalised to unit length
+#[cfg(feature = "3d")]
+pub fn get_3d_sloped_direction_unit_vector_from_bits(
+	cell_value: u8,
+	sector: SectorID,
+	field: FieldCell,
+	elevation: &ElevationField,
+	map_dimensions: &MapDimensions,
+) -> Vec3 {
+	let flat = get_3d_direction_unit_vector_from_bits(cell_value);
+	let Some(centre) = elevation.get_xyz_from_field_sector(map_dimensions, sector, field) else {
+		return flat.normalize_or_zero();
+	};
+	if flat == Vec3::ZERO {
+		return flat;
+	}
+	let step = flat * map_dimensions.get_field_cell_unit_size();
+	let rise = elevation.get_height_at_xyz(map_dimensions, centre + step) - centre.y;
+	Vec3::new(step.x, rise, step.z).normalize()
+}
 // #[rustfmt::skip]
 #[cfg(test)]
 mod tests {
@@ -803,4 +851,79 @@ mod tests {
 			}
 		}
 	}
+	#[test]
+	fn affected_by_reachable_cells_only() {
+		// wall along column 5 sealing off the eastern half from the goal
+		let mut cost_field = CostField::default();
+		for row in 0..FIELD_RESOLUTION {
+			cost_field.set_field_cell_value(255, FieldCell::new(5, row));
+		}
+		let goals = vec![FieldCell::new(0, 0)];
+		let mut integration_field = IntegrationField::new(&goals);
+		integration_field.calculate_field(&goals, &cost_field);
+		let mut flow_field = FlowField::default();
+		flow_field.calculate(&goals, None, &integration_field);
+		assert!(flow_field.is_affected_by(&[FieldCell::new(2, 7)]));
+		// part of the wall bordering the reachable side
+		assert!(flow_field.is_affected_by(&[FieldCell::new(5, 3)]));
+		assert!(!flow_field.is_affected_by(&[FieldCell::new(7, 3), FieldCell::new(9, 9)]));
+	}
+	#[test]
+	#[cfg(feature = "3d")]
+	fn sloped_direction() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		// rising one unit per cell towards the east
+		let elevation = ElevationField::from_map_cells(&map_dimensions, |column, _| column as f32);
+		let sector = SectorID::new(0, 0);
+		let field = FieldCell::new(5, 5);
+		let up_hill = get_3d_sloped_direction_unit_vector_from_bits(
+			BITS_EAST,
+			sector,
+			field,
+			&elevation,
+			&map_dimensions,
+		);
+		assert!((Vec3::new(1.0, 1.0, 0.0).normalize() - up_hill).length() < 0.001);
+		let down_hill = get_3d_sloped_direction_unit_vector_from_bits(
+			BITS_WEST,
+			sector,
+			field,
+			&elevation,
+			&map_dimensions,
+		);
+		assert!((Vec3::new(-1.0, -1.0, 0.0).normalize() - down_hill).length() < 0.001);
+		let across = get_3d_sloped_direction_unit_vector_from_bits(
+			BITS_NORTH,
+			sector,
+			field,
+			&elevation,
+			&map_dimensions,
+		);
+		assert_eq!(Vec3::new(0.0, 0.0, -1.0), across);
+		let flat = get_3d_sloped_direction_unit_vector_from_bits(
+			BITS_NORTH_EAST,
+			sector,
+			field,
+			&ElevationField::default(),
+			&map_dimensions,
+		);
+		assert!((Vec3::new(1.0, 0.0, -1.0).normalize() - flat).length() < 0.001);
+	}
+	#[test]
+	fn flow_to_layer_connection() {
+		let cost_field = CostField::default();
+		let goals = vec![FieldCell::new(5, 5)];
+		let mut integration_field = IntegrationField::new(&goals);
+		integration_field.calculate_field(&goals, &cost_field);
+		let mut flow_field = FlowField::default();
+		flow_field.calculate_to_layer_connection(&goals, &integration_field);
+		let goal = flow_field.get_field_cell_value(FieldCell::new(5, 5));
+		assert!(is_layer_connection_goal(goal));
+		assert!(is_portal_goal(goal));
+		assert!(!has_line_of_sight(goal));
+		let west = flow_field.get_field_cell_value(FieldCell::new(4, 5));
+		assert_eq!(BITS_PATHABLE | BITS_EAST, west);
+		assert!(!is_layer_connection_goal(west));
+		assert!(!is_layer_connection_goal(BITS_PORTAL_GOAL | BITS_SOUTH));
+	}
 }
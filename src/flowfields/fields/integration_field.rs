@@ -106,16 +106,30 @@ impl IntegrationField {
 		for goal in goals.iter() {
 			queue.push(((*goal), self.get_field_cell_value(*goal)));
 		}
-		process_neighbours(self, queue, cost_field);
+		process_neighbours(self, queue, cost_field, None);
+	}
+	/// Calculate the field values in the same manner as [IntegrationField::calculate_field] but additionally adding the dynamic cost of a [DensityField] on top of the `cost_field`. This allows congested areas to become more expensive without modifying the [CostField] itself
+	pub fn calculate_field_with_density(
+		&mut self,
+		goals: &[FieldCell],
+		cost_field: &CostField,
+		density_field: &DensityField,
+	) {
+		let mut queue: Vec<(FieldCell, u16)> = Vec::new();
+		for goal in goals.iter() {
+			queue.push(((*goal), self.get_field_cell_value(*goal)));
+		}
+		process_neighbours(self, queue, cost_field, Some(density_field));
 	}
 }
 
 /// Recursively expand the neighbours of a list of [FieldCell] and calculate
-/// their value in the [IntegrationField]
+/// their value in the [IntegrationField], optionally adding the cost of a [DensityField]
 fn process_neighbours(
 	int_field: &mut IntegrationField,
 	queue: Vec<(FieldCell, u16)>,
 	cost_field: &CostField,
+	density_field: Option<&DensityField>,
 ) {
 	let mut next_neighbours = Vec::new();
 	// iterate over the queue calculating neighbour int costs
@@ -126,8 +140,10 @@ fn process_neighbours(
 			let cell_cost = cost_field.get_field_cell_value(*n);
 			// ignore impassable cells
 			if cell_cost != 255 {
+				let density_cost =
+					density_field.map_or(0, |density| density.get_field_cell_value(*n) as u16);
 				// don't overwrite an int cell with a better cost
-				let int_cost = cell_cost as u16 + prev_int_cost;
+				let int_cost = (cell_cost as u16 + density_cost).saturating_add(*prev_int_cost);
 				if int_cost < int_field.get_field_cell_value(*n) {
 					int_field.set_field_cell_value(int_cost, *n);
 					next_neighbours.push((*n, int_cost));
@@ -136,7 +152,7 @@ fn process_neighbours(
 		}
 	}
 	if !next_neighbours.is_empty() {
-		process_neighbours(int_field, next_neighbours, cost_field);
+		process_neighbours(int_field, next_neighbours, cost_field, density_field);
 	}
 }
 
@@ -190,4 +206,22 @@ mod tests {
 		];
 		assert_eq!(actual, *result);
 	}
+	/// Congestion along the direct route pushes the wavefront around it
+	#[test]
+	fn density_field() {
+		let cost_field = CostField::default();
+		let mut density_field = DensityField::default();
+		for row in 0..FIELD_RESOLUTION {
+			density_field.set_field_cell_value(10, FieldCell::new(5, row));
+		}
+		let mut integration_field = IntegrationField::default();
+		let source = vec![FieldCell::new(4, 4)];
+		integration_field.reset(&source);
+		integration_field.calculate_field_with_density(&source, &cost_field, &density_field);
+		// crossing the congested column costs the base cost plus the density
+		assert_eq!(12, integration_field.get_field_cell_value(FieldCell::new(6, 4)));
+		assert_eq!(11, integration_field.get_field_cell_value(FieldCell::new(5, 4)));
+		// unaffected cells match the basic field
+		assert_eq!(4, integration_field.get_field_cell_value(FieldCell::new(0, 4)));
+	}
 }
@@ -2,10 +2,14 @@
 //!
 
 pub mod cost_field;
+pub mod density_field;
 pub mod flow_field;
 pub mod integration_field;
 
-use std::collections::BTreeMap;
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	sync::atomic::{AtomicU64, Ordering},
+};
 
 use crate::prelude::*;
 use bevy::prelude::*;
@@ -43,6 +47,13 @@ impl FieldCell {
 	pub fn get_row(&self) -> usize {
 		self.0 .1
 	}
+	/// Does the cell sit along a boundary of the field
+	pub fn is_on_boundary(&self) -> bool {
+		self.get_row() == 0
+			|| self.get_column() == FIELD_RESOLUTION - 1
+			|| self.get_row() == FIELD_RESOLUTION - 1
+			|| self.get_column() == 0
+	}
 	/// From the position of a `cell_id`, if it sits along a boundary, return the [Ordinal] of that boundary. Note that if the `cell_id` is in a field corner then it'll have two boundaries. Note that if the `cell_id` is not in fact along a boundary then this will panic
 	pub fn get_boundary_ordinal_from_field_cell(&self) -> Vec<Ordinal> {
 		let mut boundaries = Vec::new();
@@ -240,8 +251,10 @@ pub struct RouteMetadata {
 	//? If a game is running for 136 years bad things will start happening here
 	/// Marks the route based on time elapsed since app start, used to enable automatic cleardown of long lived routes that are probably not needed anymore
 	time_generated: Duration,
+	/// Urgency of the route, higher priorities are calculated first
+	priority: u8,
 }
-// we don't want to compare `time_generated` so manually impl PartialEq
+// we don't want to compare `time_generated` or `priority` so manually impl PartialEq
 impl PartialEq for RouteMetadata {
 	fn eq(&self, other: &Self) -> bool {
 		self.source_sector == other.source_sector
@@ -290,8 +303,14 @@ impl RouteMetadata {
 			target_sector,
 			target_goal,
 			time_generated,
+			priority: 0,
 		}
 	}
+	/// Set the priority of the route, higher priorities are calculated first
+	pub fn with_priority(mut self, priority: u8) -> Self {
+		self.priority = priority;
+		self
+	}
 	/// Get the source sector
 	pub fn get_source_sector(&self) -> SectorID {
 		self.source_sector
@@ -312,17 +331,83 @@ impl RouteMetadata {
 	pub fn get_time_generated(&self) -> Duration {
 		self.time_generated
 	}
+	/// Get the priority of the route
+	pub fn get_priority(&self) -> u8 {
+		self.priority
+	}
+	/// Key that queues are serviced in, the highest priority first and then the oldest
+	fn get_queue_order(&self) -> (std::cmp::Reverse<u8>, Duration) {
+		(std::cmp::Reverse(self.priority), self.time_generated)
+	}
+}
+/// Source of unique [RouteHandle]s
+static NEXT_ROUTE_HANDLE: AtomicU64 = AtomicU64::new(0);
+
+/// Stable identifier of a requested route. Unlike [RouteMetadata], which records when a route was generated, a handle keeps referring to the same route when it is regenerated after a [CostField] change so an actor holding one always finds the current route with [RouteCache::get_route_by_handle]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Reflect)]
+pub struct RouteHandle(u64);
+
+impl RouteHandle {
+	/// Create a new unique handle
+	pub fn new() -> Self {
+		RouteHandle(NEXT_ROUTE_HANDLE.fetch_add(1, Ordering::Relaxed))
+	}
+	/// Get the raw ID of the handle
+	pub fn get(&self) -> u64 {
+		self.0
+	}
+}
+
+impl Default for RouteHandle {
+	fn default() -> Self {
+		RouteHandle::new()
+	}
 }
+
 /// Each key makes use of custom Ord and Eq implementations based on comparing `(source_id, target_id, goal_id)` so that RouteMetaData can be used to refer to the high-level route an actor has asked for. The value is a list of `(sector_id, goal_id)` referring to the sector-portal (or just the end goal) route. An actor can use this as a fallback if the `field_cache` doesn't yet contain the granular [FlowField] routes or for when [CostField]s have been changed and so [FlowField]s in the cache need to be regenerated
 #[derive(Component, Default, Clone)]
 pub struct RouteCache {
+	/// Requested routes waiting for a high-level route to be calculated
+	requests: Vec<RouteMetadata>,
 	/// A queue of high-level routes which get processed into the `routes` field
 	route_queue: BTreeMap<RouteMetadata, Vec<(SectorID, FieldCell)>>,
 	/// High-level routes describing the path from an actor to an end goal
 	routes: BTreeMap<RouteMetadata, Vec<(SectorID, FieldCell)>>,
+	/// The route each [RouteHandle] refers to, several handles can refer to the same route
+	handles: BTreeMap<RouteHandle, RouteMetadata>,
+	/// The [RouteHandle]s referring to each route
+	route_handles: BTreeMap<RouteMetadata, BTreeSet<RouteHandle>>,
+	/// The entity which requested the route of a [RouteHandle], if it was given one
+	requesters: BTreeMap<RouteHandle, Entity>,
 }
 
 impl RouteCache {
+	/// Get the requests waiting for a high-level route to be calculated
+	pub fn get_requests(&self) -> &Vec<RouteMetadata> {
+		&self.requests
+	}
+	/// Record a request for a route and make `handle` refer to it. If the same route is already waiting to be calculated the requests are merged, keeping the age of the original request and the highest priority of the two
+	pub fn add_request(&mut self, handle: RouteHandle, route_metadata: RouteMetadata) {
+		self.insert_handle(handle, route_metadata);
+		if let Some(existing) = self.requests.iter_mut().find(|r| **r == route_metadata) {
+			existing.priority = existing.priority.max(route_metadata.priority);
+		} else {
+			self.requests.push(route_metadata);
+		}
+	}
+	/// Is a route waiting to be calculated, either as a request or in the queue of high-level routes
+	pub fn is_pending(&self, route_metadata: &RouteMetadata) -> bool {
+		self.requests.contains(route_metadata) || self.route_queue.contains_key(route_metadata)
+	}
+	/// Remove and return the request that should be calculated next, the highest priority request is picked and requests of equal priority are picked oldest first
+	pub fn pop_next_request(&mut self) -> Option<RouteMetadata> {
+		let (index, _) = self
+			.requests
+			.iter()
+			.enumerate()
+			.min_by_key(|(_, r)| r.get_queue_order())?;
+		Some(self.requests.remove(index))
+	}
 	/// Get a refernce to the map of queued routes
 	pub fn get_queue(&self) -> &BTreeMap<RouteMetadata, Vec<(SectorID, FieldCell)>> {
 		&self.route_queue
@@ -353,6 +438,7 @@ impl RouteCache {
 			target_sector,
 			target_goal: goal_id,
 			time_generated: Duration::default(),
+			priority: 0,
 		};
 		let route = self.routes.get(&route_data);
 		trace!("Route: {:?}", route);
@@ -372,6 +458,7 @@ impl RouteCache {
 			target_sector,
 			target_goal: goal_id,
 			time_generated: Duration::default(),
+			priority: 0,
 		};
 		let route = self.routes.get_key_value(&route_data);
 		trace!("Route: {:?}", route);
@@ -393,9 +480,18 @@ impl RouteCache {
 			target_sector,
 			target_goal: goal_id,
 			time_generated: elapsed_duration,
+			priority: 0,
 		};
 		self.route_queue.insert(route_data, route);
 	}
+	/// Insert a high-level route of sector-portal paths (or just the end goal if local sector pathing) into the queue with an already created [RouteMetadata] structure
+	pub fn add_to_queue_with_metadata(
+		&mut self,
+		route_metadata: RouteMetadata,
+		route: Vec<(SectorID, FieldCell)>,
+	) {
+		self.route_queue.insert(route_metadata, route);
+	}
 	/// Insert a high-level route of sector-portal paths (or just the end goal if local sector pathing) into the `route_cache`
 	pub fn insert_route(
 		&mut self,
@@ -412,6 +508,7 @@ impl RouteCache {
 			target_sector,
 			target_goal: goal_id,
 			time_generated: elapsed_duration,
+			priority: 0,
 		};
 		self.routes.insert(route_data, route);
 	}
@@ -423,10 +520,105 @@ impl RouteCache {
 	) {
 		self.routes.insert(route_metadata, route);
 	}
-	/// Remove a high-level  route of sector-portal paths (or just the end goal if local sector pathing) from the `route_cache`
+	/// Remove a high-level  route of sector-portal paths (or just the end goal if local sector pathing) from the `route_cache`. Any [RouteHandle]s referring to the route are kept so that they find the route again if it is regenerated
 	pub fn remove_route(&mut self, route_metadata: RouteMetadata) {
 		self.routes.remove(&route_metadata);
 	}
+	/// Get a high-level route by its [RouteHandle]. Returns [None] if the handle is unknown or the route isn't currently generated, i.e it is being regenerated after a [CostField] change
+	pub fn get_route_by_handle(
+		&self,
+		handle: RouteHandle,
+	) -> Option<(&RouteMetadata, &Vec<(SectorID, FieldCell)>)> {
+		let route_data = self.handles.get(&handle)?;
+		self.routes.get_key_value(route_data)
+	}
+	/// Get the [RouteMetadata] that a [RouteHandle] refers to, regardless of whether the route is currently generated
+	pub fn get_handle_metadata(&self, handle: RouteHandle) -> Option<&RouteMetadata> {
+		self.handles.get(&handle)
+	}
+	/// Get every [RouteHandle] referring to a route
+	pub fn get_handles(&self, route_metadata: &RouteMetadata) -> Vec<RouteHandle> {
+		self.route_handles
+			.get(route_metadata)
+			.map(|handles| handles.iter().copied().collect())
+			.unwrap_or_default()
+	}
+	/// Make a [RouteHandle] refer to the route described by `route_metadata`
+	pub fn insert_handle(&mut self, handle: RouteHandle, route_metadata: RouteMetadata) {
+		if let Some(previous) = self.handles.insert(handle, route_metadata) {
+			self.remove_from_route_handles(handle, &previous);
+		}
+		self.route_handles
+			.entry(route_metadata)
+			.or_default()
+			.insert(handle);
+	}
+	/// Stop tracking a [RouteHandle], i.e once an actor no longer needs its route
+	pub fn remove_handle(&mut self, handle: RouteHandle) {
+		if let Some(route_metadata) = self.handles.remove(&handle) {
+			self.remove_from_route_handles(handle, &route_metadata);
+		}
+		self.requesters.remove(&handle);
+	}
+	/// Remove every [RouteHandle] referring to a route
+	pub fn remove_handles(&mut self, route_metadata: &RouteMetadata) {
+		let handles = self
+			.route_handles
+			.remove(route_metadata)
+			.unwrap_or_default();
+		for handle in handles {
+			self.handles.remove(&handle);
+			self.requesters.remove(&handle);
+		}
+	}
+	/// Remove `handle` from the set of handles referring to a route, dropping the set once it is empty
+	fn remove_from_route_handles(&mut self, handle: RouteHandle, route_metadata: &RouteMetadata) {
+		if let Some(handles) = self.route_handles.get_mut(route_metadata) {
+			handles.remove(&handle);
+			if handles.is_empty() {
+				self.route_handles.remove(route_metadata);
+			}
+		}
+	}
+	/// Record the entity which requested the route of a [RouteHandle]
+	pub fn insert_requester(&mut self, handle: RouteHandle, requester: Entity) {
+		self.requesters.insert(handle, requester);
+	}
+	/// Get the entity which requested the route of a [RouteHandle]
+	pub fn get_requester(&self, handle: RouteHandle) -> Option<Entity> {
+		self.requesters.get(&handle).copied()
+	}
+	/// Get every [RouteHandle] requested by an entity
+	pub fn get_requester_handles(&self, requester: Entity) -> Vec<RouteHandle> {
+		self.requesters
+			.iter()
+			.filter(|(_, entity)| **entity == requester)
+			.map(|(handle, _)| *handle)
+			.collect()
+	}
+	/// Get the entity which requested each [RouteHandle], handles without a requester are omitted
+	pub fn get_requesters(&self) -> &BTreeMap<RouteHandle, Entity> {
+		&self.requesters
+	}
+	/// Is the route referred to by a [RouteHandle] with a requester. Such routes are kept until the requester cancels them or despawns rather than expiring with age
+	pub fn has_requester(&self, route_metadata: &RouteMetadata) -> bool {
+		self.route_handles
+			.get(route_metadata)
+			.is_some_and(|handles| handles.iter().any(|h| self.requesters.contains_key(h)))
+	}
+	/// Cancel the request a [RouteHandle] refers to. The handle is removed and if no other handle refers to the route it is also removed from the requests and the queue of high-level routes.
+	///
+	/// Returns the [RouteMetadata] of the route when nothing refers to it anymore so that any fields still queued for it can be discarded, otherwise [None]
+	pub fn cancel_request(&mut self, handle: RouteHandle) -> Option<RouteMetadata> {
+		let route_metadata = *self.handles.get(&handle)?;
+		self.remove_handle(handle);
+		if self.route_handles.contains_key(&route_metadata) {
+			return None;
+		}
+		self.requests.retain(|r| *r != route_metadata);
+		self.route_queue.remove(&route_metadata);
+		Some(route_metadata)
+	}
 	/// Remove a high-level route that has been queued (or just the end goal if
 	/// local sector pathing)
 	pub fn remove_queued_route(&mut self, route_metadata: RouteMetadata) {
@@ -516,6 +708,10 @@ impl IntegrationBuilder {
 	) {
 		self.integration_fields = Some(fields);
 	}
+	/// Discard any built [IntegrationField]s so that they are calculated again, i.e after a [CostField] along the path has changed
+	pub fn reset_integration_fields(&mut self) {
+		self.integration_fields = None;
+	}
 }
 
 /// Each generated [FlowField] is placed into this cache so that multiple actors can read from the same dataset.
@@ -528,6 +724,8 @@ pub struct FlowFieldCache {
 	queue: BTreeMap<RouteMetadata, IntegrationBuilder>,
 	/// Created FlowFields that actors can use to pathfind
 	flows: BTreeMap<FlowFieldMetadata, FlowField>,
+	/// The routes making use of each [FlowField], a field is freed once the last of them is removed
+	users: BTreeMap<FlowFieldMetadata, BTreeSet<RouteMetadata>>,
 }
 
 impl FlowFieldCache {
@@ -539,9 +737,20 @@ impl FlowFieldCache {
 	pub fn get_mut(&mut self) -> &mut BTreeMap<FlowFieldMetadata, FlowField> {
 		&mut self.flows
 	}
+	/// Get the queue of routes whose fields are being built
+	pub fn get_queue(&self) -> &BTreeMap<RouteMetadata, IntegrationBuilder> {
+		&self.queue
+	}
+	/// Get a mutable reference to the queue of routes whose fields are being built
 	pub fn get_queue_mut(&mut self) -> &mut BTreeMap<RouteMetadata, IntegrationBuilder> {
 		&mut self.queue
 	}
+	/// Get the queued routes in the order they should be serviced, the highest priority first and then the oldest
+	pub fn get_queue_by_priority(&self) -> Vec<RouteMetadata> {
+		let mut queued: Vec<RouteMetadata> = self.queue.keys().copied().collect();
+		queued.sort_by_key(|r| r.get_queue_order());
+		queued
+	}
 	pub fn add_to_queue(&mut self, metadata: RouteMetadata, path: Vec<(SectorID, FieldCell)>) {
 		let int_builder = IntegrationBuilder::new(path);
 		self.queue.insert(metadata, int_builder);
@@ -555,6 +764,19 @@ impl FlowFieldCache {
 		};
 		self.flows.get(&flow_meta)
 	}
+	/// Get a mutable reference to a [FlowField] based on the `sector_id` and `goal_id`, i.e to update it in place. Returns [None] if the cache doesn't contain a record
+	pub fn get_field_mut(
+		&mut self,
+		sector_id: SectorID,
+		goal_id: FieldCell,
+	) -> Option<&mut FlowField> {
+		let flow_meta = FlowFieldMetadata {
+			sector_id,
+			goal_id,
+			time_generated: Duration::default(),
+		};
+		self.flows.get_mut(&flow_meta)
+	}
 	/// Insert a [FlowField] into the cache with a sector-goal ID
 	pub fn insert_field(
 		&mut self,
@@ -574,6 +796,55 @@ impl FlowFieldCache {
 	/// [CostField] update)
 	pub fn remove_field(&mut self, flow_meta: FlowFieldMetadata) {
 		self.flows.remove(&flow_meta);
+		self.users.remove(&flow_meta);
+	}
+	/// Record that a route makes use of the [FlowField] of `sector_id` and `goal_id`
+	pub fn add_field_user(
+		&mut self,
+		sector_id: SectorID,
+		goal_id: FieldCell,
+		route_metadata: RouteMetadata,
+	) {
+		let flow_meta = FlowFieldMetadata {
+			sector_id,
+			goal_id,
+			time_generated: Duration::default(),
+		};
+		self.users
+			.entry(flow_meta)
+			.or_default()
+			.insert(route_metadata);
+	}
+	/// Get the routes making use of the [FlowField] of `sector_id` and `goal_id`
+	pub fn get_field_users(&self, sector_id: SectorID, goal_id: FieldCell) -> Vec<RouteMetadata> {
+		let flow_meta = FlowFieldMetadata {
+			sector_id,
+			goal_id,
+			time_generated: Duration::default(),
+		};
+		self.users
+			.get(&flow_meta)
+			.map(|users| users.iter().copied().collect())
+			.unwrap_or_default()
+	}
+	/// Is the [FlowField] used by any route
+	pub fn is_field_used(&self, flow_meta: &FlowFieldMetadata) -> bool {
+		self.users.contains_key(flow_meta)
+	}
+	/// Stop a route from making use of any [FlowField]s. Fields that are left without any users are removed from the cache and returned
+	pub fn remove_route_user(&mut self, route_metadata: RouteMetadata) -> Vec<FlowFieldMetadata> {
+		let mut freed = Vec::new();
+		self.users.retain(|flow_meta, users| {
+			users.remove(&route_metadata);
+			if users.is_empty() {
+				freed.push(*flow_meta);
+			}
+			!users.is_empty()
+		});
+		for flow_meta in freed.iter() {
+			self.flows.remove(flow_meta);
+		}
+		freed
 	}
 	/// Remove a [RouteMetadata] from the cache integratino queue (when it
 	/// needs regenerating from a [CostField] update)
@@ -586,6 +857,160 @@ impl FlowFieldCache {
 mod tests {
 	use super::*;
 	#[test]
+	fn route_handle_kept_when_route_removed() {
+		let mut route_cache = RouteCache::default();
+		let metadata = RouteMetadata::new(
+			SectorID::new(0, 0),
+			FieldCell::new(1, 1),
+			SectorID::new(1, 0),
+			FieldCell::new(2, 2),
+			Duration::default(),
+		);
+		let route = vec![(SectorID::new(0, 0), FieldCell::new(9, 4))];
+		let handle = RouteHandle::new();
+		route_cache.insert_handle(handle, metadata);
+		assert!(route_cache.get_route_by_handle(handle).is_none());
+		route_cache.insert_route_with_metadata(metadata, route.clone());
+		assert_eq!(&route, route_cache.get_route_by_handle(handle).unwrap().1);
+		route_cache.remove_route(metadata);
+		assert!(route_cache.get_route_by_handle(handle).is_none());
+		assert_eq!(vec![handle], route_cache.get_handles(&metadata));
+		// regenerated at a later time
+		let later = RouteMetadata::new(
+			SectorID::new(0, 0),
+			FieldCell::new(1, 1),
+			SectorID::new(1, 0),
+			FieldCell::new(2, 2),
+			Duration::from_secs(5),
+		);
+		route_cache.insert_route_with_metadata(later, route.clone());
+		let (found, _) = route_cache.get_route_by_handle(handle).unwrap();
+		assert_eq!(Duration::from_secs(5), found.get_time_generated());
+		route_cache.remove_handles(&metadata);
+		assert!(route_cache.get_handle_metadata(handle).is_none());
+	}
+	#[test]
+	fn requests_by_priority_then_age() {
+		let mut route_cache = RouteCache::default();
+		let request = |column: usize, secs: u64, priority: u8| {
+			RouteMetadata::new(
+				SectorID::new(0, 0),
+				FieldCell::new(column, 0),
+				SectorID::new(1, 0),
+				FieldCell::new(2, 2),
+				Duration::from_secs(secs),
+			)
+			.with_priority(priority)
+		};
+		let ambient_old = request(0, 1, 0);
+		let ambient_new = request(1, 2, 0);
+		let player = request(2, 3, 5);
+		route_cache.add_request(RouteHandle::new(), ambient_new);
+		route_cache.add_request(RouteHandle::new(), ambient_old);
+		route_cache.add_request(RouteHandle::new(), player);
+		// a later duplicate of an ambient request raises its priority but keeps its age
+		let handle = RouteHandle::new();
+		route_cache.add_request(handle, request(1, 4, 5));
+		assert_eq!(3, route_cache.get_requests().len());
+		assert_eq!(Some(&ambient_new), route_cache.get_handle_metadata(handle));
+		let next = route_cache.pop_next_request().unwrap();
+		assert_eq!(ambient_new, next);
+		assert_eq!(5, next.get_priority());
+		assert_eq!(Duration::from_secs(2), next.get_time_generated());
+		assert_eq!(Some(player), route_cache.pop_next_request());
+		assert_eq!(Some(ambient_old), route_cache.pop_next_request());
+		assert_eq!(None, route_cache.pop_next_request());
+	}
+	#[test]
+	fn cancel_request_kept_while_shared() {
+		let mut route_cache = RouteCache::default();
+		let metadata = RouteMetadata::new(
+			SectorID::new(0, 0),
+			FieldCell::new(1, 1),
+			SectorID::new(1, 0),
+			FieldCell::new(2, 2),
+			Duration::default(),
+		);
+		let requester = Entity::from_raw(7);
+		let first = RouteHandle::new();
+		let second = RouteHandle::new();
+		route_cache.add_request(first, metadata);
+		route_cache.insert_requester(first, requester);
+		route_cache.add_request(second, metadata);
+		assert_eq!(vec![first], route_cache.get_requester_handles(requester));
+		// another handle still wants the route
+		assert_eq!(None, route_cache.cancel_request(first));
+		assert_eq!(1, route_cache.get_requests().len());
+		assert!(route_cache.get_requester(first).is_none());
+		assert_eq!(Some(metadata), route_cache.cancel_request(second));
+		assert!(route_cache.get_requests().is_empty());
+		// already cancelled
+		assert_eq!(None, route_cache.cancel_request(second));
+	}
+	#[test]
+	fn handle_index_follows_reassignment() {
+		let mut route_cache = RouteCache::default();
+		let route = |column: usize| {
+			RouteMetadata::new(
+				SectorID::new(0, 0),
+				FieldCell::new(column, 1),
+				SectorID::new(1, 0),
+				FieldCell::new(2, 2),
+				Duration::default(),
+			)
+		};
+		let (first, second) = (route(0), route(1));
+		let handle = RouteHandle::new();
+		route_cache.insert_handle(handle, first);
+		route_cache.insert_requester(handle, Entity::from_raw(3));
+		assert!(route_cache.has_requester(&first));
+		route_cache.insert_handle(handle, second);
+		assert!(route_cache.get_handles(&first).is_empty());
+		assert_eq!(vec![handle], route_cache.get_handles(&second));
+		assert!(!route_cache.has_requester(&first));
+		assert!(route_cache.has_requester(&second));
+		route_cache.remove_handle(handle);
+		assert!(route_cache.get_handles(&second).is_empty());
+		assert!(!route_cache.has_requester(&second));
+	}
+	#[test]
+	fn shared_field_freed_with_last_user() {
+		let mut flow_cache = FlowFieldCache::default();
+		let route = |column: u32| {
+			RouteMetadata::new(
+				SectorID::new(column, 0),
+				FieldCell::new(1, 1),
+				SectorID::new(1, 0),
+				FieldCell::new(2, 2),
+				Duration::default(),
+			)
+		};
+		let (first, second) = (route(0), route(2));
+		let shared = (SectorID::new(1, 0), FieldCell::new(2, 2));
+		let only_first = (SectorID::new(0, 0), FieldCell::new(9, 4));
+		for (sector_id, goal_id) in [shared, only_first] {
+			flow_cache.insert_field(
+				sector_id,
+				goal_id,
+				Duration::default(),
+				FlowField::default(),
+			);
+			flow_cache.add_field_user(sector_id, goal_id, first);
+		}
+		flow_cache.add_field_user(shared.0, shared.1, second);
+		assert_eq!(
+			vec![first, second],
+			flow_cache.get_field_users(shared.0, shared.1)
+		);
+		let freed = flow_cache.remove_route_user(first);
+		assert_eq!(1, freed.len());
+		assert_eq!(only_first.0, freed[0].get_sector_id());
+		assert!(flow_cache.get_field(only_first.0, only_first.1).is_none());
+		assert!(flow_cache.get_field(shared.0, shared.1).is_some());
+		flow_cache.remove_route_user(second);
+		assert!(flow_cache.get().is_empty());
+	}
+	#[test]
 	fn field_cell_line_horizontal() {
 		let source = FieldCell::new(3, 4);
 		let target = FieldCell::new(7, 4);
@@ -126,6 +126,65 @@ impl std::hash::Hash for Edge {
 	}
 }
 
+/// An explicit link between a [FieldCell] of a sector on one layer and a [FieldCell] of a sector on another layer, such as a flight of stairs, a ramp or an elevator. Connections are added to the [PortalGraph] as edges so that routes can move between layers
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayerConnection {
+	/// Sector and [FieldCell] at one end of the connection
+	from: (SectorID, FieldCell),
+	/// Sector and [FieldCell] at the other end of the connection
+	to: (SectorID, FieldCell),
+	/// How expensive it is to travel across the connection, i.e the length of a flight of stairs
+	cost: i32,
+	/// Whether the connection can be travelled from `to` back to `from`
+	bidirectional: bool,
+}
+
+impl LayerConnection {
+	/// Create a connection that can be travelled in both directions between `from` and `to`, `cost` is how expensive it is to travel across. Panics if both ends are on the same layer, sectors of the same layer are already joined by their portals
+	pub fn new(from: (SectorID, FieldCell), to: (SectorID, FieldCell), cost: i32) -> Self {
+		if from.0.get_layer() == to.0.get_layer() {
+			panic!(
+				"A LayerConnection must join two different layers, both {:?} and {:?} are on layer {}",
+				from.0,
+				to.0,
+				from.0.get_layer()
+			);
+		}
+		LayerConnection {
+			from,
+			to,
+			cost,
+			bidirectional: true,
+		}
+	}
+	/// Set whether the connection can be travelled from `to` back to `from`, i.e `false` for a drop down from a ledge
+	pub fn with_bidirectional(mut self, bidirectional: bool) -> Self {
+		self.bidirectional = bidirectional;
+		self
+	}
+	/// Get the sector and [FieldCell] at the start of the connection
+	pub fn get_from(&self) -> (SectorID, FieldCell) {
+		self.from
+	}
+	/// Get the sector and [FieldCell] at the end of the connection
+	pub fn get_to(&self) -> (SectorID, FieldCell) {
+		self.to
+	}
+	/// Get how expensive it is to travel across the connection
+	pub fn get_cost(&self) -> i32 {
+		self.cost
+	}
+	/// Can the connection be travelled from `to` back to `from`
+	pub fn is_bidirectional(&self) -> bool {
+		self.bidirectional
+	}
+	/// Does either end of the connection sit within `sector_id`
+	pub fn is_in_sector(&self, sector_id: &SectorID) -> bool {
+		self.from.0 == *sector_id || self.to.0 == *sector_id
+	}
+}
+
 /// The graph represents all the [Portals] across sectors in a [Node] notation. Each [Node] is then associated with `edges_internal` and `edges_external` which define routes to travel between [Portals].
 ///
 /// The graph can be queried to find the best path of [Portals] from one sector to another
@@ -139,6 +198,9 @@ pub struct PortalGraph {
 	edges_internal: HashSet<Edge>,
 	/// A pair of [Node]s that indicate that a [Node] within the current sector can allow passage to another [Node] in a different sector
 	edges_external: HashSet<Edge>,
+	/// Links between sectors on different layers, each end is a [Node] sitting on [Ordinal::Zero] and the link itself is a pair of external [Edge]s
+	#[cfg_attr(feature = "serde", serde(default))]
+	connections: Vec<LayerConnection>,
 }
 // interface methods to the graph
 impl PortalGraph {
@@ -205,12 +267,127 @@ impl PortalGraph {
 		sector_cost_fields: &SectorCostFields,
 		map_dimensions: &MapDimensions,
 	) -> Self {
-		let mut graph = PortalGraph::default();
+		PortalGraph::new_with_connections(
+			sector_portals,
+			sector_cost_fields,
+			map_dimensions,
+			Vec::new(),
+		)
+	}
+	/// Create a new instance of [PortalGraph] from sector data where sectors on different layers are joined by `connections`
+	pub fn new_with_connections(
+		sector_portals: &SectorPortals,
+		sector_cost_fields: &SectorCostFields,
+		map_dimensions: &MapDimensions,
+		connections: Vec<LayerConnection>,
+	) -> Self {
+		let mut graph = PortalGraph {
+			connections,
+			..default()
+		};
 		graph.create_all_nodes(sector_portals, sector_cost_fields);
+		graph.create_all_connections(sector_cost_fields);
 		graph.create_all_internal_edges(sector_portals, sector_cost_fields);
 		graph.create_all_external_edges(sector_portals, sector_cost_fields, map_dimensions);
 		graph
 	}
+	/// Get the [LayerConnection]s joining sectors on different layers
+	pub fn get_layer_connections(&self) -> &Vec<LayerConnection> {
+		&self.connections
+	}
+	/// Join two sectors on different layers with a [LayerConnection], the [Node]s at either end are linked to the portals of their sectors
+	pub fn add_layer_connection(
+		&mut self,
+		connection: LayerConnection,
+		sector_portals: &SectorPortals,
+		sector_cost_fields: &SectorCostFields,
+	) -> &mut Self {
+		self.connections.push(connection);
+		self.create_connection(&connection, sector_cost_fields);
+		for sector_id in [connection.get_from().0, connection.get_to().0] {
+			if let (Some(cost_field), Some(portals)) = (
+				sector_cost_fields.get_scaled().get(&sector_id),
+				sector_portals.get().get(&sector_id),
+			) {
+				self.create_sector_internal_edges(&sector_id, cost_field, portals);
+			}
+		}
+		self
+	}
+	/// Create the [Node]s and [Edge]s of every [LayerConnection]
+	fn create_all_connections(&mut self, sector_cost_fields: &SectorCostFields) {
+		for connection in self.connections.clone().iter() {
+			self.create_connection(connection, sector_cost_fields);
+		}
+	}
+	/// Create the [Node]s and [Edge]s of every [LayerConnection] with an end in `sector_id`
+	fn create_sector_connections(
+		&mut self,
+		sector_id: &SectorID,
+		sector_cost_fields: &SectorCostFields,
+	) {
+		for connection in self.connections.clone().iter() {
+			if connection.is_in_sector(sector_id) {
+				self.create_connection(connection, sector_cost_fields);
+			}
+		}
+	}
+	/// Remove the [Node]s of any [LayerConnection] ending in `sector_id`, along with all of their [Edge]s
+	fn remove_sector_connections(&mut self, sector_id: &SectorID) {
+		let nodes_to_remove: Vec<Node> = self
+			.get_nodes()
+			.iter()
+			.filter(|n| n.is_in_sector(sector_id) && *n.get_side() == Ordinal::Zero)
+			.cloned()
+			.collect();
+		for n in nodes_to_remove.iter() {
+			self.remove_node(n);
+		}
+	}
+	/// Add a [Node] for each end of a `connection` and the external [Edge]s between them. Connections to a sector which doesn't exist are ignored
+	fn create_connection(
+		&mut self,
+		connection: &LayerConnection,
+		sector_cost_fields: &SectorCostFields,
+	) {
+		let (from_sector, from_cell) = connection.get_from();
+		let (to_sector, to_cell) = connection.get_to();
+		let (Some(from_field), Some(to_field)) = (
+			sector_cost_fields.get_scaled().get(&from_sector),
+			sector_cost_fields.get_scaled().get(&to_sector),
+		) else {
+			warn!(
+				"LayerConnection between {:?} and {:?} refers to a sector without a CostField",
+				from_sector, to_sector
+			);
+			return;
+		};
+		let from_weight = from_field.get_field_cell_value(from_cell);
+		let to_weight = to_field.get_field_cell_value(to_cell);
+		if from_weight == 255 || to_weight == 255 {
+			return;
+		}
+		let from_node = Node::new(from_sector, from_cell, from_weight, Ordinal::Zero);
+		let to_node = Node::new(to_sector, to_cell, to_weight, Ordinal::Zero);
+		self.add_node(from_node);
+		self.add_node(to_node);
+		self.add_edge_external(Edge::new(from_node, to_node, connection.get_cost()));
+		if connection.is_bidirectional() {
+			self.add_edge_external(Edge::new(to_node, from_node, connection.get_cost()));
+		}
+	}
+	/// Get the [FieldCell]s within `sector_id` that are an end of a [LayerConnection]
+	fn get_connection_cells(&self, sector_id: &SectorID) -> Vec<FieldCell> {
+		let mut cells = Vec::new();
+		for connection in self.connections.iter() {
+			for (sector, cell) in [connection.get_from(), connection.get_to()] {
+				if sector == *sector_id && !cells.contains(&cell) {
+					cells.push(cell);
+				}
+			}
+		}
+		cells
+	}
 	/// Add nodes for all sectors to the [PortalGraph]
 	fn create_all_nodes(
 		&mut self,
@@ -268,18 +445,22 @@ impl PortalGraph {
 		let mut cells = vec![];
 		for ord in ords.iter() {
 			for cell in portals.get(ord).iter() {
-				cells.push((cell, ord));
+				cells.push((*cell, *ord));
 			}
 		}
+		// the ends of any layer connections act as portals too
+		for cell in self.get_connection_cells(sector_id) {
+			cells.push((cell, Ordinal::Zero));
+		}
 		for (i, (source, ord_source)) in cells.iter().enumerate() {
 			for (j, (target, ord_target)) in cells.iter().enumerate() {
 				if i != j {
 					if let Some(distance) = cost_field.get_distance_between_cells(source, target) {
 						// create the edge
-						let s_weight = cost_field.get_field_cell_value(**source);
-						let source_node = Node::new(*sector_id, **source, s_weight, **ord_source);
-						let t_weight = cost_field.get_field_cell_value(**target);
-						let target_node = Node::new(*sector_id, **target, t_weight, **ord_target);
+						let s_weight = cost_field.get_field_cell_value(*source);
+						let source_node = Node::new(*sector_id, *source, s_weight, *ord_source);
+						let t_weight = cost_field.get_field_cell_value(*target);
+						let target_node = Node::new(*sector_id, *target, t_weight, *ord_target);
 						let edge = Edge::new(source_node, target_node, distance);
 						self.add_edge_internal(edge);
 					}
@@ -389,6 +570,8 @@ impl PortalGraph {
 		// create new nodes in changed sector
 		let portals = sector_portals.get().get(&changed_sector).unwrap();
 		self.create_sector_nodes(sector_cost_fields, &changed_sector, portals);
+		// the connection nodes of the changed sector were removed with it
+		self.create_sector_connections(&changed_sector, sector_cost_fields);
 		// create nodes in the neighbouring sectors
 		//TODO lets not rebuild all, on 3 sides of neighbours they should be exactly as they are
 		for (_ord, sector) in sectors_to_rebuild.iter() {
@@ -431,6 +614,74 @@ impl PortalGraph {
 		}
 		self
 	}
+	/// Update the graph after only some portal boundaries have been recalculated (see [SectorPortals::update_portals_on_boundaries]). The nodes and external edges along each `boundaries` side are rebuilt and the internal edges are rebuilt for any sector which either has a rebuilt side or is listed in `changed_sectors`, as a change to the interior of a sector can change how its portals connect. All other nodes and edges are left untouched.
+	///
+	/// # This must run after any updates to a [Portals]!
+	pub fn update_graph_boundaries(
+		&mut self,
+		changed_sectors: &[SectorID],
+		boundaries: &[(SectorID, Ordinal)],
+		sector_portals: &SectorPortals,
+		sector_cost_fields: &SectorCostFields,
+		map_dimensions: &MapDimensions,
+	) -> &mut Self {
+		// remove the nodes along each boundary side, this also removes their edges
+		let nodes_to_remove: Vec<Node> = self
+			.get_nodes()
+			.iter()
+			.filter(|n| boundaries.contains(&(*n.get_sector(), *n.get_side())))
+			.cloned()
+			.collect();
+		for n in nodes_to_remove.iter() {
+			self.remove_node(n);
+		}
+		// sectors whose internal edges need rebuilding
+		let mut sectors: Vec<SectorID> = changed_sectors.to_vec();
+		for (sector_id, _) in boundaries.iter() {
+			if !sectors.contains(sector_id) {
+				sectors.push(*sector_id);
+			}
+		}
+		self.edges_internal
+			.retain(|edge| !sectors.contains(edge.get_from().get_sector()));
+		// the cost of a connection cell may have changed so rebuild the connections too
+		for sector_id in sectors.iter() {
+			self.remove_sector_connections(sector_id);
+			self.create_sector_connections(sector_id, sector_cost_fields);
+		}
+		// create new nodes along each boundary side
+		for (sector_id, side) in boundaries.iter() {
+			let portals = sector_portals.get().get(sector_id).unwrap();
+			let cost_field = sector_cost_fields.get_scaled().get(sector_id).unwrap();
+			for cell in portals.get(side).iter() {
+				let weight = cost_field.get_field_cell_value(*cell);
+				self.add_node(Node::new(*sector_id, *cell, weight, *side));
+			}
+		}
+		// recreate internal edges
+		for sector_id in sectors.iter() {
+			let cost_field = sector_cost_fields.get_scaled().get(sector_id).unwrap();
+			let portals = sector_portals.get().get(sector_id).unwrap();
+			self.create_sector_internal_edges(sector_id, cost_field, portals);
+		}
+		// recreate external edges across each boundary side, the neighbouring side is also in `boundaries` so both directions are covered
+		for (sector_id, side) in boundaries.iter() {
+			let neighbours: Vec<(Ordinal, SectorID)> = map_dimensions
+				.get_ordinal_and_ids_of_neighbouring_sectors(sector_id)
+				.into_iter()
+				.filter(|(ord, _)| ord == side)
+				.collect();
+			let portals = sector_portals.get().get(sector_id).unwrap();
+			self.create_sector_external_edges(
+				sector_portals,
+				sector_cost_fields,
+				sector_id,
+				portals,
+				&neighbours,
+			);
+		}
+		self
+	}
 }
 
 /// An edge between [PortalNode]s comes in two varieties.
@@ -488,6 +739,14 @@ impl PortalGraph {
 				}
 			}
 		}
+		// the ends of layer connections within the sector
+		if let Some(cost_field) = sector_cost_fields.get_scaled().get(&source_sector_id) {
+			for cell in self.get_connection_cells(&source_sector_id) {
+				if cost_field.is_cell_pair_reachable(source_field_cell, cell) {
+					source_portals.push((cell, Ordinal::Zero));
+				}
+			}
+		}
 		// find portals that can reach the target/goal
 		let target_sector_id = target.0;
 		let target_field_cell = target.1;
@@ -510,6 +769,14 @@ impl PortalGraph {
 				}
 			}
 		}
+		// the ends of layer connections within the sector
+		if let Some(cost_field) = sector_cost_fields.get_scaled().get(&target_sector_id) {
+			for cell in self.get_connection_cells(&target_sector_id) {
+				if cost_field.is_cell_pair_reachable(target_field_cell, cell) {
+					target_portals.push((cell, Ordinal::Zero));
+				}
+			}
+		}
 		// iterate over the source and target portals to find a series of paths
 		let mut paths = Vec::new();
 		for (source_portal, source_ordinal) in source_portals.iter() {
@@ -544,6 +811,46 @@ impl PortalGraph {
 		}
 		best_path
 	}
+	/// Find a cell-by-cell path from a `source` to a `target`. The sequence of portals from [PortalGraph::find_best_path] is stitched together by searching for a cell path across each sector between the portal an actor enters by and the portal it exits from. If the `source` and `target` share a sector and can reach each other directly then the portals are ignored entirely.
+	///
+	/// The returned path begins with the `source` and ends with the `target`
+	pub fn find_best_cell_path(
+		&self,
+		source: (SectorID, FieldCell),
+		target: (SectorID, FieldCell),
+		sector_portals: &SectorPortals,
+		sector_cost_fields: &SectorCostFields,
+	) -> Option<Vec<(SectorID, FieldCell)>> {
+		if source.0 == target.0 {
+			let cost_field = sector_cost_fields.get_scaled().get(&source.0)?;
+			if let Some(cells) = cost_field.find_cell_path(source.1, target.1) {
+				return Some(cells.into_iter().map(|cell| (source.0, cell)).collect());
+			}
+		}
+		let portal_path =
+			self.find_best_path(source, target, sector_portals, sector_cost_fields)?;
+		// the portal path is made of pairs of portals, an exit portal from one sector and the
+		// entry portal of the next sector, so walk the pairs appending a path across each sector
+		let mut path = Vec::new();
+		let mut current = source;
+		for pair in portal_path.chunks(2) {
+			let exit = pair[0];
+			let cost_field = sector_cost_fields.get_scaled().get(&current.0)?;
+			let cells = cost_field.find_cell_path(current.1, exit.1)?;
+			path.extend(cells.into_iter().map(|cell| (current.0, cell)));
+			if let Some(entry) = pair.get(1) {
+				current = *entry;
+			} else {
+				// an unpaired portal is searched from again below so avoid duplicating it
+				current = exit;
+				path.pop();
+			}
+		}
+		let cost_field = sector_cost_fields.get_scaled().get(&current.0)?;
+		let cells = cost_field.find_cell_path(current.1, target.1)?;
+		path.extend(cells.into_iter().map(|cell| (current.0, cell)));
+		Some(path)
+	}
 	/// Find a path from a source [Node] to a target [Node] if it
 	/// exists and return the path with a weighting of how expensive it is
 	fn find_path_between_sector_portals(
@@ -743,6 +1050,96 @@ use super::*;
 	// |         P         P         |
 	// |         |         |         |
 	// |_________|_________|_________|
+	/// Describe every node and edge of a graph, including weights and distances which are ignored by equality of [Node] and [Edge]
+	fn describe_graph(graph: &PortalGraph) -> (Vec<String>, Vec<String>, Vec<String>) {
+		let mut nodes: Vec<String> = graph.get_nodes().iter().map(|n| format!("{:?}", n)).collect();
+		nodes.sort();
+		let mut internal: Vec<String> = graph
+			.get_edges_internal()
+			.iter()
+			.map(|e| format!("{:?}", e))
+			.collect();
+		internal.sort();
+		let mut external: Vec<String> = graph
+			.get_edges_external()
+			.iter()
+			.map(|e| format!("{:?}", e))
+			.collect();
+		external.sort();
+		(nodes, internal, external)
+	}
+	#[test]
+	fn update_graph_boundaries_matches_rebuild() {
+		let map_dimensions = MapDimensions::new(40, 40, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut sector_portals = SectorPortals::new(
+			map_dimensions.get_length(),
+			map_dimensions.get_depth(),
+			map_dimensions.get_sector_resolution(),
+		);
+		for sector_id in sector_cost_fields.get_scaled().keys() {
+			sector_portals.update_portals(*sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		let mut graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		// interior only change
+		let interior = vec![(SectorID::new(2, 1), FieldCell::new(4, 4), 255)];
+		let edited = sector_cost_fields.set_baseline_field_cell_values(&interior);
+		let changed = sector_cost_fields.rescale_costfields(&edited, &map_dimensions);
+		assert_eq!(vec![SectorID::new(2, 1)], changed.keys().cloned().collect::<Vec<_>>());
+		let boundaries = sector_portals.update_portals_on_boundaries(
+			&changed,
+			&sector_cost_fields,
+			&map_dimensions,
+		);
+		assert!(boundaries.is_empty());
+		// wall touching the northern edge and a cell on the eastern edge
+		let mut edits = Vec::new();
+		for row in 0..9 {
+			edits.push((SectorID::new(1, 1), FieldCell::new(5, row), 255));
+		}
+		edits.push((SectorID::new(2, 2), FieldCell::new(9, 5), 255));
+		edits.push((SectorID::new(0, 3), FieldCell::new(3, 3), 40));
+		let edited = sector_cost_fields.set_baseline_field_cell_values(&edits);
+		let changed = sector_cost_fields.rescale_costfields(&edited, &map_dimensions);
+		let boundaries = sector_portals.update_portals_on_boundaries(
+			&changed,
+			&sector_cost_fields,
+			&map_dimensions,
+		);
+		let actual = vec![
+			(SectorID::new(1, 1), Ordinal::North),
+			(SectorID::new(1, 0), Ordinal::South),
+			(SectorID::new(2, 2), Ordinal::East),
+			(SectorID::new(3, 2), Ordinal::West),
+		];
+		assert_eq!(actual, boundaries);
+		let mut changed_sectors: Vec<SectorID> = changed.keys().cloned().collect();
+		changed_sectors.push(SectorID::new(2, 1));
+		graph.update_graph_boundaries(
+			&changed_sectors,
+			&boundaries,
+			&sector_portals,
+			&sector_cost_fields,
+			&map_dimensions,
+		);
+		// compare with building everything from scratch
+		let mut rebuilt_portals = SectorPortals::new(
+			map_dimensions.get_length(),
+			map_dimensions.get_depth(),
+			map_dimensions.get_sector_resolution(),
+		);
+		for sector_id in sector_cost_fields.get_scaled().keys() {
+			rebuilt_portals.update_portals(*sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		for (sector_id, portals) in rebuilt_portals.get().iter() {
+			assert_eq!(
+				format!("{:?}", portals),
+				format!("{:?}", sector_portals.get().get(sector_id).unwrap())
+			);
+		}
+		let rebuilt = PortalGraph::new(&rebuilt_portals, &sector_cost_fields, &map_dimensions);
+		assert_eq!(describe_graph(&rebuilt), describe_graph(&graph));
+	}
 	#[test]
 	fn node_count_default() {
 		//init
@@ -1044,4 +1441,91 @@ use super::*;
 		
 		assert_eq!(actual, path.1);
 	}
+	#[test]
+	fn best_cell_path_across_sectors() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut sector_portals = SectorPortals::new(map_dimensions.get_length(), map_dimensions.get_depth(), map_dimensions.get_sector_resolution());
+		for (sector_id, _cost_fields) in sector_cost_fields.get_scaled().iter() {
+			let portals = sector_portals.get_mut();
+			match portals.get_mut(sector_id) {
+				Some(portals) => portals.recalculate_portals(&sector_cost_fields, sector_id, &map_dimensions),
+				None => panic!("Key {:?} not found in Portals", sector_id),
+			}
+		}
+		let graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+
+		let source = (SectorID::new(0, 0), FieldCell::new(2, 2));
+		let target = (SectorID::new(2, 0), FieldCell::new(7, 2));
+		let path = graph.find_best_cell_path(source, target, &sector_portals, &sector_cost_fields).unwrap();
+		assert_eq!(source, path[0]);
+		assert_eq!(target, *path.last().unwrap());
+		// every step is to an adjacent cell when measured across the whole map
+		for pair in path.windows(2) {
+			let a_column = pair[0].0.get_column() as usize * FIELD_RESOLUTION + pair[0].1.get_column();
+			let a_row = pair[0].0.get_row() as usize * FIELD_RESOLUTION + pair[0].1.get_row();
+			let b_column = pair[1].0.get_column() as usize * FIELD_RESOLUTION + pair[1].1.get_column();
+			let b_row = pair[1].0.get_row() as usize * FIELD_RESOLUTION + pair[1].1.get_row();
+			assert!(a_column.abs_diff(b_column) <= 1);
+			assert!(a_row.abs_diff(b_row) <= 1);
+		}
+	}
+	#[test]
+	fn best_cell_path_same_sector() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector_portals = SectorPortals::new(map_dimensions.get_length(), map_dimensions.get_depth(), map_dimensions.get_sector_resolution());
+		let graph = PortalGraph::default();
+
+		let source = (SectorID::new(1, 1), FieldCell::new(0, 0));
+		let target = (SectorID::new(1, 1), FieldCell::new(3, 3));
+		let path = graph.find_best_cell_path(source, target, &sector_portals, &sector_cost_fields).unwrap();
+		let actual = vec![(SectorID::new(1, 1), FieldCell::new(0, 0)), (SectorID::new(1, 1), FieldCell::new(1, 1)), (SectorID::new(1, 1), FieldCell::new(2, 2)), (SectorID::new(1, 1), FieldCell::new(3, 3))];
+		assert_eq!(actual, path);
+	}
+	#[test]
+	fn best_path_across_layer_connection() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let stairs = LayerConnection::new(
+			(SectorID::new(2, 0), FieldCell::new(5, 5)),
+			(SectorID::new_layered(0, 2, 1), FieldCell::new(5, 5)),
+			10,
+		)
+		.with_bidirectional(false);
+		let bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5)
+			.with_layer(SectorCostFields::new(&map_dimensions))
+			.with_layer_connection(stairs);
+		let sector_portals = bundle.get_sector_portals();
+		let sector_cost_fields = bundle.get_sector_cost_fields();
+		assert_eq!(2, sector_cost_fields.get_sector_layer_count());
+		assert_eq!(18, sector_portals.get().len());
+		let mut graph = bundle.get_portal_graph().clone();
+
+		let source = (SectorID::new(0, 0), FieldCell::new(5, 5));
+		let target = (SectorID::new_layered(2, 2, 1), FieldCell::new(5, 5));
+		let path = graph.find_best_path(source, target, sector_portals, sector_cost_fields).unwrap();
+		// the route climbs the stairs and then crosses the upper layer
+		let climb = path.iter().position(|(sector, _)| sector.get_layer() == 1).unwrap();
+		assert_eq!((SectorID::new(2, 0), FieldCell::new(5, 5)), path[climb - 1]);
+		assert_eq!((SectorID::new_layered(0, 2, 1), FieldCell::new(5, 5)), path[climb]);
+		assert!(path[..climb].iter().all(|(sector, _)| sector.get_layer() == 0));
+		assert!(path[climb..].iter().all(|(sector, _)| sector.get_layer() == 1));
+		let cells = graph.find_best_cell_path(source, target, sector_portals, sector_cost_fields).unwrap();
+		assert_eq!(source, cells[0]);
+		assert_eq!(target, *cells.last().unwrap());
+		// the stairs only go up
+		assert!(graph.find_best_path(target, source, sector_portals, sector_cost_fields).is_none());
+		// rebuilding the sector at the bottom of the stairs keeps the connection
+		graph.update_graph(SectorID::new(2, 0), sector_portals, sector_cost_fields, &map_dimensions);
+		assert!(graph.find_best_path(source, target, sector_portals, sector_cost_fields).is_some());
+	}
+	#[test]
+	#[should_panic]
+	fn layer_connection_same_layer() {
+		LayerConnection::new(
+			(SectorID::new(0, 0), FieldCell::new(5, 5)),
+			(SectorID::new(1, 0), FieldCell::new(5, 5)),
+			1,
+		);
+	}
 }
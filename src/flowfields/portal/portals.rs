@@ -141,218 +141,239 @@ impl Portals {
 			map_dimensions.get_ordinal_and_ids_of_neighbouring_sectors(sector_id);
 		// moving in a clockwise fashion around the valid ordinals of the boundary sector movement
 		// we inspect the [CostField] values to calculate the portals along each valid sector side
+		for (ord, adjoining_sector_id) in valid_ordinals_for_this_sector.iter() {
+			self.calculate_side(sector_cost_fields, sector_id, ord, adjoining_sector_id);
+		}
+	}
+	/// Recalculate the portal [FieldCell]s along only the `ordinal` side of a sector, the other sides are left untouched. If the side sits along the edge of the map it is cleared.
+	///
+	/// The matching side of the neighbouring sector should also be recalculated so that both sectors keep the same number of portals along the boundary
+	pub fn recalculate_portals_on_side(
+		&mut self,
+		sector_cost_fields: &SectorCostFields,
+		sector_id: &SectorID,
+		ordinal: Ordinal,
+		map_dimensions: &MapDimensions,
+	) {
+		self.clear(ordinal);
+		if let Some((ord, adjoining_sector_id)) = map_dimensions
+			.get_ordinal_and_ids_of_neighbouring_sectors(sector_id)
+			.iter()
+			.find(|(ord, _)| *ord == ordinal)
+		{
+			self.calculate_side(sector_cost_fields, sector_id, ord, adjoining_sector_id);
+		}
+	}
+	/// Inspect the [CostField]s either side of the `ord` boundary of a sector and record the portal [FieldCell]s along it
+	fn calculate_side(
+		&mut self,
+		sector_cost_fields: &SectorCostFields,
+		sector_id: &SectorID,
+		ord: &Ordinal,
+		adjoining_sector_id: &SectorID,
+	) {
 		let cost_field = sector_cost_fields
 			.get_scaled()
 			.get(sector_id)
 			.expect("Invalid sector id");
-		for (ord, adjoining_sector_id) in valid_ordinals_for_this_sector.iter() {
-			match ord {
-				Ordinal::North => {
-					let portal_nodes = self.get_mut(ord);
-					let column_range = 0..FIELD_RESOLUTION;
-					let fixed_row = 0;
-					let adjoining_cost_field = sector_cost_fields
-						.get_scaled()
-						.get(adjoining_sector_id)
-						.unwrap();
-					// walk along the side of the field
-					let mut neighbouring_pathable = Vec::new();
-					for i in column_range {
-						let field_cost =
-							cost_field.get_field_cell_value(FieldCell::new(i, fixed_row));
-						let adjacent_field_cost = adjoining_cost_field
-							.get_field_cell_value(FieldCell::new(i, FIELD_RESOLUTION - 1));
-						if field_cost != 255 && adjacent_field_cost != 255 {
-							// a pathable point along the edge so we record it to be
-							// published later as a FieldCell
-							neighbouring_pathable.push((i, fixed_row));
-						} else {
-							// if a length along the edge was previously calculated then publish
-							// it as FieldCell
-							if !neighbouring_pathable.is_empty() {
-								// find the most centre like cell for this portal window
-								let mut column_index_sum = 0;
-								for (m, _) in neighbouring_pathable.iter() {
-									column_index_sum += m;
-								}
-								let portal_midpoint_column =
-									column_index_sum / neighbouring_pathable.len();
-								portal_nodes
-									.push(FieldCell::new(portal_midpoint_column, fixed_row));
-								// clear the recording list so any other portals along the side can be built
-								neighbouring_pathable.clear();
+		match ord {
+			Ordinal::North => {
+				let portal_nodes = self.get_mut(ord);
+				let column_range = 0..FIELD_RESOLUTION;
+				let fixed_row = 0;
+				let adjoining_cost_field = sector_cost_fields
+					.get_scaled()
+					.get(adjoining_sector_id)
+					.unwrap();
+				// walk along the side of the field
+				let mut neighbouring_pathable = Vec::new();
+				for i in column_range {
+					let field_cost = cost_field.get_field_cell_value(FieldCell::new(i, fixed_row));
+					let adjacent_field_cost = adjoining_cost_field
+						.get_field_cell_value(FieldCell::new(i, FIELD_RESOLUTION - 1));
+					if field_cost != 255 && adjacent_field_cost != 255 {
+						// a pathable point along the edge so we record it to be
+						// published later as a FieldCell
+						neighbouring_pathable.push((i, fixed_row));
+					} else {
+						// if a length along the edge was previously calculated then publish
+						// it as FieldCell
+						if !neighbouring_pathable.is_empty() {
+							// find the most centre like cell for this portal window
+							let mut column_index_sum = 0;
+							for (m, _) in neighbouring_pathable.iter() {
+								column_index_sum += m;
 							}
+							let portal_midpoint_column =
+								column_index_sum / neighbouring_pathable.len();
+							portal_nodes.push(FieldCell::new(portal_midpoint_column, fixed_row));
+							// clear the recording list so any other portals along the side can be built
+							neighbouring_pathable.clear();
 						}
 					}
-					// if the side doesn't end with a cost field of 255 then there's one more portal window that needs to be published after iterating over the side
-					if !neighbouring_pathable.is_empty() {
-						// find the most centre like cell for this portal window
-						let mut column_index_sum = 0;
-						for (i, _) in neighbouring_pathable.iter() {
-							column_index_sum += i;
-						}
-						let portal_midpoint_column = column_index_sum / neighbouring_pathable.len();
-						portal_nodes.push(FieldCell::new(portal_midpoint_column, fixed_row));
-						// clear the recording list so any other portals along the side can be built
-						neighbouring_pathable.clear();
+				}
+				// if the side doesn't end with a cost field of 255 then there's one more portal window that needs to be published after iterating over the side
+				if !neighbouring_pathable.is_empty() {
+					// find the most centre like cell for this portal window
+					let mut column_index_sum = 0;
+					for (i, _) in neighbouring_pathable.iter() {
+						column_index_sum += i;
 					}
+					let portal_midpoint_column = column_index_sum / neighbouring_pathable.len();
+					portal_nodes.push(FieldCell::new(portal_midpoint_column, fixed_row));
+					// clear the recording list so any other portals along the side can be built
+					neighbouring_pathable.clear();
 				}
-				Ordinal::East => {
-					let portal_nodes = self.get_mut(ord);
-					let fixed_column = FIELD_RESOLUTION - 1;
-					let row_range = 0..FIELD_RESOLUTION;
-					let adjoining_cost_field = sector_cost_fields
-						.get_scaled()
-						.get(adjoining_sector_id)
-						.unwrap();
-					// walk along the side of the field
-					let mut neighbouring_pathable = Vec::new();
-					for j in row_range {
-						let field_cost =
-							cost_field.get_field_cell_value(FieldCell::new(fixed_column, j));
-						let adjacent_field_cost =
-							adjoining_cost_field.get_field_cell_value(FieldCell::new(0, j));
-						if field_cost != 255 && adjacent_field_cost != 255 {
-							// a pathable point along the edge so we record it to be
-							// published later as a FieldCell
-							neighbouring_pathable.push((fixed_column, j));
-						} else {
-							// if a length along the edge was previously calculated then publish
-							// it as FieldCell
-							if !neighbouring_pathable.is_empty() {
-								// find the most centre like cell for this portal window
-								let mut row_index_sum = 0;
-								for (_, n) in neighbouring_pathable.iter() {
-									row_index_sum += n;
-								}
-								let portal_midpoint_row =
-									row_index_sum / neighbouring_pathable.len();
-								portal_nodes
-									.push(FieldCell::new(fixed_column, portal_midpoint_row));
-								// clear the recording list so any other portals along the side can be built
-								neighbouring_pathable.clear();
+			}
+			Ordinal::East => {
+				let portal_nodes = self.get_mut(ord);
+				let fixed_column = FIELD_RESOLUTION - 1;
+				let row_range = 0..FIELD_RESOLUTION;
+				let adjoining_cost_field = sector_cost_fields
+					.get_scaled()
+					.get(adjoining_sector_id)
+					.unwrap();
+				// walk along the side of the field
+				let mut neighbouring_pathable = Vec::new();
+				for j in row_range {
+					let field_cost =
+						cost_field.get_field_cell_value(FieldCell::new(fixed_column, j));
+					let adjacent_field_cost =
+						adjoining_cost_field.get_field_cell_value(FieldCell::new(0, j));
+					if field_cost != 255 && adjacent_field_cost != 255 {
+						// a pathable point along the edge so we record it to be
+						// published later as a FieldCell
+						neighbouring_pathable.push((fixed_column, j));
+					} else {
+						// if a length along the edge was previously calculated then publish
+						// it as FieldCell
+						if !neighbouring_pathable.is_empty() {
+							// find the most centre like cell for this portal window
+							let mut row_index_sum = 0;
+							for (_, n) in neighbouring_pathable.iter() {
+								row_index_sum += n;
 							}
+							let portal_midpoint_row = row_index_sum / neighbouring_pathable.len();
+							portal_nodes.push(FieldCell::new(fixed_column, portal_midpoint_row));
+							// clear the recording list so any other portals along the side can be built
+							neighbouring_pathable.clear();
 						}
 					}
-					// if the side doesn't end with a cost field of 255 then there's one more portal window that needs to be published after iterating over the side
-					if !neighbouring_pathable.is_empty() {
-						// find the most centre like cell for this portal window
-						let mut row_index_sum = 0;
-						for (_, n) in neighbouring_pathable.iter() {
-							row_index_sum += n;
-						}
-						let portal_midpoint_row = row_index_sum / neighbouring_pathable.len();
-						portal_nodes.push(FieldCell::new(fixed_column, portal_midpoint_row));
-						// clear the recording list so any other portals along the side can be built
-						neighbouring_pathable.clear();
+				}
+				// if the side doesn't end with a cost field of 255 then there's one more portal window that needs to be published after iterating over the side
+				if !neighbouring_pathable.is_empty() {
+					// find the most centre like cell for this portal window
+					let mut row_index_sum = 0;
+					for (_, n) in neighbouring_pathable.iter() {
+						row_index_sum += n;
 					}
+					let portal_midpoint_row = row_index_sum / neighbouring_pathable.len();
+					portal_nodes.push(FieldCell::new(fixed_column, portal_midpoint_row));
+					// clear the recording list so any other portals along the side can be built
+					neighbouring_pathable.clear();
 				}
-				Ordinal::South => {
-					let portal_nodes = self.get_mut(ord);
-					let column_range = 0..FIELD_RESOLUTION;
-					let fixed_row = FIELD_RESOLUTION - 1;
-					let adjoining_cost_field = sector_cost_fields
-						.get_scaled()
-						.get(adjoining_sector_id)
-						.unwrap();
-					// walk along the side of the field
-					let mut neighbouring_pathable = Vec::new();
-					for i in column_range {
-						let field_cost =
-							cost_field.get_field_cell_value(FieldCell::new(i, fixed_row));
-						let adjacent_field_cost =
-							adjoining_cost_field.get_field_cell_value(FieldCell::new(i, 0));
-						if field_cost != 255 && adjacent_field_cost != 255 {
-							// a pathable point along the edge so we record it to be
-							// published later as a FieldCell
-							neighbouring_pathable.push((i, fixed_row));
-						} else {
-							// if a length along the edge was previously calculated then publish
-							// it as FieldCell
-							if !neighbouring_pathable.is_empty() {
-								// find the most centre like cell for this portal window
-								let mut column_index_sum = 0;
-								for (m, _) in neighbouring_pathable.iter() {
-									column_index_sum += m;
-								}
-								let portal_midpoint_column =
-									column_index_sum / neighbouring_pathable.len();
-								portal_nodes
-									.push(FieldCell::new(portal_midpoint_column, fixed_row));
-								// clear the recording list so any other portals along the side can be built
-								neighbouring_pathable.clear();
+			}
+			Ordinal::South => {
+				let portal_nodes = self.get_mut(ord);
+				let column_range = 0..FIELD_RESOLUTION;
+				let fixed_row = FIELD_RESOLUTION - 1;
+				let adjoining_cost_field = sector_cost_fields
+					.get_scaled()
+					.get(adjoining_sector_id)
+					.unwrap();
+				// walk along the side of the field
+				let mut neighbouring_pathable = Vec::new();
+				for i in column_range {
+					let field_cost = cost_field.get_field_cell_value(FieldCell::new(i, fixed_row));
+					let adjacent_field_cost =
+						adjoining_cost_field.get_field_cell_value(FieldCell::new(i, 0));
+					if field_cost != 255 && adjacent_field_cost != 255 {
+						// a pathable point along the edge so we record it to be
+						// published later as a FieldCell
+						neighbouring_pathable.push((i, fixed_row));
+					} else {
+						// if a length along the edge was previously calculated then publish
+						// it as FieldCell
+						if !neighbouring_pathable.is_empty() {
+							// find the most centre like cell for this portal window
+							let mut column_index_sum = 0;
+							for (m, _) in neighbouring_pathable.iter() {
+								column_index_sum += m;
 							}
+							let portal_midpoint_column =
+								column_index_sum / neighbouring_pathable.len();
+							portal_nodes.push(FieldCell::new(portal_midpoint_column, fixed_row));
+							// clear the recording list so any other portals along the side can be built
+							neighbouring_pathable.clear();
 						}
 					}
-					// if the side doesn't end with a cost field of 255 then there's one more portal window that needs to be published after iterating over the side
-					if !neighbouring_pathable.is_empty() {
-						// find the most centre like cell for this portal window
-						let mut column_index_sum = 0;
-						for (i, _) in neighbouring_pathable.iter() {
-							column_index_sum += i;
-						}
-						let portal_midpoint_column = column_index_sum / neighbouring_pathable.len();
-						portal_nodes.push(FieldCell::new(portal_midpoint_column, fixed_row));
-						// clear the recording list so any other portals along the side can be built
-						neighbouring_pathable.clear();
+				}
+				// if the side doesn't end with a cost field of 255 then there's one more portal window that needs to be published after iterating over the side
+				if !neighbouring_pathable.is_empty() {
+					// find the most centre like cell for this portal window
+					let mut column_index_sum = 0;
+					for (i, _) in neighbouring_pathable.iter() {
+						column_index_sum += i;
 					}
+					let portal_midpoint_column = column_index_sum / neighbouring_pathable.len();
+					portal_nodes.push(FieldCell::new(portal_midpoint_column, fixed_row));
+					// clear the recording list so any other portals along the side can be built
+					neighbouring_pathable.clear();
 				}
-				Ordinal::West => {
-					let portal_nodes = self.get_mut(ord);
-					let fixed_column = 0;
-					let row_range = 0..FIELD_RESOLUTION;
-					let adjoining_cost_field = sector_cost_fields
-						.get_scaled()
-						.get(adjoining_sector_id)
-						.unwrap();
-					// walk along the side of the field
-					let mut neighbouring_pathable = Vec::new();
-					for j in row_range {
-						let field_cost =
-							cost_field.get_field_cell_value(FieldCell::new(fixed_column, j));
-						let adjacent_field_cost = adjoining_cost_field
-							.get_field_cell_value(FieldCell::new(FIELD_RESOLUTION - 1, j));
-						if field_cost != 255 && adjacent_field_cost != 255 {
-							// a pathable point along the edge so we record it to be
-							// published later as a FieldCell
-							neighbouring_pathable.push((fixed_column, j));
-						} else {
-							// if a length along the edge was previously calculated then publish
-							// it as FieldCell
-							if !neighbouring_pathable.is_empty() {
-								// find the most centre like cell for this portal window
-								let mut row_index_sum = 0;
-								for (_, n) in neighbouring_pathable.iter() {
-									row_index_sum += n;
-								}
-								let portal_midpoint_row =
-									row_index_sum / neighbouring_pathable.len();
-								portal_nodes
-									.push(FieldCell::new(fixed_column, portal_midpoint_row));
-								// clear the recording list so any other portals along the side can be built
-								neighbouring_pathable.clear();
+			}
+			Ordinal::West => {
+				let portal_nodes = self.get_mut(ord);
+				let fixed_column = 0;
+				let row_range = 0..FIELD_RESOLUTION;
+				let adjoining_cost_field = sector_cost_fields
+					.get_scaled()
+					.get(adjoining_sector_id)
+					.unwrap();
+				// walk along the side of the field
+				let mut neighbouring_pathable = Vec::new();
+				for j in row_range {
+					let field_cost =
+						cost_field.get_field_cell_value(FieldCell::new(fixed_column, j));
+					let adjacent_field_cost = adjoining_cost_field
+						.get_field_cell_value(FieldCell::new(FIELD_RESOLUTION - 1, j));
+					if field_cost != 255 && adjacent_field_cost != 255 {
+						// a pathable point along the edge so we record it to be
+						// published later as a FieldCell
+						neighbouring_pathable.push((fixed_column, j));
+					} else {
+						// if a length along the edge was previously calculated then publish
+						// it as FieldCell
+						if !neighbouring_pathable.is_empty() {
+							// find the most centre like cell for this portal window
+							let mut row_index_sum = 0;
+							for (_, n) in neighbouring_pathable.iter() {
+								row_index_sum += n;
 							}
+							let portal_midpoint_row = row_index_sum / neighbouring_pathable.len();
+							portal_nodes.push(FieldCell::new(fixed_column, portal_midpoint_row));
+							// clear the recording list so any other portals along the side can be built
+							neighbouring_pathable.clear();
 						}
 					}
-					// if the side doesn't end with a cost field of 255 then there's one more portal window that needs to be published after iterating over the side
-					if !neighbouring_pathable.is_empty() {
-						// find the most centre like cell for this portal window
-						let mut row_index_sum = 0;
-						for (_, n) in neighbouring_pathable.iter() {
-							row_index_sum += n;
-						}
-						let portal_midpoint_row = row_index_sum / neighbouring_pathable.len();
-						portal_nodes.push(FieldCell::new(fixed_column, portal_midpoint_row));
-						// clear the recording list so any other portals along the side can be built
-						neighbouring_pathable.clear();
+				}
+				// if the side doesn't end with a cost field of 255 then there's one more portal window that needs to be published after iterating over the side
+				if !neighbouring_pathable.is_empty() {
+					// find the most centre like cell for this portal window
+					let mut row_index_sum = 0;
+					for (_, n) in neighbouring_pathable.iter() {
+						row_index_sum += n;
 					}
+					let portal_midpoint_row = row_index_sum / neighbouring_pathable.len();
+					portal_nodes.push(FieldCell::new(fixed_column, portal_midpoint_row));
+					// clear the recording list so any other portals along the side can be built
+					neighbouring_pathable.clear();
 				}
-				_ => panic!(
-					"Portal ordinals can only be North, East, South or West. Asked for {:?}",
-					ord
-				),
-			};
-		}
+			}
+			_ => panic!(
+				"Portal ordinals can only be North, East, South or West. Asked for {:?}",
+				ord
+			),
+		};
 	}
 	/// A [FieldCell] represents the midpoint of a segment along a boundary, for smooth pathfinding any field cell along the segemnt should be a viable goal node when calculating an [IntegrationField]. This takes inspects the `portal_id` within the given `sector_id` and build a list of field cells which comprise the true dimension of the portal
 	pub fn expand_portal_into_goals(
@@ -363,6 +384,10 @@ impl Portals {
 		neighbour_sector_id: &SectorID,
 		map_dimensions: &MapDimensions,
 	) -> Vec<FieldCell> {
+		// the end of a [LayerConnection] is a single cell rather than a boundary segment
+		if sector_id.get_layer() != neighbour_sector_id.get_layer() {
+			return vec![*portal_id];
+		}
 		// find the bounudary the portal sit along
 		let mut boundary_ordinals = portal_id.get_boundary_ordinal_from_field_cell();
 		// if it's in a corner then it could apply to two boundaries, narrow it down so we know which boundary to walk
@@ -0,0 +1,143 @@
+//! A plain text representation of a map where each character is a [FieldCell](crate::prelude::FieldCell), which makes it easy to sketch and hand-edit a map in any text editor:
+//!
+//! ```text
+//! ..........
+//! ...####...
+//! ...#~~#...
+//! ..........
+//! ```
+//!
+//! An [AsciiLegend] describes which [CostField](crate::prelude::CostField) value each character represents
+//!
+
+/// Describes the cost represented by each character of a plain text map.
+///
+/// By default `.` is open ground with a cost of `1`, `~` is difficult terrain with a cost of `100` and `#` is an impassable `255`. When writing a map the first character of the legend with a matching cost is used, see [AsciiLegend::get_nearest_character] for costs without a character
+#[derive(Clone, Debug)]
+pub struct AsciiLegend {
+	/// Characters and the cost they represent in the order they were added
+	characters: Vec<(char, u8)>,
+}
+
+impl Default for AsciiLegend {
+	fn default() -> Self {
+		AsciiLegend {
+			characters: vec![('.', 1), ('~', 100), ('#', 255)],
+		}
+	}
+}
+
+impl AsciiLegend {
+	/// Create a legend without any characters
+	pub fn empty() -> Self {
+		AsciiLegend {
+			characters: Vec::new(),
+		}
+	}
+	/// Make `character` represent `cost`, replacing any existing cost of the character
+	pub fn with_character(mut self, character: char, cost: u8) -> Self {
+		match self.characters.iter_mut().find(|(c, _)| *c == character) {
+			Some(existing) => existing.1 = cost,
+			None => self.characters.push((character, cost)),
+		}
+		self
+	}
+	/// Get the cost represented by a `character`
+	pub fn get_cost(&self, character: char) -> Option<u8> {
+		self.characters
+			.iter()
+			.find(|(c, _)| *c == character)
+			.map(|(_, cost)| *cost)
+	}
+	/// Get the character which represents a `cost`
+	pub fn get_character(&self, cost: u8) -> Option<char> {
+		self.characters
+			.iter()
+			.find(|(_, c)| *c == cost)
+			.map(|(character, _)| *character)
+	}
+	/// Get the character which best represents a `cost`. If no character matches the cost exactly the character with the nearest cost is used, so a map with costs edited at runtime can still be written. A passable cost is only written as an impassable `255` character (and vice versa) when the legend has nothing else. An empty legend writes `?`
+	pub fn get_nearest_character(&self, cost: u8) -> char {
+		if let Some(character) = self.get_character(cost) {
+			return character;
+		}
+		let is_impassable = cost == 255;
+		// characters of the other kind sort after every character of the same kind
+		self.characters
+			.iter()
+			.min_by_key(|(_, c)| ((*c == 255) != is_impassable, c.abs_diff(cost)))
+			.map(|(character, _)| *character)
+			.unwrap_or('?')
+	}
+	/// Translate the lines of a plain text map into rows of costs, blank lines are ignored
+	pub fn parse(&self, text: &str) -> Vec<Vec<u8>> {
+		text.lines()
+			.map(str::trim_end)
+			.filter(|line| !line.is_empty())
+			.enumerate()
+			.map(|(row, line)| {
+				line.chars()
+					.enumerate()
+					.map(|(column, character)| {
+						self.get_cost(character).unwrap_or_else(|| {
+							panic!(
+								"Unknown character '{}' at column {} row {} of ascii map",
+								character, column, row
+							)
+						})
+					})
+					.collect()
+			})
+			.collect()
+	}
+	/// Translate rows of costs into a plain text map with a line per row, each cost is written with [AsciiLegend::get_nearest_character]
+	pub fn format(&self, rows: &[Vec<u8>]) -> String {
+		let mut text = String::new();
+		for row in rows.iter() {
+			for cost in row.iter() {
+				text.push(self.get_nearest_character(*cost));
+			}
+			text.push('\n');
+		}
+		text
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn parse_and_format() {
+		let text = "..#\n.~#\n\n";
+		let legend = AsciiLegend::default();
+		let rows = legend.parse(text);
+		assert_eq!(vec![vec![1, 1, 255], vec![1, 100, 255]], rows);
+		assert_eq!("..#\n.~#\n", legend.format(&rows));
+	}
+	#[test]
+	fn custom_characters() {
+		let legend = AsciiLegend::empty()
+			.with_character('g', 1)
+			.with_character('m', 50)
+			.with_character('M', 50)
+			.with_character('g', 2);
+		assert_eq!(vec![vec![2, 50, 50]], legend.parse("gmM"));
+		// the first character of a cost is written
+		assert_eq!("gmm\n", legend.format(&[vec![2, 50, 50]]));
+	}
+	#[test]
+	fn nearest_character() {
+		let legend = AsciiLegend::default();
+		assert_eq!("..~~#\n", legend.format(&[vec![1, 7, 60, 120, 255]]));
+		// passable costs are never written as impassable
+		assert_eq!('~', legend.get_nearest_character(254));
+		let walls = AsciiLegend::empty().with_character('#', 255);
+		assert_eq!('#', walls.get_nearest_character(1));
+		assert_eq!('?', AsciiLegend::empty().get_nearest_character(1));
+	}
+	#[test]
+	#[should_panic]
+	fn unknown_character() {
+		AsciiLegend::default().parse("..x");
+	}
+}
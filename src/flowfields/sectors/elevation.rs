@@ -0,0 +1,273 @@
+//! The height of the terrain at each [FieldCell] of a 3d map.
+//!
+//! An [ElevationField] can be loaded from a greyscale elevation heightmap (with the `heightmap` feature) and used to derive the [CostField](crate::prelude::CostField)s of a map from the steepness of the terrain, where [SlopeCosts] describes how the slope between neighbouring cells translates into a cost
+//!
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Translates the slope of the terrain in degrees into a cost.
+///
+/// The curve is a list of `(slope, cost)` points with linear interpolation between them, slopes shallower than the first point use its cost and slopes steeper than the last point use its cost. Any slope at or above the impassable threshold is a cost of `255`. By default the cost rises linearly from `1` on flat ground to `254` at `45` degrees, which is also the threshold
+#[derive(Clone, Debug)]
+pub struct SlopeCosts {
+	/// Points of the curve ordered by slope in degrees
+	curve: Vec<(f32, u8)>,
+	/// Slope in degrees from which terrain is impassable
+	max_slope: f32,
+}
+
+impl Default for SlopeCosts {
+	fn default() -> Self {
+		SlopeCosts {
+			curve: vec![(0.0, 1), (45.0, 254)],
+			max_slope: 45.0,
+		}
+	}
+}
+
+impl SlopeCosts {
+	/// Replace the curve with a list of `(slope, cost)` points where slopes are in degrees
+	pub fn with_curve(mut self, mut curve: Vec<(f32, u8)>) -> Self {
+		curve.sort_by(|a, b| a.0.total_cmp(&b.0));
+		self.curve = curve;
+		self
+	}
+	/// Set the slope in degrees from which terrain is impassable
+	pub fn with_max_slope(mut self, degrees: f32) -> Self {
+		self.max_slope = degrees;
+		self
+	}
+	/// Get the points of the curve ordered by slope
+	pub fn get_curve(&self) -> &Vec<(f32, u8)> {
+		&self.curve
+	}
+	/// Get the slope in degrees from which terrain is impassable
+	pub fn get_max_slope(&self) -> f32 {
+		self.max_slope
+	}
+	/// Get the cost of a slope in degrees. An empty curve is a cost of `1` for any passable slope
+	pub fn get_cost(&self, degrees: f32) -> u8 {
+		if degrees >= self.max_slope {
+			return 255;
+		}
+		let (Some(first), Some(last)) = (self.curve.first(), self.curve.last()) else {
+			return 1;
+		};
+		if degrees <= first.0 {
+			return first.1.max(1);
+		}
+		if degrees >= last.0 {
+			return last.1.max(1);
+		}
+		let cost = self
+			.curve
+			.windows(2)
+			.find(|pair| degrees <= pair[1].0)
+			.map(|pair| {
+				let (a, b) = (pair[0], pair[1]);
+				let t = (degrees - a.0) / (b.0 - a.0);
+				a.1 as f32 + (b.1 as f32 - a.1 as f32) * t
+			})
+			.unwrap_or(last.1 as f32);
+		(cost.round() as u8).max(1)
+	}
+}
+
+/// The height of the terrain at the centre of every [FieldCell] across the whole map, arranged as `[column][row]` starting from the top left
+#[cfg_attr(
+	feature = "serde",
+	derive(serde::Deserialize, serde::Serialize),
+	serde(default)
+)]
+#[derive(Component, Clone, Default, Debug, Reflect)]
+#[reflect(Component)]
+pub struct ElevationField {
+	/// Height of each cell, `[column][row]`
+	heights: Vec<Vec<f32>>,
+}
+
+impl ElevationField {
+	/// Create a flat [ElevationField] with a height of `0.0` for every [FieldCell] of the map
+	pub fn new(map_dimensions: &MapDimensions) -> Self {
+		ElevationField::from_map_cells(map_dimensions, |_, _| 0.0)
+	}
+	/// Create an [ElevationField] where `height` gives the height of the [FieldCell] at each `(column, row)` across the whole map, starting from the top left
+	pub fn from_map_cells(
+		map_dimensions: &MapDimensions,
+		height: impl Fn(usize, usize) -> f32,
+	) -> Self {
+		let heights = (0..map_dimensions.get_total_field_cell_columns())
+			.map(|column| {
+				(0..map_dimensions.get_total_field_cell_rows())
+					.map(|row| height(column, row))
+					.collect()
+			})
+			.collect();
+		ElevationField { heights }
+	}
+	/// Create an [ElevationField] from a greyscale image where each pixel represents a [FieldCell]. Black is a height of `0.0` and white is `max_height`, the average of the colour channels is used for anything in between
+	#[cfg(feature = "heightmap")]
+	pub fn from_heightmap(map_dimensions: &MapDimensions, path: String, max_height: f32) -> Self {
+		let pixels = read_heightmap_pixels(map_dimensions, &path);
+		ElevationField::from_map_cells(map_dimensions, |column, row| {
+			let px = pixels[row][column];
+			let colour_avg = (px[0] as f32 + px[1] as f32 + px[2] as f32) / 3.0;
+			colour_avg / 255.0 * max_height
+		})
+	}
+	/// Get the heights of every cell, `[column][row]`
+	pub fn get(&self) -> &Vec<Vec<f32>> {
+		&self.heights
+	}
+	/// Does the field have no heights. An empty field, such as the default one of a [FlowFieldTilesBundle], describes a flat map where every cell has a height of `0.0`
+	pub fn is_empty(&self) -> bool {
+		self.heights.is_empty()
+	}
+	/// Get the height of a `(column, row)` cell across the whole map. Cells outside of the field have a height of `0.0`
+	pub fn get_height(&self, column: usize, row: usize) -> f32 {
+		self.heights
+			.get(column)
+			.and_then(|rows| rows.get(row))
+			.copied()
+			.unwrap_or(0.0)
+	}
+	/// Set the height of a `(column, row)` cell across the whole map. Panics if the cell is outside of the map
+	pub fn set_height(&mut self, column: usize, row: usize, height: f32) {
+		self.heights[column][row] = height;
+	}
+	/// Get the height of a [FieldCell] within a sector
+	pub fn get_field_cell_height(&self, sector: SectorID, field: FieldCell) -> f32 {
+		self.get_height(
+			sector.get_column() as usize * FIELD_RESOLUTION + field.get_column(),
+			sector.get_row() as usize * FIELD_RESOLUTION + field.get_row(),
+		)
+	}
+	/// Get the steepest slope in degrees between a `(column, row)` cell and any of its eight neighbours. Taking the steepest neighbour means a cliff is detected from both its top and bottom
+	pub fn get_slope(&self, map_dimensions: &MapDimensions, column: usize, row: usize) -> f32 {
+		let cell_size = map_dimensions.get_sector_resolution() as f32 / FIELD_RESOLUTION as f32;
+		let height = self.get_height(column, row);
+		let mut steepest: f32 = 0.0;
+		for (dc, dr) in [
+			(-1, -1),
+			(0, -1),
+			(1, -1),
+			(-1, 0),
+			(1, 0),
+			(-1, 1),
+			(0, 1),
+			(1, 1),
+		] {
+			let c = column as i32 + dc;
+			let r = row as i32 + dr;
+			if c < 0 || r < 0 || c as usize >= self.heights.len() {
+				continue;
+			}
+			let Some(neighbour) = self.heights[c as usize].get(r as usize) else {
+				continue;
+			};
+			let distance = cell_size * ((dc * dc + dr * dr) as f32).sqrt();
+			let gradient = (neighbour - height).abs() / distance;
+			steepest = steepest.max(gradient.atan().to_degrees());
+		}
+		steepest
+	}
+	/// Get the height of the terrain beneath a 3d `position` by interpolating between the centres of the nearest four [FieldCell]s. The `y` of the position is ignored and positions beyond the edge of the map use the height of the closest edge cell
+	pub fn get_height_at_xyz(&self, map_dimensions: &MapDimensions, position: Vec3) -> f32 {
+		if self.is_empty() {
+			return 0.0;
+		}
+		// cell centres sit half a cell in from the corner of each cell
+		let grid = map_dimensions.get_grid_position_from_xyz(position) - Vec2::splat(0.5);
+		let max_column = map_dimensions.get_total_field_cell_columns() - 1;
+		let max_row = map_dimensions.get_total_field_cell_rows() - 1;
+		let grid = grid.clamp(Vec2::ZERO, Vec2::new(max_column as f32, max_row as f32));
+		let (c0, r0) = (grid.x.floor() as usize, grid.y.floor() as usize);
+		let (c1, r1) = ((c0 + 1).min(max_column), (r0 + 1).min(max_row));
+		let (tx, tz) = (grid.x - c0 as f32, grid.y - r0 as f32);
+		let top = self.get_height(c0, r0) * (1.0 - tx) + self.get_height(c1, r0) * tx;
+		let bottom = self.get_height(c0, r1) * (1.0 - tx) + self.get_height(c1, r1) * tx;
+		top * (1.0 - tz) + bottom * tz
+	}
+	/// From a [FieldCell] within a sector retrieve the position of its centre, including the height of the terrain as the `y` coordinate. If the position is outside of the world then [None] is returned
+	pub fn get_xyz_from_field_sector(
+		&self,
+		map_dimensions: &MapDimensions,
+		sector: SectorID,
+		field: FieldCell,
+	) -> Option<Vec3> {
+		let mut position = map_dimensions.get_xyz_from_field_sector(sector, field)?;
+		position.y = self.get_field_cell_height(sector, field);
+		Some(position)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn slope_curve() {
+		let costs = SlopeCosts::default();
+		assert_eq!(1, costs.get_cost(0.0));
+		assert_eq!(128, costs.get_cost(22.5));
+		assert_eq!(255, costs.get_cost(45.0));
+		let costs = SlopeCosts::default()
+			.with_curve(vec![(30.0, 100), (10.0, 1), (20.0, 10)])
+			.with_max_slope(60.0);
+		assert_eq!(1, costs.get_cost(5.0));
+		assert_eq!(6, costs.get_cost(15.0));
+		assert_eq!(55, costs.get_cost(25.0));
+		assert_eq!(100, costs.get_cost(50.0));
+		assert_eq!(255, costs.get_cost(70.0));
+		let costs = SlopeCosts::default().with_curve(vec![]);
+		assert_eq!(1, costs.get_cost(44.0));
+	}
+	#[test]
+	fn slope_between_neighbours() {
+		let map_dimensions = MapDimensions::new(20, 10, 10, 0.5);
+		// a ramp rising one unit per cell along the columns with a cliff at column 15
+		let elevation = ElevationField::from_map_cells(&map_dimensions, |column, _| {
+			if column < 15 {
+				column as f32
+			} else {
+				column as f32 + 10.0
+			}
+		});
+		assert!((45.0 - elevation.get_slope(&map_dimensions, 5, 5)).abs() < 0.001);
+		assert!(elevation.get_slope(&map_dimensions, 15, 0) > 80.0);
+		assert!(elevation.get_slope(&map_dimensions, 14, 9) > 80.0);
+		let flat = ElevationField::new(&map_dimensions);
+		assert_eq!(0.0, flat.get_slope(&map_dimensions, 0, 0));
+	}
+	#[test]
+	fn height_between_cells() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		let elevation =
+			ElevationField::from_map_cells(&map_dimensions, |column, row| (column + row) as f32);
+		// centre of cell (0, 0)
+		assert_eq!(
+			0.0,
+			elevation.get_height_at_xyz(&map_dimensions, Vec3::new(-9.5, 5.0, -9.5))
+		);
+		// halfway between the centres of cells (4, 2) and (5, 2)
+		let height = elevation.get_height_at_xyz(&map_dimensions, Vec3::new(-5.0, 0.0, -7.5));
+		assert!((6.5 - height).abs() < 0.001);
+		// beyond the bottom right corner
+		let height = elevation.get_height_at_xyz(&map_dimensions, Vec3::new(15.0, 0.0, 15.0));
+		assert_eq!(38.0, height);
+		let flat = ElevationField::default();
+		assert!(flat.is_empty());
+		assert_eq!(0.0, flat.get_height(3, 4));
+		assert_eq!(0.0, flat.get_height_at_xyz(&map_dimensions, Vec3::ZERO));
+	}
+	#[test]
+	fn xyz_includes_height() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		let elevation =
+			ElevationField::from_map_cells(&map_dimensions, |column, row| (column + row) as f32);
+		let position = elevation
+			.get_xyz_from_field_sector(&map_dimensions, SectorID::new(1, 0), FieldCell::new(2, 3))
+			.unwrap();
+		assert_eq!(Vec3::new(2.5, 15.0, -6.5), position);
+	}
+}
@@ -0,0 +1,209 @@
+//! Translating the pixels of a heightmap image into [CostField](crate::prelude::CostField) values.
+//!
+//! By default a heightmap is read as greyscale where white is a cost of `1` and black is impassable, alternatively a single colour channel can be read or the image can be painted with a palette of colours where each colour represents a class of terrain with its own cost
+//!
+
+use crate::prelude::MapDimensions;
+
+/// A colour channel of a pixel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeightmapChannel {
+	/// Red channel
+	Red,
+	/// Green channel
+	Green,
+	/// Blue channel
+	Blue,
+	/// Alpha channel, images without one are treated as fully opaque
+	Alpha,
+}
+
+/// How a pixel colour which isn't in a [ColourPalette] is handled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaletteMatch {
+	/// Every pixel must be exactly one of the colours of the palette
+	Exact,
+	/// A pixel uses the cost of the closest colour of the palette, which copes with anti-aliased or compressed images
+	Nearest,
+}
+
+/// A table of RGB colours and the cost each represents, such as blue water, green grass and grey rock
+#[derive(Clone, Debug)]
+pub struct ColourPalette {
+	/// Colours and their cost in the order they were added
+	colours: Vec<([u8; 3], u8)>,
+	/// How colours not in the palette are handled
+	matching: PaletteMatch,
+}
+
+impl ColourPalette {
+	/// Create an empty palette which will match colours with `matching`
+	pub fn new(matching: PaletteMatch) -> Self {
+		ColourPalette {
+			colours: Vec::new(),
+			matching,
+		}
+	}
+	/// Make the RGB `colour` represent `cost`, replacing any existing cost of the colour
+	pub fn with_colour(mut self, colour: [u8; 3], cost: u8) -> Self {
+		match self.colours.iter_mut().find(|(c, _)| *c == colour) {
+			Some(existing) => existing.1 = cost,
+			None => self.colours.push((colour, cost)),
+		}
+		self
+	}
+	/// Get how colours not in the palette are handled
+	pub fn get_matching(&self) -> PaletteMatch {
+		self.matching
+	}
+	/// Get the cost of an RGB `colour`. When matching exactly [None] is returned if the colour isn't in the palette, when matching the nearest colour the closest by euclidean distance is used with ties going to the colour added first
+	pub fn get_cost(&self, colour: [u8; 3]) -> Option<u8> {
+		match self.matching {
+			PaletteMatch::Exact => self
+				.colours
+				.iter()
+				.find(|(c, _)| *c == colour)
+				.map(|(_, cost)| *cost),
+			PaletteMatch::Nearest => {
+				let mut nearest: Option<(u32, u8)> = None;
+				for (c, cost) in self.colours.iter() {
+					let distance: u32 = c
+						.iter()
+						.zip(colour.iter())
+						.map(|(a, b)| (*a as i32 - *b as i32).pow(2) as u32)
+						.sum();
+					if nearest.map_or(true, |(d, _)| distance < d) {
+						nearest = Some((distance, *cost));
+					}
+				}
+				nearest.map(|(_, cost)| cost)
+			}
+		}
+	}
+}
+
+/// Describes how the pixels of a heightmap are translated into [CostField](crate::prelude::CostField) values
+#[derive(Clone, Debug, Default)]
+pub enum HeightmapCosts {
+	/// The average of the red, green and blue channels where white `(255, 255, 255)` is a cost of `1` and black `(0, 0, 0)` is impassable
+	#[default]
+	Greyscale,
+	/// A single channel where a value of `255` is a cost of `1` and `0` is impassable
+	Channel(HeightmapChannel),
+	/// Each colour represents a cost from the palette
+	Palette(ColourPalette),
+}
+
+impl HeightmapCosts {
+	/// Get the cost of an RGBA pixel, panics if the pixel isn't in a palette which requires an exact match
+	pub fn get_cost(&self, pixel: [u8; 4]) -> u8 {
+		match self {
+			HeightmapCosts::Greyscale => {
+				// careful of u8 overflow
+				let colour_avg = (pixel[0] as f32 + pixel[1] as f32 + pixel[2] as f32) / 3.0;
+				(255 - colour_avg as u8).clamp(1, 255)
+			}
+			HeightmapCosts::Channel(channel) => {
+				let value = match channel {
+					HeightmapChannel::Red => pixel[0],
+					HeightmapChannel::Green => pixel[1],
+					HeightmapChannel::Blue => pixel[2],
+					HeightmapChannel::Alpha => pixel[3],
+				};
+				(255 - value).clamp(1, 255)
+			}
+			HeightmapCosts::Palette(palette) => {
+				let colour = [pixel[0], pixel[1], pixel[2]];
+				palette.get_cost(colour).unwrap_or_else(|| {
+					panic!("Heightmap colour {:?} is not in the palette", colour)
+				})
+			}
+		}
+	}
+}
+
+/// Read the pixels of an image as rows of RGBA values starting from the top left, where each pixel represents a [FieldCell](crate::prelude::FieldCell) of the map. Panics if the image can't be opened or its size doesn't match the number of [FieldCell](crate::prelude::FieldCell)s of the [MapDimensions]
+pub fn read_heightmap_pixels(map_dimensions: &MapDimensions, path: &str) -> Vec<Vec<[u8; 4]>> {
+	use photon_rs::native::open_image;
+	let img = open_image(path).expect("Failed to open heightmap");
+	let img_width = img.get_width();
+	let img_height = img.get_height();
+	// ensure the size of the heightmap actually represents the number of FieldCells required by the MapDimensions
+	let required_px_width = map_dimensions.get_total_field_cell_columns() as u32;
+	if img_width != required_px_width {
+		panic!(
+			"Heightmap has incorrect width, expected width of {} pixels, found {}",
+			required_px_width, img_width
+		);
+	}
+	let required_px_height = map_dimensions.get_total_field_cell_rows() as u32;
+	if img_height != required_px_height {
+		panic!(
+			"Heightmap has incorrect height, expected hieght of {} pixels, found {}",
+			required_px_height, img_height
+		);
+	}
+	let raw_pixels = img.get_raw_pixels();
+	// raw pixels are arranged from the top left of the image and come in sets of either 3 or 4 (if alpha channel is inlcuded).
+	// Each sequential set corresponds to Red, Green, Blue, (Alpha).
+	let len_if_alpha = img_width * img_height * 4;
+	let chunk_size = {
+		if len_if_alpha as usize == raw_pixels.len() {
+			4
+		} else {
+			3
+		}
+	};
+	let pixels_rgba: Vec<[u8; 4]> = raw_pixels
+		.chunks(chunk_size)
+		.map(|rgb| {
+			// without an alpha channel the pixel is opaque
+			let alpha = rgb.get(3).copied().unwrap_or(255);
+			[rgb[0], rgb[1], rgb[2], alpha]
+		})
+		.collect();
+	// By chunking the list of pixel RGBAs based on the width of the image
+	// we can iterate on the rows
+	pixels_rgba
+		.chunks(img_width as usize)
+		.map(|row| row.to_vec())
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn greyscale_and_channel() {
+		let costs = HeightmapCosts::default();
+		assert_eq!(1, costs.get_cost([255, 255, 255, 255]));
+		assert_eq!(255, costs.get_cost([0, 0, 0, 255]));
+		assert_eq!(155, costs.get_cost([100, 100, 100, 255]));
+		let costs = HeightmapCosts::Channel(HeightmapChannel::Green);
+		assert_eq!(205, costs.get_cost([255, 50, 255, 255]));
+		let costs = HeightmapCosts::Channel(HeightmapChannel::Alpha);
+		assert_eq!(1, costs.get_cost([0, 0, 0, 255]));
+	}
+	#[test]
+	fn palette_matching() {
+		let palette = ColourPalette::new(PaletteMatch::Exact)
+			.with_colour([0, 0, 255], 255)
+			.with_colour([0, 255, 0], 1)
+			.with_colour([128, 128, 128], 40);
+		assert_eq!(Some(40), palette.get_cost([128, 128, 128]));
+		assert_eq!(None, palette.get_cost([120, 130, 128]));
+		let palette = ColourPalette {
+			matching: PaletteMatch::Nearest,
+			..palette
+		};
+		assert_eq!(Some(40), palette.get_cost([120, 130, 128]));
+		assert_eq!(Some(255), palette.get_cost([10, 20, 200]));
+		assert_eq!(Some(1), palette.get_cost([30, 220, 10]));
+	}
+	#[test]
+	#[should_panic]
+	fn palette_missing_colour() {
+		let palette = ColourPalette::new(PaletteMatch::Exact).with_colour([0, 255, 0], 1);
+		HeightmapCosts::Palette(palette).get_cost([255, 0, 0, 255]);
+	}
+}
@@ -0,0 +1,376 @@
+//! Reading of [LDtk](https://ldtk.io/) projects so that the values of an IntGrid layer can be translated into [CostField](crate::prelude::CostField) values.
+//!
+//! Each cell of the IntGrid layer represents a [FieldCell](crate::prelude::FieldCell). Unlike the other importers a malformed project is reported as an [LdtkError] rather than a panic
+//!
+
+use std::{collections::BTreeMap, fmt, path::Path};
+
+/// Describes which IntGrid layer of an LDtk project is read and how its values are translated into [CostField](crate::prelude::CostField) values.
+///
+/// A cell is given the cost assigned to its IntGrid value with [LdtkCosts::with_value_cost], values without an assigned cost use the default cost and empty cells (a value of `0`) use the empty cost
+#[derive(Clone, Debug)]
+pub struct LdtkCosts {
+	/// Costs of IntGrid values
+	values: BTreeMap<u32, u8>,
+	/// Cost of IntGrid values without a cost of their own
+	default_cost: u8,
+	/// Cost of empty cells
+	empty_cost: u8,
+	/// Identifier of the level to read, the first level is read if [None]
+	level: Option<String>,
+	/// Identifier of the IntGrid layer to read, the first IntGrid layer of the level is read if [None]
+	layer: Option<String>,
+}
+
+impl Default for LdtkCosts {
+	fn default() -> Self {
+		LdtkCosts {
+			values: BTreeMap::new(),
+			default_cost: 1,
+			empty_cost: 1,
+			level: None,
+			layer: None,
+		}
+	}
+}
+
+impl LdtkCosts {
+	/// Give every cell with the IntGrid `value` a cost
+	pub fn with_value_cost(mut self, value: u32, cost: u8) -> Self {
+		self.values.insert(value, cost);
+		self
+	}
+	/// Set the cost of IntGrid values without a cost of their own
+	pub fn with_default_cost(mut self, cost: u8) -> Self {
+		self.default_cost = cost;
+		self
+	}
+	/// Set the cost of empty cells
+	pub fn with_empty_cost(mut self, cost: u8) -> Self {
+		self.empty_cost = cost;
+		self
+	}
+	/// Read the level with the identifier `name` rather than the first level
+	pub fn with_level(mut self, name: &str) -> Self {
+		self.level = Some(name.to_string());
+		self
+	}
+	/// Read the IntGrid layer with the identifier `name` rather than the first IntGrid layer
+	pub fn with_layer(mut self, name: &str) -> Self {
+		self.layer = Some(name.to_string());
+		self
+	}
+	/// Get the cost of IntGrid values without a cost of their own
+	pub fn get_default_cost(&self) -> u8 {
+		self.default_cost
+	}
+	/// Get the cost of empty cells
+	pub fn get_empty_cost(&self) -> u8 {
+		self.empty_cost
+	}
+	/// Get the identifier of the level to read
+	pub fn get_level(&self) -> Option<&str> {
+		self.level.as_deref()
+	}
+	/// Get the identifier of the IntGrid layer to read
+	pub fn get_layer(&self) -> Option<&str> {
+		self.layer.as_deref()
+	}
+	/// Get the cost of an IntGrid `value`
+	pub fn get_cost(&self, value: u32) -> u8 {
+		if value == 0 {
+			self.empty_cost
+		} else {
+			self.values
+				.get(&value)
+				.copied()
+				.unwrap_or(self.default_cost)
+		}
+	}
+}
+
+/// Problems encountered reading an LDtk project
+#[derive(Debug)]
+pub enum LdtkError {
+	/// The project or an external level file could not be read
+	Io(std::io::Error),
+	/// The project or an external level file is not valid LDtk JSON
+	Json(serde_json::Error),
+	/// The project has no level with the identifier, or no levels at all when [None]
+	LevelNotFound(Option<String>),
+	/// The level has no IntGrid layer with the identifier, or no IntGrid layers at all when [None]
+	LayerNotFound(Option<String>),
+	/// The layer exists but is not an IntGrid layer
+	NotIntGrid(String),
+	/// The number of values in the layer doesn't match the number of cells it claims to have
+	InvalidLayer {
+		/// Identifier of the layer
+		layer: String,
+		/// Number of cells of the layer
+		expected: usize,
+		/// Number of values found
+		found: usize,
+	},
+	/// The size of the grid doesn't match the number of [FieldCell](crate::prelude::FieldCell)s of the map, sizes are `(columns, rows)`
+	SizeMismatch {
+		/// Number of columns and rows of FieldCells in the map
+		expected: (usize, usize),
+		/// Number of columns and rows in the IntGrid layer
+		found: (usize, usize),
+	},
+}
+
+impl fmt::Display for LdtkError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			LdtkError::Io(e) => write!(f, "Failed reading LDtk file: {}", e),
+			LdtkError::Json(e) => write!(f, "Failed deserializing LDtk file: {}", e),
+			LdtkError::LevelNotFound(Some(name)) => write!(f, "LDtk level {} not found", name),
+			LdtkError::LevelNotFound(None) => write!(f, "LDtk project has no levels"),
+			LdtkError::LayerNotFound(Some(name)) => write!(f, "LDtk layer {} not found", name),
+			LdtkError::LayerNotFound(None) => write!(f, "LDtk level has no IntGrid layers"),
+			LdtkError::NotIntGrid(name) => write!(f, "LDtk layer {} is not an IntGrid layer", name),
+			LdtkError::InvalidLayer {
+				layer,
+				expected,
+				found,
+			} => write!(
+				f,
+				"LDtk layer {} has {} values, expected {}",
+				layer, found, expected
+			),
+			LdtkError::SizeMismatch { expected, found } => write!(
+				f,
+				"LDtk IntGrid has incorrect size, expected {}x{} cells, found {}x{}",
+				expected.0, expected.1, found.0, found.1
+			),
+		}
+	}
+}
+
+impl std::error::Error for LdtkError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			LdtkError::Io(e) => Some(e),
+			LdtkError::Json(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+impl From<std::io::Error> for LdtkError {
+	fn from(e: std::io::Error) -> Self {
+		LdtkError::Io(e)
+	}
+}
+
+impl From<serde_json::Error> for LdtkError {
+	fn from(e: serde_json::Error) -> Self {
+		LdtkError::Json(e)
+	}
+}
+
+/// The values of an IntGrid layer read from an LDtk project
+#[derive(Clone, Debug)]
+pub struct LdtkIntGrid {
+	/// Number of cell columns
+	width: usize,
+	/// Number of cell rows
+	height: usize,
+	/// IntGrid values in rows from the top left of the level, `0` means the cell is empty
+	values: Vec<u32>,
+}
+
+impl LdtkIntGrid {
+	/// Read the IntGrid layer selected by `costs` from the `.ldtk` project at `path`. Levels saved as separate `.ldtkl` files are read relative to the project
+	pub fn from_file(path: &str, costs: &LdtkCosts) -> Result<Self, LdtkError> {
+		let contents = std::fs::read_to_string(path)?;
+		let project: JsonProject = serde_json::from_str(&contents)?;
+		// multi-world projects keep their levels within each world
+		let mut levels = project
+			.levels
+			.iter()
+			.chain(project.worlds.iter().flat_map(|w| w.levels.iter()));
+		let level = match &costs.level {
+			Some(name) => levels
+				.find(|l| l.identifier == *name)
+				.ok_or_else(|| LdtkError::LevelNotFound(Some(name.clone())))?,
+			None => levels.next().ok_or(LdtkError::LevelNotFound(None))?,
+		};
+		let layers = match (&level.layer_instances, &level.external_rel_path) {
+			(Some(layers), _) => layers.clone(),
+			(None, Some(external)) => {
+				let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+				let contents = std::fs::read_to_string(directory.join(external))?;
+				let level: JsonLevel = serde_json::from_str(&contents)?;
+				level.layer_instances.unwrap_or_default()
+			}
+			(None, None) => Vec::new(),
+		};
+		let layer = match &costs.layer {
+			Some(name) => {
+				let layer = layers
+					.into_iter()
+					.find(|l| l.identifier == *name)
+					.ok_or_else(|| LdtkError::LayerNotFound(Some(name.clone())))?;
+				if layer.kind != "IntGrid" {
+					return Err(LdtkError::NotIntGrid(layer.identifier));
+				}
+				layer
+			}
+			None => layers
+				.into_iter()
+				.find(|l| l.kind == "IntGrid")
+				.ok_or(LdtkError::LayerNotFound(None))?,
+		};
+		if layer.int_grid_csv.len() != layer.width * layer.height {
+			return Err(LdtkError::InvalidLayer {
+				layer: layer.identifier,
+				expected: layer.width * layer.height,
+				found: layer.int_grid_csv.len(),
+			});
+		}
+		Ok(LdtkIntGrid {
+			width: layer.width,
+			height: layer.height,
+			values: layer.int_grid_csv,
+		})
+	}
+	/// Get the number of cell columns
+	pub fn get_width(&self) -> usize {
+		self.width
+	}
+	/// Get the number of cell rows
+	pub fn get_height(&self) -> usize {
+		self.height
+	}
+	/// Get the IntGrid value of the cell at `(column, row)` from the top left of the level
+	pub fn get_value(&self, column: usize, row: usize) -> u32 {
+		self.values[row * self.width + column]
+	}
+	/// Get the cost of the cell at `(column, row)` from the top left of the level
+	pub fn get_cost(&self, column: usize, row: usize, costs: &LdtkCosts) -> u8 {
+		costs.get_cost(self.get_value(column, row))
+	}
+}
+
+/// An `.ldtk` project
+#[derive(serde::Deserialize)]
+struct JsonProject {
+	/// Levels of a single world project
+	#[serde(default)]
+	levels: Vec<JsonLevel>,
+	/// Worlds of a multi-world project
+	#[serde(default)]
+	worlds: Vec<JsonWorld>,
+}
+
+/// A world of a multi-world `.ldtk` project
+#[derive(serde::Deserialize)]
+struct JsonWorld {
+	/// Levels of the world
+	#[serde(default)]
+	levels: Vec<JsonLevel>,
+}
+
+/// A level of an `.ldtk` project, or the contents of an external `.ldtkl` level
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonLevel {
+	/// Identifier of the level
+	#[serde(default)]
+	identifier: String,
+	/// Path of the `.ldtkl` file holding the level when levels are saved separately
+	external_rel_path: Option<String>,
+	/// Layers of the level, [None] when the level is saved separately
+	layer_instances: Option<Vec<JsonLayer>>,
+}
+
+/// A layer of a level
+#[derive(Clone, serde::Deserialize)]
+struct JsonLayer {
+	/// Identifier of the layer
+	#[serde(rename = "__identifier")]
+	identifier: String,
+	/// Kind of layer, such as `IntGrid` or `Entities`
+	#[serde(rename = "__type")]
+	kind: String,
+	/// Number of cell columns
+	#[serde(rename = "__cWid")]
+	width: usize,
+	/// Number of cell rows
+	#[serde(rename = "__cHei")]
+	height: usize,
+	/// IntGrid values in rows from the top left of the level
+	#[serde(rename = "intGridCsv", default)]
+	int_grid_csv: Vec<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Path of a fixture project
+	fn fixture(name: &str) -> String {
+		env!("CARGO_MANIFEST_DIR").to_string() + "/assets/ldtk/" + name
+	}
+	#[test]
+	fn int_grid_costs() {
+		let costs = LdtkCosts::default()
+			.with_value_cost(1, 255)
+			.with_value_cost(2, 50);
+		let grid = LdtkIntGrid::from_file(&fixture("project.ldtk"), &costs).unwrap();
+		assert_eq!((20, 20), (grid.get_width(), grid.get_height()));
+		// empty
+		assert_eq!(1, grid.get_cost(0, 0, &costs));
+		// wall
+		assert_eq!(255, grid.get_cost(10, 5, &costs));
+		// mud
+		assert_eq!(50, grid.get_cost(3, 3, &costs));
+		// water has no cost of its own
+		assert_eq!(3, grid.get_value(15, 15));
+		assert_eq!(1, grid.get_cost(15, 15, &costs));
+		assert_eq!(
+			9,
+			grid.get_cost(15, 15, &costs.clone().with_default_cost(9))
+		);
+	}
+	#[test]
+	fn external_level_and_layer() {
+		let costs = LdtkCosts::default()
+			.with_level("Cave")
+			.with_layer("Hazards");
+		let grid = LdtkIntGrid::from_file(&fixture("project.ldtk"), &costs).unwrap();
+		assert_eq!((10, 10), (grid.get_width(), grid.get_height()));
+		assert_eq!(1, grid.get_value(4, 6));
+		assert_eq!(0, grid.get_value(0, 0));
+	}
+	#[test]
+	fn errors() {
+		let path = fixture("project.ldtk");
+		let missing_level = LdtkCosts::default().with_level("Nowhere");
+		assert!(matches!(
+			LdtkIntGrid::from_file(&path, &missing_level),
+			Err(LdtkError::LevelNotFound(Some(_)))
+		));
+		let missing_layer = LdtkCosts::default().with_layer("Nothing");
+		assert!(matches!(
+			LdtkIntGrid::from_file(&path, &missing_layer),
+			Err(LdtkError::LayerNotFound(Some(_)))
+		));
+		let entities = LdtkCosts::default().with_layer("Entities");
+		assert!(matches!(
+			LdtkIntGrid::from_file(&path, &entities),
+			Err(LdtkError::NotIntGrid(_))
+		));
+		assert!(matches!(
+			LdtkIntGrid::from_file(&fixture("missing.ldtk"), &LdtkCosts::default()),
+			Err(LdtkError::Io(_))
+		));
+		let not_ldtk = env!("CARGO_MANIFEST_DIR").to_string() + "/assets/tiled/map.tmx";
+		assert!(matches!(
+			LdtkIntGrid::from_file(&not_ldtk, &LdtkCosts::default()),
+			Err(LdtkError::Json(_))
+		));
+	}
+}
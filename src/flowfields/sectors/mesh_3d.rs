@@ -0,0 +1,415 @@
+//! Rasterising 3d geometry, such as terrain meshes and static level geometry, onto the `x-z` grid of [FieldCell]s to find the [CostField](crate::prelude::CostField) value of each cell.
+//!
+//! A vertical line is cast through the centre of each [FieldCell] and every triangle it passes through is recorded. The floor of the cell is the highest upward facing surface with at least the clearance height of free space above it, so a bridge or the top of a building is chosen over the ground beneath it. A surface isn't free when the first surface above it faces upwards, meaning it is inside a solid object such as a crate sitting on the ground. The cell is then impassable if:
+//! - no surface has enough free space above it, such as beneath a low ceiling or overhang
+//! - the floor is steeper than the maximum walkable slope
+//! - a neighbouring floor is lower than where the surface of the floor would continue to by more than the maximum step height, such as the edge of a ledge or the top of a wall
+//!
+//! Geometry is only sampled at the centre of each cell so objects thinner than a [FieldCell] may be missed. For several storeys of geometry build a layer of sectors for each storey from the geometry of that storey
+//!
+
+use crate::prelude::*;
+use bevy::{prelude::*, render::mesh::PrimitiveTopology};
+
+/// Distance within which two surfaces are treated as touching
+const SURFACE_EPSILON: f32 = 0.0001;
+
+/// Describes how 3d geometry is translated into [CostField](crate::prelude::CostField) values
+#[derive(Clone, Copy, Debug)]
+pub struct MeshCostSettings {
+	/// Steepest angle in degrees from the horizontal which can be walked on
+	max_slope: f32,
+	/// Greatest difference in height between neighbouring cells which can be stepped up
+	max_step: f32,
+	/// Free space required above the floor of a cell
+	clearance: f32,
+	/// Cost of cells which can be walked on
+	walkable_cost: u8,
+	/// Cost of cells without any geometry beneath them
+	void_cost: u8,
+}
+
+impl Default for MeshCostSettings {
+	fn default() -> Self {
+		MeshCostSettings {
+			max_slope: 45.0,
+			max_step: 0.5,
+			clearance: 2.0,
+			walkable_cost: 1,
+			void_cost: 255,
+		}
+	}
+}
+
+impl MeshCostSettings {
+	/// Set the steepest angle in degrees from the horizontal which can be walked on
+	pub fn with_max_slope(mut self, degrees: f32) -> Self {
+		self.max_slope = degrees;
+		self
+	}
+	/// Set the greatest difference in height between neighbouring cells which can be stepped up
+	pub fn with_max_step(mut self, height: f32) -> Self {
+		self.max_step = height;
+		self
+	}
+	/// Set the free space required above the floor of a cell
+	pub fn with_clearance(mut self, height: f32) -> Self {
+		self.clearance = height;
+		self
+	}
+	/// Set the cost of cells which can be walked on
+	pub fn with_walkable_cost(mut self, cost: u8) -> Self {
+		self.walkable_cost = cost;
+		self
+	}
+	/// Set the cost of cells without any geometry beneath them
+	pub fn with_void_cost(mut self, cost: u8) -> Self {
+		self.void_cost = cost;
+		self
+	}
+	/// Get the steepest angle in degrees from the horizontal which can be walked on
+	pub fn get_max_slope(&self) -> f32 {
+		self.max_slope
+	}
+	/// Get the greatest difference in height between neighbouring cells which can be stepped up
+	pub fn get_max_step(&self) -> f32 {
+		self.max_step
+	}
+	/// Get the free space required above the floor of a cell
+	pub fn get_clearance(&self) -> f32 {
+		self.clearance
+	}
+	/// Get the cost of cells which can be walked on
+	pub fn get_walkable_cost(&self) -> u8 {
+		self.walkable_cost
+	}
+	/// Get the cost of cells without any geometry beneath them
+	pub fn get_void_cost(&self) -> u8 {
+		self.void_cost
+	}
+}
+
+/// A surface found beneath or above the centre of a [FieldCell]
+#[derive(Clone, Copy, Debug)]
+struct SurfaceHit {
+	/// Height of the surface
+	y: f32,
+	/// Unit normal of the triangle
+	normal: Vec3,
+}
+
+/// The floor of a [FieldCell]
+#[derive(Clone, Copy, Debug)]
+struct CellFloor {
+	/// Height of the floor
+	y: f32,
+	/// Unit normal of the floor
+	normal: Vec3,
+	/// Whether the floor can be stood on, ignoring neighbouring cells
+	walkable: bool,
+}
+
+impl CellFloor {
+	/// Get the height of the plane of the floor at an `offset` from the centre of the cell in the x-z plane
+	fn get_height_at(&self, offset: Vec2) -> f32 {
+		self.y - (self.normal.x * offset.x + self.normal.z * offset.y) / self.normal.y
+	}
+}
+
+/// Get the triangles of a mesh in world space after applying its `transform`. Meshes which aren't a `TriangleList` or `TriangleStrip` produce no triangles
+pub fn get_mesh_triangles(mesh: &Mesh, transform: &Transform) -> Vec<[Vec3; 3]> {
+	let Some(points) = mesh
+		.attribute(Mesh::ATTRIBUTE_POSITION)
+		.and_then(|p| p.as_float3())
+	else {
+		warn!("Mesh has no vertex positions for use with Flowfields");
+		return vec![];
+	};
+	let indices: Vec<usize> = match mesh.indices() {
+		Some(indices) => indices.iter().collect(),
+		None => (0..points.len()).collect(),
+	};
+	let vertex = |i: usize| transform.transform_point(Vec3::from(points[indices[i]]));
+	let mut triangles = vec![];
+	match mesh.primitive_topology() {
+		PrimitiveTopology::TriangleList => {
+			for n in (0..indices.len() / 3).map(|t| t * 3) {
+				triangles.push([vertex(n), vertex(n + 1), vertex(n + 2)]);
+			}
+		}
+		PrimitiveTopology::TriangleStrip => {
+			for n in 0..indices.len().saturating_sub(2) {
+				// every other triangle of a strip has reversed winding
+				if n % 2 == 0 {
+					triangles.push([vertex(n), vertex(n + 1), vertex(n + 2)]);
+				} else {
+					triangles.push([vertex(n + 1), vertex(n), vertex(n + 2)]);
+				}
+			}
+		}
+		_ => {
+			warn!("Mesh topology must be of TriangleList or TriangleStrip for use with Flowfields");
+		}
+	}
+	triangles
+}
+
+/// Find the cost of every [FieldCell] of the map from world space `triangles`, returned as rows starting from the top left
+pub fn rasterise_triangles(
+	map_dimensions: &MapDimensions,
+	triangles: &[[Vec3; 3]],
+	settings: &MeshCostSettings,
+) -> Vec<Vec<u8>> {
+	let columns = map_dimensions.get_total_field_cell_columns();
+	let rows = map_dimensions.get_total_field_cell_rows();
+	let min_normal_y = settings.max_slope.to_radians().cos();
+	let buckets = bucket_triangles(map_dimensions, triangles);
+	// find the floor of each cell
+	let mut floors: Vec<Vec<Option<CellFloor>>> = vec![vec![None; columns]; rows];
+	for (row, floor_row) in floors.iter_mut().enumerate() {
+		for (column, floor) in floor_row.iter_mut().enumerate() {
+			let sector_id = SectorID::new(
+				(column / FIELD_RESOLUTION) as u32,
+				(row / FIELD_RESOLUTION) as u32,
+			);
+			let field_cell = FieldCell::new(column % FIELD_RESOLUTION, row % FIELD_RESOLUTION);
+			let Some(centre) = map_dimensions.get_xyz_from_field_sector(sector_id, field_cell)
+			else {
+				continue;
+			};
+			let mut hits: Vec<SurfaceHit> = buckets[row][column]
+				.iter()
+				.filter_map(|i| get_surface_hit(&triangles[*i], centre.x, centre.z))
+				.collect();
+			hits.sort_by(|a, b| a.y.total_cmp(&b.y));
+			*floor = find_floor(&hits, settings, min_normal_y);
+		}
+	}
+	// translate floors into costs, the upper side of a ledge is impassable
+	let cell_size = map_dimensions.get_field_cell_unit_size();
+	let mut costs = vec![vec![settings.void_cost; columns]; rows];
+	for row in 0..rows {
+		for column in 0..columns {
+			let Some(floor) = floors[row][column] else {
+				continue;
+			};
+			// neighbouring floors and their offset in the x-z plane
+			let mut neighbours = vec![];
+			if row > 0 {
+				neighbours.push((floors[row - 1][column], Vec2::new(0.0, -cell_size)));
+			}
+			if row + 1 < rows {
+				neighbours.push((floors[row + 1][column], Vec2::new(0.0, cell_size)));
+			}
+			if column > 0 {
+				neighbours.push((floors[row][column - 1], Vec2::new(-cell_size, 0.0)));
+			}
+			if column + 1 < columns {
+				neighbours.push((floors[row][column + 1], Vec2::new(cell_size, 0.0)));
+			}
+			// a slope continues smoothly into its neighbours, a ledge drops away from where the surface of the floor would be
+			let is_ledge = neighbours.iter().any(|(neighbour, offset)| {
+				neighbour.is_some_and(|n| floor.get_height_at(*offset) - n.y > settings.max_step)
+			});
+			costs[row][column] = if floor.walkable && !is_ledge {
+				settings.walkable_cost
+			} else {
+				255
+			};
+		}
+	}
+	costs
+}
+
+/// Group the indices of `triangles` by the cells of the map whose centre may lie within the bounds of the triangle in the x-z plane, returned as rows starting from the top left. Each cell then only needs to test the triangles of its bucket
+fn bucket_triangles(
+	map_dimensions: &MapDimensions,
+	triangles: &[[Vec3; 3]],
+) -> Vec<Vec<Vec<usize>>> {
+	let columns = map_dimensions.get_total_field_cell_columns();
+	let rows = map_dimensions.get_total_field_cell_rows();
+	let mut buckets = vec![vec![Vec::new(); columns]; rows];
+	for (i, triangle) in triangles.iter().enumerate() {
+		let grid = triangle.map(|p| map_dimensions.get_grid_position_from_xyz(p));
+		let min = grid[0].min(grid[1]).min(grid[2]);
+		let max = grid[0].max(grid[1]).max(grid[2]);
+		if max.x < 0.0 || max.y < 0.0 || min.x > columns as f32 || min.y > rows as f32 {
+			continue;
+		}
+		// the centre of a cell sits half a cell from its top left corner, err on the side of including a cell
+		let first_column = (min.x - 0.5).floor().max(0.0) as usize;
+		let last_column = ((max.x - 0.5).ceil().max(0.0) as usize).min(columns - 1);
+		let first_row = (min.y - 0.5).floor().max(0.0) as usize;
+		let last_row = ((max.y - 0.5).ceil().max(0.0) as usize).min(rows - 1);
+		for bucket_row in buckets.iter_mut().take(last_row + 1).skip(first_row) {
+			for bucket in bucket_row
+				.iter_mut()
+				.take(last_column + 1)
+				.skip(first_column)
+			{
+				bucket.push(i);
+			}
+		}
+	}
+	buckets
+}
+
+/// Choose the floor of a cell from the surfaces beneath and above its centre, sorted from lowest to highest. The highest upward facing surface with the clearance height of free space above it is the floor, if there isn't one the highest upward facing surface is used and can't be walked on. Returns [None] if there is no upward facing surface
+fn find_floor(
+	hits: &[SurfaceHit],
+	settings: &MeshCostSettings,
+	min_normal_y: f32,
+) -> Option<CellFloor> {
+	let mut floor = None;
+	let mut highest = None;
+	for surface in hits.iter().filter(|h| h.normal.y > 0.0) {
+		highest = Some(surface);
+		// the first surface above, a downward face touching the surface means something rests on it
+		let above = hits.iter().find(|h| {
+			h.y > surface.y + SURFACE_EPSILON
+				|| (h.y >= surface.y - SURFACE_EPSILON && h.normal.y <= 0.0)
+		});
+		// looking up at the back of an upward face means the surface is inside something solid
+		let is_clear = above.map_or(true, |h| {
+			h.normal.y <= 0.0 && h.y - surface.y >= settings.clearance
+		});
+		if is_clear {
+			floor = Some(CellFloor {
+				y: surface.y,
+				normal: surface.normal,
+				walkable: surface.normal.y >= min_normal_y,
+			});
+		}
+	}
+	floor.or(highest.map(|surface| CellFloor {
+		y: surface.y,
+		normal: surface.normal,
+		walkable: false,
+	}))
+}
+
+/// If a vertical line at `(x, z)` passes through the `triangle` get the height and normal of the surface it passes through
+fn get_surface_hit(triangle: &[Vec3; 3], x: f32, z: f32) -> Option<SurfaceHit> {
+	let [a, b, c] = *triangle;
+	// barycentric coordinates of the point projected onto the x-z plane
+	let denominator = (b.z - c.z) * (a.x - c.x) + (c.x - b.x) * (a.z - c.z);
+	// vertical triangles have no area when viewed from above
+	if denominator.abs() < f32::EPSILON {
+		return None;
+	}
+	let u = ((b.z - c.z) * (x - c.x) + (c.x - b.x) * (z - c.z)) / denominator;
+	let v = ((c.z - a.z) * (x - c.x) + (a.x - c.x) * (z - c.z)) / denominator;
+	let w = 1.0 - u - v;
+	if u < 0.0 || v < 0.0 || w < 0.0 {
+		return None;
+	}
+	Some(SurfaceHit {
+		y: u * a.y + v * b.y + w * c.y,
+		normal: (b - a).cross(c - a).normalize(),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn solid_overhang_and_clearance() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		let ground = Plane3d::default().mesh().size(20.0, 20.0).build();
+		let crate_mesh = Mesh::from(Cuboid::new(2.0, 1.0, 2.0));
+		let roof = Mesh::from(Cuboid::new(4.0, 0.2, 4.0));
+		let ceiling = Plane3d::default().mesh().size(4.0, 4.0).build();
+		let facing_down = Quat::from_rotation_x(std::f32::consts::PI);
+		let meshes = [
+			(&ground, Transform::IDENTITY),
+			(&crate_mesh, Transform::from_xyz(5.0, 0.5, 5.0)),
+			(&roof, Transform::from_xyz(-5.0, 1.1, -5.0)),
+			(
+				&ceiling,
+				Transform::from_xyz(5.0, 1.0, -5.0).with_rotation(facing_down),
+			),
+			(
+				&ceiling,
+				Transform::from_xyz(-5.0, 3.0, 5.0).with_rotation(facing_down),
+			),
+		];
+		let triangles: Vec<[Vec3; 3]> = meshes
+			.iter()
+			.flat_map(|(mesh, transform)| get_mesh_triangles(mesh, transform))
+			.collect();
+		let costs = rasterise_triangles(&map_dimensions, &triangles, &MeshCostSettings::default());
+		// open ground
+		assert_eq!(1, costs[0][0]);
+		// on top of the crate, too high to step down from
+		assert_eq!(255, costs[14][14]);
+		assert_eq!(255, costs[15][15]);
+		assert_eq!(1, costs[16][16]);
+		// the top of the roof is the highest floor, it drops away at the edges
+		assert_eq!(1, costs[5][5]);
+		assert_eq!(255, costs[3][5]);
+		// under the low ceiling
+		assert_eq!(255, costs[5][15]);
+		// under the high ceiling
+		assert_eq!(1, costs[15][5]);
+	}
+	#[test]
+	fn slope_and_step() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		// a 30 degree incline
+		let incline = Plane3d::default().mesh().size(30.0, 30.0).build();
+		let triangles = get_mesh_triangles(
+			&incline,
+			&Transform::from_rotation(Quat::from_rotation_x(30.0_f32.to_radians())),
+		);
+		let settings = MeshCostSettings::default();
+		let costs = rasterise_triangles(&map_dimensions, &triangles, &settings);
+		assert!(costs.iter().flatten().all(|c| *c == 1));
+		let costs =
+			rasterise_triangles(&map_dimensions, &triangles, &settings.with_max_slope(20.0));
+		assert!(costs.iter().flatten().all(|c| *c == 255));
+		// a ledge one unit high along x = 0
+		let half = Plane3d::default().mesh().size(10.0, 20.0).build();
+		let mut triangles = get_mesh_triangles(&half, &Transform::from_xyz(-5.0, 0.0, 0.0));
+		triangles.extend(get_mesh_triangles(
+			&half,
+			&Transform::from_xyz(5.0, 1.0, 0.0),
+		));
+		let costs = rasterise_triangles(&map_dimensions, &triangles, &settings);
+		assert_eq!(1, costs[3][9]);
+		assert_eq!(255, costs[3][10]);
+		assert_eq!(1, costs[3][11]);
+		let costs = rasterise_triangles(&map_dimensions, &triangles, &settings.with_max_step(1.5));
+		assert_eq!(1, costs[3][10]);
+	}
+	#[test]
+	fn triangle_buckets() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		// covers the centres of the cells in columns 2 to 4 and rows 7 to 8
+		let triangle = [
+			Vec3::new(-8.0, 0.0, -3.0),
+			Vec3::new(-5.0, 0.0, -3.0),
+			Vec3::new(-8.0, 0.0, -1.0),
+		];
+		let outside = [
+			Vec3::new(30.0, 0.0, 30.0),
+			Vec3::new(31.0, 0.0, 30.0),
+			Vec3::new(30.0, 0.0, 31.0),
+		];
+		let buckets = bucket_triangles(&map_dimensions, &[triangle, outside]);
+		assert_eq!(vec![0], buckets[7][2]);
+		assert_eq!(vec![0], buckets[8][4]);
+		assert!(buckets[0][0].is_empty());
+		assert!(buckets[12][12].is_empty());
+		assert!(buckets.iter().flatten().flatten().all(|i| *i == 0));
+	}
+	#[test]
+	fn void_cost() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		let island = Plane3d::default().mesh().size(4.0, 4.0).build();
+		let triangles = get_mesh_triangles(&island, &Transform::IDENTITY);
+		let settings = MeshCostSettings::default().with_void_cost(200);
+		let costs = rasterise_triangles(&map_dimensions, &triangles, &settings);
+		assert_eq!(200, costs[0][0]);
+		assert_eq!(1, costs[10][10]);
+	}
+}
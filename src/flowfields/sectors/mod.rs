@@ -3,21 +3,44 @@
 //!
 //!
 
+pub mod ascii;
+#[cfg(feature = "3d")]
+pub mod elevation;
+#[cfg(feature = "heightmap")]
+pub mod heightmap;
+#[cfg(feature = "ldtk")]
+pub mod ldtk;
+#[cfg(feature = "3d")]
+pub mod mesh_3d;
 pub mod sector_cost;
+pub mod sector_density;
+pub mod sector_modifiers;
 pub mod sector_portals;
+#[cfg(feature = "tiled")]
+pub mod tiled;
 
 use crate::prelude::*;
 use bevy::prelude::*;
 
-/// Unique ID of a sector
-#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+/// Unique ID of a sector.
+///
+/// Sectors sit on a grid of `(column, row)` and each grid is a layer of the map, for instance a floor of a building, a bridge over a road or a cave beneath terrain. Every layer shares the same [MapDimensions] and layer `0` is the ground that world positions resolve to. Layers are only joined together by explicit [LayerConnection]s
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default, Hash, Reflect)]
-pub struct SectorID((u32, u32));
+pub struct SectorID((u32, u32), u32);
 
 impl SectorID {
-	/// Create a new instance of [SectorID]
+	/// Create a new instance of [SectorID] on layer `0`
 	pub fn new(column: u32, row: u32) -> Self {
-		SectorID((column, row))
+		SectorID((column, row), 0)
+	}
+	/// Create a new instance of [SectorID] on a particular `layer`
+	pub fn new_layered(column: u32, row: u32, layer: u32) -> Self {
+		SectorID((column, row), layer)
+	}
+	/// Get the same sector on a different `layer`
+	pub fn with_layer(mut self, layer: u32) -> Self {
+		self.1 = layer;
+		self
 	}
 	/// Get the sector `(column, row)` tuple
 	pub fn get(&self) -> (u32, u32) {
@@ -31,6 +54,67 @@ impl SectorID {
 	pub fn get_row(&self) -> u32 {
 		self.0 .1
 	}
+	/// Get the layer the sector is on
+	pub fn get_layer(&self) -> u32 {
+		self.1
+	}
+}
+
+// a sector on the ground layer is written as `SectorID((column, row))` so that existing assets remain valid, other layers are written as `SectorID((column, row, layer))`
+#[cfg(feature = "serde")]
+impl serde::Serialize for SectorID {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		if self.1 == 0 {
+			serializer.serialize_newtype_struct("SectorID", &self.0)
+		} else {
+			serializer.serialize_newtype_struct("SectorID", &(self.0 .0, self.0 .1, self.1))
+		}
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SectorID {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		/// Reads a `(column, row)` or `(column, row, layer)` tuple
+		struct SectorTuple;
+		impl<'de> serde::de::Visitor<'de> for SectorTuple {
+			type Value = SectorID;
+			fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+				formatter.write_str("a tuple of (column, row) or (column, row, layer)")
+			}
+			fn visit_seq<A: serde::de::SeqAccess<'de>>(
+				self,
+				mut seq: A,
+			) -> Result<SectorID, A::Error> {
+				let column = seq
+					.next_element()?
+					.ok_or_else(|| serde::de::Error::invalid_length(0, &self))?;
+				let row = seq
+					.next_element()?
+					.ok_or_else(|| serde::de::Error::invalid_length(1, &self))?;
+				let layer = seq.next_element()?.unwrap_or(0);
+				Ok(SectorID::new_layered(column, row, layer))
+			}
+		}
+		/// Reads the `SectorID(...)` newtype wrapping the tuple
+		struct SectorNewtype;
+		impl<'de> serde::de::Visitor<'de> for SectorNewtype {
+			type Value = SectorID;
+			fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+				formatter.write_str("a SectorID")
+			}
+			fn visit_newtype_struct<D: serde::Deserializer<'de>>(
+				self,
+				deserializer: D,
+			) -> Result<SectorID, D::Error> {
+				deserializer.deserialize_tuple(3, SectorTuple)
+			}
+			fn visit_seq<A: serde::de::SeqAccess<'de>>(self, seq: A) -> Result<SectorID, A::Error> {
+				SectorTuple.visit_seq(seq)
+			}
+		}
+		deserializer.deserialize_newtype_struct("SectorID", SectorNewtype)
+	}
 }
 
 /// The dimensions of the world
@@ -428,16 +512,16 @@ impl MapDimensions {
 		sector_id: &SectorID,
 	) -> Option<SectorID> {
 		match ordinal {
-			Ordinal::North => sector_id
-				.get_row()
-				.checked_sub(1)
-				.map(|row| SectorID::new(sector_id.get_column(), row)),
+			Ordinal::North => sector_id.get_row().checked_sub(1).map(|row| {
+				SectorID::new_layered(sector_id.get_column(), row, sector_id.get_layer())
+			}),
 			Ordinal::East => {
 				if sector_id.get_column() + 1 < self.get_length() / self.get_sector_resolution() - 1
 				{
-					Some(SectorID::new(
+					Some(SectorID::new_layered(
 						sector_id.get_column() + 1,
 						sector_id.get_row(),
+						sector_id.get_layer(),
 					))
 				} else {
 					None
@@ -445,24 +529,28 @@ impl MapDimensions {
 			}
 			Ordinal::South => {
 				if sector_id.get_row() + 1 < self.get_depth() / self.get_sector_resolution() - 1 {
-					Some(SectorID::new(
+					Some(SectorID::new_layered(
 						sector_id.get_column(),
 						sector_id.get_row() + 1,
+						sector_id.get_layer(),
 					))
 				} else {
 					None
 				}
 			}
-			Ordinal::West => sector_id
-				.get_column()
-				.checked_sub(1)
-				.map(|column| SectorID::new(column, sector_id.get_row())),
+			Ordinal::West => sector_id.get_column().checked_sub(1).map(|column| {
+				SectorID::new_layered(column, sector_id.get_row(), sector_id.get_layer())
+			}),
 			Ordinal::NorthEast => {
 				if let Some(row) = sector_id.get_row().checked_sub(1) {
 					if sector_id.get_column() + 1
 						< self.get_length() / self.get_sector_resolution() - 1
 					{
-						Some(SectorID::new(sector_id.get_column() + 1, row))
+						Some(SectorID::new_layered(
+							sector_id.get_column() + 1,
+							row,
+							sector_id.get_layer(),
+						))
 					} else {
 						None
 					}
@@ -475,9 +563,10 @@ impl MapDimensions {
 					if sector_id.get_column() + 1
 						< self.get_length() / self.get_sector_resolution() - 1
 					{
-						Some(SectorID::new(
+						Some(SectorID::new_layered(
 							sector_id.get_column() + 1,
 							sector_id.get_row() + 1,
+							sector_id.get_layer(),
 						))
 					} else {
 						None
@@ -488,10 +577,13 @@ impl MapDimensions {
 			}
 			Ordinal::SouthWest => {
 				if sector_id.get_row() + 1 < self.get_depth() / self.get_sector_resolution() - 1 {
-					sector_id
-						.get_column()
-						.checked_sub(1)
-						.map(|column| SectorID::new(column, sector_id.get_row() + 1))
+					sector_id.get_column().checked_sub(1).map(|column| {
+						SectorID::new_layered(
+							column,
+							sector_id.get_row() + 1,
+							sector_id.get_layer(),
+						)
+					})
 				} else {
 					None
 				}
@@ -501,7 +593,7 @@ impl MapDimensions {
 					sector_id
 						.get_column()
 						.checked_sub(1)
-						.map(|column| SectorID::new(column, row))
+						.map(|column| SectorID::new_layered(column, row, sector_id.get_layer()))
 				} else {
 					None
 				}
@@ -512,6 +604,53 @@ impl MapDimensions {
 			}
 		}
 	}
+	/// Convert a `(column, row)` within the grid of [FieldCell]s spanning the whole map into the sector and [FieldCell] it refers to. Returns [None] if the grid cell lies outside of the map
+	pub fn get_sector_and_field_cell_from_grid_cell(
+		&self,
+		column: usize,
+		row: usize,
+	) -> Option<(SectorID, FieldCell)> {
+		if column >= self.get_total_field_cell_columns() || row >= self.get_total_field_cell_rows()
+		{
+			return None;
+		}
+		Some((
+			SectorID::new(
+				(column / FIELD_RESOLUTION) as u32,
+				(row / FIELD_RESOLUTION) as u32,
+			),
+			FieldCell::new(column % FIELD_RESOLUTION, row % FIELD_RESOLUTION),
+		))
+	}
+	/// Convert a sector and [FieldCell] into a `(column, row)` within the grid of [FieldCell]s spanning the whole map
+	pub fn get_grid_cell_from_sector_and_field_cell(
+		&self,
+		sector: SectorID,
+		field_cell: FieldCell,
+	) -> (usize, usize) {
+		(
+			sector.get_column() as usize * FIELD_RESOLUTION + field_cell.get_column(),
+			sector.get_row() as usize * FIELD_RESOLUTION + field_cell.get_row(),
+		)
+	}
+	/// Convert a 2d position into a continuous position within the grid of [FieldCell]s spanning the whole map, measured in [FieldCell]s from the top left corner of the map. The position is not clamped to the map so may be negative or exceed the grid
+	#[cfg(feature = "2d")]
+	pub fn get_grid_position_from_xy(&self, position: Vec2) -> Vec2 {
+		let cell_size = self.get_sector_resolution() as f32 / FIELD_RESOLUTION as f32;
+		Vec2::new(
+			(position.x + self.get_length() as f32 / 2.0) / cell_size,
+			(self.get_depth() as f32 / 2.0 - position.y) / cell_size,
+		)
+	}
+	/// Convert a 3d position into a continuous position within the grid of [FieldCell]s spanning the whole map, measured in [FieldCell]s from the top left corner of the map. The `y` of the position is ignored and the result is not clamped to the map so may be negative or exceed the grid
+	#[cfg(feature = "3d")]
+	pub fn get_grid_position_from_xyz(&self, position: Vec3) -> Vec2 {
+		let cell_size = self.get_sector_resolution() as f32 / FIELD_RESOLUTION as f32;
+		Vec2::new(
+			(position.x + self.get_length() as f32 / 2.0) / cell_size,
+			(position.z + self.get_depth() as f32 / 2.0) / cell_size,
+		)
+	}
 	// /// From a list of meshes find the maximum and minimum x-y dimensions across all meshes to represent the size of the world as an MxN set of Flowfields
 	// #[cfg(feature = "2d")]
 	// pub fn from_bevy_2d_meshes(meshes: &Vec<&Mesh>, sector_resolution: u32, actor_size: f32) -> Self {
@@ -571,6 +710,43 @@ impl MapDimensions {
 mod tests {
 	use super::*;
 	#[test]
+	fn layered_sector_neighbours() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let sector_id = SectorID::new_layered(1, 1, 2);
+		assert_eq!(SectorID::new(1, 1), sector_id.with_layer(0));
+		let neighbours = map_dimensions.get_ids_of_neighbouring_sectors(&sector_id);
+		assert_eq!(4, neighbours.len());
+		assert!(neighbours.iter().all(|n| n.get_layer() == 2));
+		let north = map_dimensions.get_sector_id_from_ordinal(Ordinal::North, &sector_id);
+		assert_eq!(Some(SectorID::new_layered(1, 0, 2)), north);
+		assert_eq!(
+			None,
+			Ordinal::sector_to_sector_direction(
+				SectorID::new_layered(1, 0, 1),
+				SectorID::new(1, 1)
+			)
+		);
+	}
+	#[test]
+	#[cfg(feature = "ron")]
+	fn sector_id_ron_layers() {
+		// assets written before layers existed only have a column and row
+		let ground: SectorID = ron::from_str("SectorID((2, 3))").unwrap();
+		assert_eq!(SectorID::new(2, 3), ground);
+		/// The layout of a [SectorID] before layers were added
+		#[derive(serde::Serialize)]
+		struct LegacySectorID((u32, u32));
+		assert_eq!(
+			ron::to_string(&LegacySectorID((2, 3))).unwrap(),
+			ron::to_string(&ground).unwrap()
+		);
+		let upper = SectorID::new_layered(2, 3, 1);
+		let written = ron::to_string(&upper).unwrap();
+		assert_eq!(upper, ron::from_str::<SectorID>(&written).unwrap());
+		let upper: SectorID = ron::from_str("SectorID((2, 3, 4))").unwrap();
+		assert_eq!(SectorID::new_layered(2, 3, 4), upper);
+	}
+	#[test]
 	fn sector_costfields_top_left_sector_id_from_xyz() {
 		let map_dimensions = MapDimensions::new(20, 20, 10, 1.0);
 		let position = Vec3::new(-5.0, 0.0, -5.0);
@@ -862,6 +1038,41 @@ mod tests {
 		assert!(result.is_none())
 	}
 	#[test]
+	#[cfg(feature = "2d")]
+	fn grid_position_xy() {
+		let map_dimensions = MapDimensions::new(1920, 1920, 640, 16.0);
+		let result = map_dimensions.get_grid_position_from_xy(Vec2::new(-960.0, 960.0));
+		assert_eq!(Vec2::ZERO, result);
+		let result = map_dimensions.get_grid_position_from_xy(Vec2::new(32.0, -96.0));
+		assert_eq!(Vec2::new(15.5, 16.5), result);
+	}
+	#[test]
+	#[cfg(feature = "3d")]
+	fn grid_position_xyz() {
+		let map_dimensions = MapDimensions::new(30, 20, 10, 1.0);
+		let result = map_dimensions.get_grid_position_from_xyz(Vec3::new(-15.0, 4.0, -10.0));
+		assert_eq!(Vec2::ZERO, result);
+		let result = map_dimensions.get_grid_position_from_xyz(Vec3::new(8.5, 0.0, 9.5));
+		assert_eq!(Vec2::new(23.5, 19.5), result);
+	}
+	#[test]
+	fn grid_cell_round_trip() {
+		let map_dimensions = MapDimensions::new(30, 20, 10, 1.0);
+		let (sector, field_cell) = map_dimensions
+			.get_sector_and_field_cell_from_grid_cell(23, 9)
+			.unwrap();
+		assert_eq!(SectorID::new(2, 0), sector);
+		assert_eq!(FieldCell::new(3, 9), field_cell);
+		let result = map_dimensions.get_grid_cell_from_sector_and_field_cell(sector, field_cell);
+		assert_eq!((23, 9), result);
+	}
+	#[test]
+	fn grid_cell_oob() {
+		let map_dimensions = MapDimensions::new(30, 20, 10, 1.0);
+		let result = map_dimensions.get_sector_and_field_cell_from_grid_cell(10, 20);
+		assert!(result.is_none());
+	}
+	#[test]
 	fn get_xy() {
 		let map_dimensions = MapDimensions::new(1920, 1920, 640, 16.0);
 		let sector_id = SectorID::new(2, 1);
@@ -21,10 +21,27 @@ use bevy::prelude::*;
 pub struct SectorCostFields {
 	/// Initial costs based on the unit size of each field
 	baseline: BTreeMap<SectorID, CostField>,
+	/// The baseline costs with the temporary [CostModifier]s of each cell applied, this is what gets scaled so that modifiers never alter the baseline
+	#[cfg_attr(feature = "serde", serde(skip))]
+	effective: BTreeMap<SectorID, CostField>,
+	/// The [CostModifier]s covering each modified cell in the order they are applied, see [SectorCostModifiers]
+	#[cfg_attr(feature = "serde", serde(skip))]
+	modifiers: BTreeMap<(SectorID, FieldCell), Vec<CostModifier>>,
 	/// Each [FieldCell] containing an impassable `255` value is scaled based on actor size to close off gaps which the actor could not path through
 	scaled: BTreeMap<SectorID, CostField>,
 }
 
+/// Selects which set of [CostField]s of a [SectorCostFields] is exported
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CostLayer {
+	/// The costs as they were set, which is what the loaders expect to read
+	Baseline,
+	/// The baseline costs with any temporary [CostModifier]s applied
+	Effective,
+	/// The costs after impassable cells have been scaled by actor size
+	Scaled,
+}
+
 impl SectorCostFields {
 	/// Create a new instance of [SectorCostFields] based on the map dimensions containing [CostField]
 	pub fn new(map_dimensions: &MapDimensions) -> Self {
@@ -64,6 +81,10 @@ impl SectorCostFields {
 	pub fn get_baseline_mut(&mut self) -> &mut BTreeMap<SectorID, CostField> {
 		&mut self.baseline
 	}
+	/// Get a reference to the map of sectors and [CostField]s holding the baseline costs with any temporary [CostModifier]s applied
+	pub fn get_effective(&self) -> &BTreeMap<SectorID, CostField> {
+		&self.effective
+	}
 	/// Get a reference to the map of scaled sectors and [CostField]
 	pub fn get_scaled(&self) -> &BTreeMap<SectorID, CostField> {
 		&self.scaled
@@ -72,6 +93,76 @@ impl SectorCostFields {
 	pub fn get_scaled_mut(&mut self) -> &mut BTreeMap<SectorID, CostField> {
 		&mut self.scaled
 	}
+	/// Get a reference to either the baseline, effective or scaled sectors and [CostField]
+	pub fn get_layer(&self, layer: CostLayer) -> &BTreeMap<SectorID, CostField> {
+		match layer {
+			CostLayer::Baseline => &self.baseline,
+			CostLayer::Effective => &self.effective,
+			CostLayer::Scaled => &self.scaled,
+		}
+	}
+	/// Get the number of sector layers, i.e the floors of a building, the highest layer of any sector plus one
+	pub fn get_sector_layer_count(&self) -> u32 {
+		self.baseline
+			.keys()
+			.map(|sector_id| sector_id.get_layer() + 1)
+			.max()
+			.unwrap_or(0)
+	}
+	/// Copy the ground layer [CostField]s of `layer_cost_fields`, created for the same [MapDimensions], into this as the sectors of `layer`. Any existing sectors of that layer are replaced
+	pub fn insert_sector_layer(&mut self, layer: u32, layer_cost_fields: &SectorCostFields) {
+		for (sector_id, field) in layer_cost_fields.get_baseline().iter() {
+			if sector_id.get_layer() == 0 {
+				self.baseline
+					.insert(sector_id.with_layer(layer), field.clone());
+			}
+		}
+		for (sector_id, field) in layer_cost_fields.get_effective().iter() {
+			if sector_id.get_layer() == 0 {
+				self.effective
+					.insert(sector_id.with_layer(layer), field.clone());
+			}
+		}
+		for (sector_id, field) in layer_cost_fields.get_scaled().iter() {
+			if sector_id.get_layer() == 0 {
+				self.scaled
+					.insert(sector_id.with_layer(layer), field.clone());
+			}
+		}
+	}
+	/// Set the [CostModifier]s covering a cell in the order they are applied, an empty list clears them. The effective [CostField] of the sector is updated when it is next rescaled
+	pub fn set_field_cell_modifiers(
+		&mut self,
+		sector_id: SectorID,
+		field_cell: FieldCell,
+		modifiers: Vec<CostModifier>,
+	) {
+		if modifiers.is_empty() {
+			self.modifiers.remove(&(sector_id, field_cell));
+		} else {
+			self.modifiers.insert((sector_id, field_cell), modifiers);
+		}
+	}
+	/// Rebuild the effective [CostField] of a sector from its baseline and the [CostModifier]s of its cells
+	fn apply_modifiers(&mut self, sector_id: &SectorID) {
+		let Some(mut field) = self.baseline.get(sector_id).cloned() else {
+			return;
+		};
+		let first = (*sector_id, FieldCell::new(0, 0));
+		let last = (
+			*sector_id,
+			FieldCell::new(FIELD_RESOLUTION - 1, FIELD_RESOLUTION - 1),
+		);
+		for ((_, field_cell), modifiers) in self.modifiers.range(first..=last) {
+			let cost = modifiers
+				.iter()
+				.fold(field.get_field_cell_value(*field_cell), |cost, modifier| {
+					modifier.apply(cost)
+				});
+			field.set_field_cell_value(cost, *field_cell);
+		}
+		self.effective.insert(*sector_id, field);
+	}
 	// /// Get the [CostField] of a sector wrapped in in Arc
 	// pub fn get_arc_scaled_sector(&self, sector_id: &SectorID) -> Arc<CostField> {
 	// 	//TODO really a clone?
@@ -87,6 +178,7 @@ impl SectorCostFields {
 	) {
 		if let Some(cost_field) = self.get_baseline_mut().get_mut(&sector_id) {
 			cost_field.set_field_cell_value(value, field_cell);
+			self.apply_modifiers(&sector_id);
 			self.scale_costfield(&sector_id, map_dimensions)
 		} else {
 			error!(
@@ -95,15 +187,116 @@ impl SectorCostFields {
 			);
 		}
 	}
+	/// Apply a batch of `(sector_id, field_cell, value)` cost changes. All of the baseline [CostField]s are updated first and then the scaled fields of the affected sectors are recalculated once, rather than once per change.
+	///
+	/// Returns the IDs of every sector whose scaled [CostField] has changed, this may include neighbours of the edited sectors as impassable cells are scaled across sector boundaries
+	pub fn set_field_cell_values(
+		&mut self,
+		changes: &[(SectorID, FieldCell, u8)],
+		map_dimensions: &MapDimensions,
+	) -> Vec<SectorID> {
+		let edited = self.set_baseline_field_cell_values(changes);
+		self.rescale_costfields(&edited, map_dimensions)
+			.into_keys()
+			.collect()
+	}
+	/// Write a batch of `(sector_id, field_cell, value)` cost changes into the baseline [CostField]s without updating the scaled fields, [SectorCostFields::rescale_costfields] should be called afterwards.
+	///
+	/// Returns the IDs of the sectors which were edited
+	pub fn set_baseline_field_cell_values(
+		&mut self,
+		changes: &[(SectorID, FieldCell, u8)],
+	) -> Vec<SectorID> {
+		let mut edited = Vec::new();
+		for (sector_id, field_cell, value) in changes.iter() {
+			if let Some(cost_field) = self.get_baseline_mut().get_mut(sector_id) {
+				cost_field.set_field_cell_value(*value, *field_cell);
+				if !edited.contains(sector_id) {
+					edited.push(*sector_id);
+				}
+			} else {
+				error!(
+					"Cannot mutate CostField in non-existent sector {:?}",
+					sector_id
+				);
+			}
+		}
+		edited
+	}
+	/// Recalculate the effective and scaled [CostField]s of `sector_ids` from their baselines. Impassable cells can be scaled across sector boundaries so the neighbours of each sector are reset and rescaled too, along with their own neighbours which may scale impassable cells back into them.
+	///
+	/// Returns the IDs of every sector whose scaled [CostField] has changed along with the [FieldCell]s which changed, see [CostField::get_changed_cells]
+	pub fn rescale_costfields(
+		&mut self,
+		sector_ids: &[SectorID],
+		map_dimensions: &MapDimensions,
+	) -> BTreeMap<SectorID, Vec<FieldCell>> {
+		// sectors whose scaled fields are reset to their baseline
+		let mut reset = BTreeMap::new();
+		for sector_id in sector_ids.iter() {
+			reset.insert(*sector_id, ());
+			if map_dimensions.get_actor_scale() > 1 {
+				for n in map_dimensions.get_ids_of_neighbouring_sectors(sector_id) {
+					reset.insert(n, ());
+				}
+			}
+		}
+		// sectors that may scale impassable cells into the reset sectors
+		let mut rescale = reset.clone();
+		if map_dimensions.get_actor_scale() > 1 {
+			for sector_id in reset.keys() {
+				for n in map_dimensions.get_ids_of_neighbouring_sectors(sector_id) {
+					rescale.insert(n, ());
+				}
+			}
+		}
+		for sector_id in sector_ids.iter() {
+			self.apply_modifiers(sector_id);
+		}
+		let mut previous = BTreeMap::new();
+		for sector_id in reset.keys() {
+			if let Some(effective) = self.get_effective().get(sector_id).cloned() {
+				if let Some(old) = self.scaled.insert(*sector_id, effective) {
+					previous.insert(*sector_id, old);
+				}
+			}
+		}
+		for sector_id in rescale.keys() {
+			if self.get_effective().contains_key(sector_id) {
+				self.scale_costfield(sector_id, map_dimensions);
+			}
+		}
+		let mut changed = BTreeMap::new();
+		for sector_id in reset.keys() {
+			match (previous.get(sector_id), self.scaled.get(sector_id)) {
+				(Some(old), Some(new)) if old.get() != new.get() => {
+					changed.insert(*sector_id, old.get_changed_cells(new));
+				}
+				(None, Some(_)) => {
+					// no previous field so treat every cell as changed
+					let mut cells = Vec::new();
+					for column in 0..FIELD_RESOLUTION {
+						for row in 0..FIELD_RESOLUTION {
+							cells.push(FieldCell::new(column, row));
+						}
+					}
+					changed.insert(*sector_id, cells);
+				}
+				_ => {}
+			}
+		}
+		changed
+	}
 	/// Iterate over all sectors and scale any impassable [FieldCell] based on `actor_scale`.
 	///
 	/// This can be expensive so should typically be used as part of data initialisation, i.e when loading [SectorCostFields] from a file or within a loading type of operation to a world
 	pub fn scale_all_costfields(&mut self, map_dimensions: &MapDimensions) {
 		let sector_ids: Vec<SectorID> = self.baseline.keys().cloned().collect();
 		for sector_id in sector_ids.iter() {
+			self.apply_modifiers(sector_id);
 			self.scaled.insert(
 				*sector_id,
-				self.get_baseline().get(sector_id).unwrap().clone(),
+				self.get_effective().get(sector_id).unwrap().clone(),
 			);
 		}
 		for sector_id in sector_ids.iter() {
@@ -144,12 +337,12 @@ impl SectorCostFields {
 		if map_dimensions.get_actor_scale() == 1 {
 			self.scaled.insert(
 				*sector_id,
-				self.get_baseline().get(sector_id).unwrap().clone(),
+				self.get_effective().get(sector_id).unwrap().clone(),
 			);
 		} else {
 			// identify all impassable cells
 			let mut impassable_indices = Vec::new();
-			let cost_field = self.get_baseline_mut().get(sector_id).unwrap();
+			let cost_field = self.get_effective().get(sector_id).unwrap();
 			let field_array = cost_field.get();
 			for (column, rows) in field_array.iter().enumerate() {
 				for (row, cost) in rows.iter().enumerate() {
@@ -169,7 +362,7 @@ impl SectorCostFields {
 						let field_cell = FieldCell::new(*column, n_row);
 						update_processed(&mut processed, field_cell, sector_id);
 						let value = self
-							.get_baseline()
+							.get_effective()
 							.get(sector_id)
 							.unwrap()
 							.get_field_cell_value(field_cell);
@@ -189,9 +382,10 @@ impl SectorCostFields {
 							{
 								if let Some(n_sector) = map_dimensions.get_sector_id_from_ordinal(
 									Ordinal::North,
-									&SectorID::new(
+									&SectorID::new_layered(
 										sector_id.get_column(),
 										sector_id.get_row() - factor,
+										sector_id.get_layer(),
 									),
 								) {
 									n.push(n_sector);
@@ -216,9 +410,9 @@ impl SectorCostFields {
 									let field_cell = FieldCell::new(*column, n_row);
 									update_processed(&mut processed, field_cell, n_sector);
 									let value = self
-										.get_baseline()
+										.get_effective()
 										.get(n_sector)
-										.unwrap_or_else(|| panic!("Could not get baseline costfield {:?}, this can indicates that sector_resolution and/or actor_size are not set correctly", n_sector))
+										.unwrap_or_else(|| panic!("Could not get effective costfield {:?}, this can indicates that sector_resolution and/or actor_size are not set correctly", n_sector))
 										.get_field_cell_value(field_cell);
 									// hit impassable before exceeding scale therefore
 									// gap too small for pathing
@@ -247,7 +441,7 @@ impl SectorCostFields {
 						let field_cell = FieldCell::new(column + i, *row);
 						update_processed(&mut processed, field_cell, sector_id);
 						let value = self
-							.get_baseline()
+							.get_effective()
 							.get(sector_id)
 							.unwrap()
 							.get_field_cell_value(field_cell);
@@ -267,9 +461,10 @@ impl SectorCostFields {
 							{
 								if let Some(n_sector) = map_dimensions.get_sector_id_from_ordinal(
 									Ordinal::East,
-									&SectorID::new(
+									&SectorID::new_layered(
 										sector_id.get_column() + factor,
 										sector_id.get_row(),
+										sector_id.get_layer(),
 									),
 								) {
 									n.push(n_sector);
@@ -294,7 +489,7 @@ impl SectorCostFields {
 									let field_cell = FieldCell::new(x, *row);
 									update_processed(&mut processed, field_cell, n_sector);
 									let value = self
-										.get_baseline()
+										.get_effective()
 										.get(n_sector)
 										.unwrap()
 										.get_field_cell_value(field_cell);
@@ -325,7 +520,7 @@ impl SectorCostFields {
 						let field_cell = FieldCell::new(*column, *row + i);
 						update_processed(&mut processed, field_cell, sector_id);
 						let value = self
-							.get_baseline()
+							.get_effective()
 							.get(sector_id)
 							.unwrap()
 							.get_field_cell_value(field_cell);
@@ -345,9 +540,10 @@ impl SectorCostFields {
 							{
 								if let Some(n_sector) = map_dimensions.get_sector_id_from_ordinal(
 									Ordinal::South,
-									&SectorID::new(
+									&SectorID::new_layered(
 										sector_id.get_column(),
 										sector_id.get_row() + factor,
+										sector_id.get_layer(),
 									),
 								) {
 									n.push(n_sector);
@@ -372,7 +568,7 @@ impl SectorCostFields {
 									let field_cell = FieldCell::new(*column, x);
 									update_processed(&mut processed, field_cell, n_sector);
 									let value = self
-										.get_baseline()
+										.get_effective()
 										.get(n_sector)
 										.unwrap()
 										.get_field_cell_value(field_cell);
@@ -403,7 +599,7 @@ impl SectorCostFields {
 						let field_cell = FieldCell::new(n_column, *row);
 						update_processed(&mut processed, field_cell, sector_id);
 						let value = self
-							.get_baseline()
+							.get_effective()
 							.get(sector_id)
 							.unwrap()
 							.get_field_cell_value(field_cell);
@@ -423,9 +619,10 @@ impl SectorCostFields {
 							{
 								if let Some(n_sector) = map_dimensions.get_sector_id_from_ordinal(
 									Ordinal::West,
-									&SectorID::new(
+									&SectorID::new_layered(
 										sector_id.get_column() - factor,
 										sector_id.get_row(),
+										sector_id.get_layer(),
 									),
 								) {
 									n.push(n_sector);
@@ -450,7 +647,7 @@ impl SectorCostFields {
 									let field_cell = FieldCell::new(n_column, *row);
 									update_processed(&mut processed, field_cell, n_sector);
 									let value = self
-										.get_baseline()
+										.get_effective()
 										.get(n_sector)
 										.unwrap()
 										.get_field_cell_value(field_cell);
@@ -498,7 +695,7 @@ impl SectorCostFields {
 		fields.scale_all_costfields(map_dimensions);
 		fields
 	}
-	/// From a directory containing a series of CSV files generate the [SectorCostFields]
+	/// From a directory containing a series of CSV files generate the [SectorCostFields]. Files are named after the sector ID as `column_row.csv` for the ground layer and `column_row_layer.csv` for the sectors of any higher layer, every layer must cover the whole map
 	#[cfg(feature = "csv")]
 	pub fn from_csv_dir(map_dimensions: &MapDimensions, directory: String) -> Self {
 		let required_files_count = (map_dimensions.get_length() * map_dimensions.get_depth())
@@ -519,26 +716,36 @@ impl SectorCostFields {
 		let mut csvs = Vec::new();
 		for (file_path, file_name) in files {
 			if file_path.ends_with(".csv") {
-				let sector_id_str = file_name.trim_end_matches(".csv").split_once('_').unwrap();
-				let sector_id = SectorID::new(
-					sector_id_str
-						.0
-						.parse::<u32>()
-						.expect("Failed to parse sector ID from csv file name"),
-					sector_id_str
-						.1
-						.parse::<u32>()
-						.expect("Failed to parse sector ID from csv file name"),
-				);
+				let sector_id_parts: Vec<u32> = file_name
+					.trim_end_matches(".csv")
+					.split('_')
+					.map(|part| {
+						part.parse::<u32>()
+							.expect("Failed to parse sector ID from csv file name")
+					})
+					.collect();
+				let sector_id = match sector_id_parts[..] {
+					[column, row] => SectorID::new(column, row),
+					[column, row, layer] => SectorID::new_layered(column, row, layer),
+					_ => panic!("Failed to parse sector ID from csv file name {}", file_name),
+				};
 				csvs.push((file_path, sector_id));
 			}
 		}
-		if csvs.len() != required_files_count {
-			panic!(
-				"Found {} CSVs, expected {}",
-				csvs.len(),
-				required_files_count
-			);
+		let mut layer_files_counts: BTreeMap<u32, usize> = BTreeMap::new();
+		for (_, sector_id) in csvs.iter() {
+			*layer_files_counts.entry(sector_id.get_layer()).or_default() += 1;
+		}
+		if layer_files_counts.is_empty() {
+			panic!("Found 0 CSVs, expected {}", required_files_count);
+		}
+		for (layer, count) in layer_files_counts.iter() {
+			if *count != required_files_count {
+				panic!(
+					"Found {} CSVs of layer {}, expected {}",
+					count, layer, required_files_count
+				);
+			}
 		}
 		let mut sector_cost_fields = SectorCostFields::default();
 		for (csv_file, sector_id) in csvs.iter() {
@@ -560,83 +767,287 @@ impl SectorCostFields {
 		sector_cost_fields.scale_all_costfields(map_dimensions);
 		sector_cost_fields
 	}
-	/// Create a [SectorCostFields] from a greyscale image where each pixel
-	/// represents the cost of a [FieldCell]
+	/// From a single CSV file where each value is the cost of a [FieldCell] across the whole map generate the [SectorCostFields]. The file should not contain any headers, the first line is the top row of the map and the map is split into sectors automatically
+	#[cfg(feature = "csv")]
+	pub fn from_csv(map_dimensions: &MapDimensions, path: String) -> Self {
+		let data = std::fs::File::open(path).expect("Failed opening csv");
+		let mut rdr = csv::ReaderBuilder::new()
+			.has_headers(false)
+			.flexible(true)
+			.from_reader(data);
+		let rows: Vec<Vec<u8>> = rdr
+			.records()
+			.map(|record| {
+				record
+					.expect("Failed reading csv")
+					.iter()
+					.map(|value| value.trim().parse().expect("CSV expects u8 values"))
+					.collect()
+			})
+			.collect();
+		SectorCostFields::from_rows(map_dimensions, &rows, "CSV")
+	}
+	/// From a plain text file where each character represents a [FieldCell] across the whole map generate the [SectorCostFields]. The cost of each character is looked up in the `legend`, the first line is the top row of the map and the map is split into sectors automatically
+	pub fn from_ascii(map_dimensions: &MapDimensions, path: String, legend: &AsciiLegend) -> Self {
+		let text = std::fs::read_to_string(path).expect("Failed opening ascii map");
+		SectorCostFields::from_rows(map_dimensions, &legend.parse(&text), "Ascii map")
+	}
+	/// Write the costs of every [FieldCell] of a `layer` across the whole map to a single CSV file which can be read with [SectorCostFields::from_csv]. Only the sectors of `sector_layer` are written, when read back they form the ground layer
+	#[cfg(feature = "csv")]
+	pub fn to_csv(
+		&self,
+		map_dimensions: &MapDimensions,
+		path: String,
+		layer: CostLayer,
+		sector_layer: u32,
+	) {
+		let mut wtr = csv::WriterBuilder::new()
+			.has_headers(false)
+			.from_path(path)
+			.expect("Failed creating csv");
+		for row in self.get_rows(map_dimensions, layer, sector_layer) {
+			wtr.write_record(row.iter().map(|value| value.to_string()))
+				.expect("Failed writing csv");
+		}
+		wtr.flush().expect("Failed writing csv");
+	}
+	/// Write the costs of every [FieldCell] of a `layer` across the whole map to a plain text file which can be read with [SectorCostFields::from_ascii]. Only the sectors of `sector_layer` are written and a cost without a character in the `legend` is written with the character of the nearest cost, see [AsciiLegend::get_nearest_character]
+	pub fn to_ascii(
+		&self,
+		map_dimensions: &MapDimensions,
+		path: String,
+		legend: &AsciiLegend,
+		layer: CostLayer,
+		sector_layer: u32,
+	) {
+		let text = legend.format(&self.get_rows(map_dimensions, layer, sector_layer));
+		std::fs::write(path, text).expect("Failed writing ascii map");
+	}
+	/// Write the whole [SectorCostFields], both baseline and scaled, to a `ron` file which can be read with [SectorCostFields::from_ron]. The baseline is written without any temporary [CostModifier]s and the scaled fields are recalculated from it when the file is read
+	#[cfg(feature = "ron")]
+	pub fn to_ron(&self, path: String) {
+		let ron = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+			.expect("Failed serializing SectorCostFields");
+		std::fs::write(path, ron).expect("Failed writing CostField file");
+	}
+	/// Write the [CostField] of each sector of a `layer` to a CSV file in `directory`, which can be read with [SectorCostFields::from_csv_dir]. Sectors of the ground layer are named `column_row.csv` and those of higher layers `column_row_layer.csv`. The directory is created if it doesn't exist
+	#[cfg(feature = "csv")]
+	pub fn to_csv_dir(&self, directory: String, layer: CostLayer) {
+		let directory = std::path::Path::new(&directory);
+		std::fs::create_dir_all(directory).expect("Unable to create csv directory");
+		for (sector_id, cost_field) in self.get_layer(layer).iter() {
+			let file_name = if sector_id.get_layer() == 0 {
+				format!("{}_{}.csv", sector_id.get_column(), sector_id.get_row())
+			} else {
+				format!(
+					"{}_{}_{}.csv",
+					sector_id.get_column(),
+					sector_id.get_row(),
+					sector_id.get_layer()
+				)
+			};
+			let mut wtr = csv::WriterBuilder::new()
+				.has_headers(false)
+				.from_path(directory.join(file_name))
+				.expect("Failed creating csv");
+			for row in 0..FIELD_RESOLUTION {
+				wtr.write_record((0..FIELD_RESOLUTION).map(|column| {
+					cost_field
+						.get_field_cell_value(FieldCell::new(column, row))
+						.to_string()
+				}))
+				.expect("Failed writing csv");
+			}
+			wtr.flush().expect("Failed writing csv");
+		}
+	}
+	/// Write the costs of a `layer` to a greyscale image where each pixel represents a [FieldCell], which can be read with [SectorCostFields::from_heightmap]. Only the sectors of `sector_layer` are written, a cost of `255` is written as black and a cost of `1` as almost white
 	#[cfg(feature = "heightmap")]
-	pub fn from_heightmap(map_dimensions: &MapDimensions, path: String) -> Self {
-		use photon_rs::native::open_image;
-		let img = open_image(&path).expect("Failed to open heightmap");
-		let img_width = img.get_width();
-		let img_height = img.get_height();
-		// ensure the size of the heightmap actually represents the number of FieldCells required by the MapDimensions
-		let hori_sector_count =
-			map_dimensions.get_length() / map_dimensions.get_sector_resolution();
-		let required_px_width = hori_sector_count * FIELD_RESOLUTION as u32;
-		if img_width != required_px_width {
-			panic!(
-				"Heightmap has incorrect width, expected width of {} pixels, found {}",
-				required_px_width, img_width
-			);
+	pub fn to_heightmap(
+		&self,
+		map_dimensions: &MapDimensions,
+		path: String,
+		layer: CostLayer,
+		sector_layer: u32,
+	) {
+		use photon_rs::{native::save_image, PhotonImage};
+		let rows = self.get_rows(map_dimensions, layer, sector_layer);
+		let mut raw_pixels = Vec::new();
+		for cost in rows.iter().flatten() {
+			// the inverse of the colour average used when reading a heightmap
+			let colour = 255 - cost;
+			raw_pixels.extend_from_slice(&[colour, colour, colour, 255]);
 		}
-		let vert_sector_count = map_dimensions.get_depth() / map_dimensions.get_sector_resolution();
-		let required_px_height = vert_sector_count * FIELD_RESOLUTION as u32;
-		if img_height != required_px_height {
+		let img = PhotonImage::new(
+			raw_pixels,
+			map_dimensions.get_total_field_cell_columns() as u32,
+			map_dimensions.get_total_field_cell_rows() as u32,
+		);
+		save_image(img, &path).expect("Failed writing heightmap");
+	}
+	/// Get the cost of every [FieldCell] of a `layer` across the whole map as rows starting from the top left, reading the sectors of `sector_layer`
+	pub fn get_rows(
+		&self,
+		map_dimensions: &MapDimensions,
+		layer: CostLayer,
+		sector_layer: u32,
+	) -> Vec<Vec<u8>> {
+		let columns = map_dimensions.get_total_field_cell_columns();
+		let rows = map_dimensions.get_total_field_cell_rows();
+		(0..rows)
+			.map(|row| {
+				(0..columns)
+					.map(|column| {
+						let sector_id = SectorID::new_layered(
+							(column / FIELD_RESOLUTION) as u32,
+							(row / FIELD_RESOLUTION) as u32,
+							sector_layer,
+						);
+						let field_cell =
+							FieldCell::new(column % FIELD_RESOLUTION, row % FIELD_RESOLUTION);
+						self.get_layer(layer)
+							.get(&sector_id)
+							.expect("SectorCostFields is missing a sector of the map")
+							.get_field_cell_value(field_cell)
+					})
+					.collect()
+			})
+			.collect()
+	}
+	/// Create a [SectorCostFields] from rows of costs covering every [FieldCell] of the map, panics if the size of `rows` doesn't match the `map_dimensions`. `source` names the format being read in the panic message
+	fn from_rows(map_dimensions: &MapDimensions, rows: &[Vec<u8>], source: &str) -> Self {
+		// ensure the rows actually represent the number of FieldCells required by the MapDimensions
+		let columns = map_dimensions.get_total_field_cell_columns();
+		if rows.len() != map_dimensions.get_total_field_cell_rows() {
 			panic!(
-				"Heightmap has incorrect height, expected hieght of {} pixels, found {}",
-				required_px_height, img_height
+				"{} has incorrect size, expected {} rows, found {}",
+				source,
+				map_dimensions.get_total_field_cell_rows(),
+				rows.len()
 			);
 		}
-		// init the fields so we already have the required sectors inserted
-		let mut sector_cost_fields = SectorCostFields::new(map_dimensions);
-		// iter over the pixels in chunks creating CostFields
-		let raw_pixels = img.get_raw_pixels();
-		// raw pixels are arranged from the top left of the image and come in sets of either 3 or 4 (if alpha channel is inlcuded).
-		// Each sequential set corresponds to Red, Green, Blue, (Alpha).
-		// We want to convert these into a vector of tuples which can represent each field cell
-		let len_if_alpha = img_height * img_height * 4;
-		let chunk_size = {
-			if len_if_alpha as usize == raw_pixels.len() {
-				4
-			} else {
-				3
+		for (i, row) in rows.iter().enumerate() {
+			if row.len() != columns {
+				panic!(
+					"{} has incorrect size, expected {} columns, found {} in row {}",
+					source,
+					columns,
+					row.len(),
+					i
+				);
 			}
-		};
-		let mut pixels_rgb: Vec<(u8, u8, u8)> = Vec::new();
-		for rgb in raw_pixels.chunks(chunk_size) {
-			let mut as_tuple = vec![(rgb[0], rgb[1], rgb[2])];
-			pixels_rgb.append(&mut as_tuple);
 		}
-		// By chunking the list of pixel RGBAs based on the width of the image
-		// we can iterate on the rows
-		for (line_number, rgba_slice) in pixels_rgb.chunks(img_width as usize).enumerate() {
-			let sector_row = line_number / FIELD_RESOLUTION;
-			// chunk each row by resolution to give slices of pixels for each sector column
-			for (sector_column, rgba_slice_slice) in rgba_slice.chunks(FIELD_RESOLUTION).enumerate()
-			{
-				let sector_id = SectorID::new(sector_column as u32, sector_row as u32);
-				let field = sector_cost_fields
+		SectorCostFields::from_map_cells(map_dimensions, |column, row| rows[row][column])
+	}
+	/// Create a [SectorCostFields] where `cost` gives the value of the [FieldCell] at each `(column, row)` across the whole map, starting from the top left
+	fn from_map_cells(map_dimensions: &MapDimensions, cost: impl Fn(usize, usize) -> u8) -> Self {
+		let mut sector_cost_fields = SectorCostFields::new(map_dimensions);
+		for row in 0..map_dimensions.get_total_field_cell_rows() {
+			for column in 0..map_dimensions.get_total_field_cell_columns() {
+				let sector_id = SectorID::new(
+					(column / FIELD_RESOLUTION) as u32,
+					(row / FIELD_RESOLUTION) as u32,
+				);
+				let field_cell = FieldCell::new(column % FIELD_RESOLUTION, row % FIELD_RESOLUTION);
+				sector_cost_fields
 					.get_baseline_mut()
 					.get_mut(&sector_id)
-					.unwrap();
-				// iter over the pixels in the row of the particular sector
-				for (field_column, px) in rgba_slice_slice.iter().enumerate() {
-					// calc row in the field
-					let field_row = line_number - (FIELD_RESOLUTION * sector_row);
-					let field_cell = FieldCell::new(field_column, field_row);
-					// black (0, 0, 0, 255)
-					// white (255, 255, 255, 255)
-					// careful of u8 overflow
-					let colour_avg = (px.0 as f32 + px.1 as f32 + px.2 as f32) / 3.0;
-					let value = (255 - colour_avg as u8).clamp(1, 255);
-					field.set_field_cell_value(value, field_cell);
-				}
+					.unwrap()
+					.set_field_cell_value(cost(column, row), field_cell);
 			}
 		}
-		// now that costs are popualated calcualte the scaled fields that will
-		// be used in the algorithm
 		sector_cost_fields.scale_all_costfields(map_dimensions);
 		sector_cost_fields
 	}
+	/// Create a [SectorCostFields] from a greyscale image where each pixel
+	/// represents the cost of a [FieldCell]
+	#[cfg(feature = "heightmap")]
+	pub fn from_heightmap(map_dimensions: &MapDimensions, path: String) -> Self {
+		SectorCostFields::from_heightmap_with_costs(
+			map_dimensions,
+			path,
+			&HeightmapCosts::default(),
+		)
+	}
+	/// Create a [SectorCostFields] from an image where each pixel represents a [FieldCell] and `costs` determines how the colour of a pixel is translated into a cost, such as reading a single channel or looking up a palette of terrain colours, see [HeightmapCosts]
+	#[cfg(feature = "heightmap")]
+	pub fn from_heightmap_with_costs(
+		map_dimensions: &MapDimensions,
+		path: String,
+		costs: &HeightmapCosts,
+	) -> Self {
+		let pixels = read_heightmap_pixels(map_dimensions, &path);
+		SectorCostFields::from_map_cells(map_dimensions, |column, row| {
+			costs.get_cost(pixels[row][column])
+		})
+	}
+	/// Create a [SectorCostFields] from a [Tiled](https://www.mapeditor.org/) map (`.tmx` or `.tmj`) where each tile represents a [FieldCell]. The cost of each tile is determined by `costs`, see [TiledCosts]
+	#[cfg(feature = "tiled")]
+	pub fn from_tiled(map_dimensions: &MapDimensions, path: String, costs: &TiledCosts) -> Self {
+		let map = TiledMap::from_file(&path);
+		// ensure the size of the map actually represents the number of FieldCells required by the MapDimensions
+		let columns = map_dimensions.get_total_field_cell_columns();
+		let rows = map_dimensions.get_total_field_cell_rows();
+		if map.get_width() != columns || map.get_height() != rows {
+			panic!(
+				"Tiled map has incorrect size, expected {}x{} tiles, found {}x{}",
+				columns,
+				rows,
+				map.get_width(),
+				map.get_height()
+			);
+		}
+		SectorCostFields::from_map_cells(map_dimensions, |column, row| {
+			map.get_cost(column, row, costs)
+		})
+	}
+	/// Create a [SectorCostFields] from an IntGrid layer of an [LDtk](https://ldtk.io/) project where each cell represents a [FieldCell]. `costs` selects the level and layer to read and the cost of each IntGrid value, see [LdtkCosts]. An [LdtkError] is returned if the project can't be read or the size of the layer doesn't match the `map_dimensions`
+	#[cfg(feature = "ldtk")]
+	pub fn from_ldtk(
+		map_dimensions: &MapDimensions,
+		path: String,
+		costs: &LdtkCosts,
+	) -> Result<Self, LdtkError> {
+		let grid = LdtkIntGrid::from_file(&path, costs)?;
+		// ensure the size of the layer actually represents the number of FieldCells required by the MapDimensions
+		let columns = map_dimensions.get_total_field_cell_columns();
+		let rows = map_dimensions.get_total_field_cell_rows();
+		if grid.get_width() != columns || grid.get_height() != rows {
+			return Err(LdtkError::SizeMismatch {
+				expected: (columns, rows),
+				found: (grid.get_width(), grid.get_height()),
+			});
+		}
+		Ok(SectorCostFields::from_map_cells(
+			map_dimensions,
+			|column, row| grid.get_cost(column, row, costs),
+		))
+	}
+	/// From a list of 3d meshes and their [Transform] rasterise the geometry onto the `x-z` grid of [FieldCell]s. The floor of each cell is the highest upward facing surface beneath its centre with enough clearance above it, so a bridge or rooftop is chosen over the ground beneath it, and `settings` determine whether the floor can be walked on based on its slope and the difference in height to neighbouring floors, see [MeshCostSettings]. A cell where no surface has enough clearance is impassable
+	#[cfg(feature = "3d")]
+	pub fn from_bevy_3d_meshes(
+		map_dimensions: &MapDimensions,
+		meshes: &[(&Mesh, Transform)],
+		settings: &MeshCostSettings,
+	) -> Self {
+		let triangles: Vec<[Vec3; 3]> = meshes
+			.iter()
+			.flat_map(|(mesh, transform)| get_mesh_triangles(mesh, transform))
+			.collect();
+		let rows = rasterise_triangles(map_dimensions, &triangles, settings);
+		SectorCostFields::from_rows(map_dimensions, &rows, "Rasterised meshes")
+	}
+	/// Create a [SectorCostFields] from the steepness of 3d terrain, the steepest slope between each [FieldCell] and its neighbours in the [ElevationField] is translated into a cost by `costs`, see [SlopeCosts]
+	#[cfg(feature = "3d")]
+	pub fn from_elevation(
+		map_dimensions: &MapDimensions,
+		elevation: &ElevationField,
+		costs: &SlopeCosts,
+	) -> Self {
+		SectorCostFields::from_map_cells(map_dimensions, |column, row| {
+			costs.get_cost(elevation.get_slope(map_dimensions, column, row))
+		})
+	}
 	/// From a list of meshes extract the outer edges of each mesh and project an (MxN) FieldCell representation of edges over the dimensions. The projections undergo two tests to see if a FieldCell sits inside a mesh (thereby being marked as pathable):
 	/// - The top-right vertex of each field cell is tested for mesh edge intersections, a horizontal line is taken from the vertex point to max-x and if the line intersects mesh edges an odd number of times, or touches an edge an even number of times, then it is marked as potentially being within the mesh
 	/// - From the marked FieldCells the four edges of each is then tested to see if it intersects any mesh edges, if so then it is overlapping a mesh boundary and so not fully inside the mesh, otherwise it is in the mesh and considered a pathable cell and given the cost `internal_cost` - all cells outside of the meshes are initialised with a cost of `external_cost`
@@ -988,6 +1399,348 @@ mod tests {
 		let _cost_fields = SectorCostFields::from_csv_dir(&map_dimensions, path);
 	}
 	#[test]
+	#[cfg(feature = "csv")]
+	fn sector_cost_fields_file_single_csv() {
+		let map_dimensions = MapDimensions::new(1920, 1920, 640, 16.0);
+		let dir = env!("CARGO_MANIFEST_DIR").to_string() + "/assets/csv/vis_portals/";
+		let path = env!("CARGO_MANIFEST_DIR").to_string() + "/assets/csv/vis_portals.csv";
+		let from_dir = SectorCostFields::from_csv_dir(&map_dimensions, dir);
+		let from_file = SectorCostFields::from_csv(&map_dimensions, path);
+		for (sector, field) in from_dir.get_scaled().iter() {
+			assert_eq!(
+				field.get(),
+				from_file.get_scaled().get(sector).unwrap().get()
+			);
+		}
+	}
+	#[test]
+	#[cfg(feature = "csv")]
+	fn sector_cost_fields_csv_round_trip() {
+		let map_dimensions = MapDimensions::new(30, 20, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let changes = vec![
+			(SectorID::new(2, 1), FieldCell::new(9, 9), 255),
+			(SectorID::new(1, 0), FieldCell::new(3, 4), 42),
+		];
+		cost_fields.set_field_cell_values(&changes, &map_dimensions);
+		let path = std::env::temp_dir()
+			.join("bevy_flowfield_tiles_round_trip.csv")
+			.into_os_string()
+			.into_string()
+			.unwrap();
+		cost_fields.to_csv(&map_dimensions, path.clone(), CostLayer::Baseline, 0);
+		let read = SectorCostFields::from_csv(&map_dimensions, path);
+		for (sector, field) in cost_fields.get_baseline().iter() {
+			assert_eq!(field.get(), read.get_baseline().get(sector).unwrap().get());
+		}
+	}
+	#[test]
+	#[cfg(feature = "csv")]
+	fn sector_cost_fields_csv_dir_round_trip() {
+		let map_dimensions = MapDimensions::new(30, 20, 10, 1.5);
+		// a gap too narrow for the actor
+		let changes = vec![
+			(SectorID::new(0, 0), FieldCell::new(4, 3), 255),
+			(SectorID::new(0, 0), FieldCell::new(4, 5), 255),
+			(SectorID::new(2, 1), FieldCell::new(3, 7), 17),
+		];
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		cost_fields.set_field_cell_values(&changes, &map_dimensions);
+		let directory = std::env::temp_dir().join("bevy_flowfield_tiles_csv_dir");
+		let directory = directory.into_os_string().into_string().unwrap();
+		cost_fields.to_csv_dir(directory.clone(), CostLayer::Baseline);
+		let read = SectorCostFields::from_csv_dir(&map_dimensions, directory.clone());
+		for (sector, field) in cost_fields.get_baseline().iter() {
+			assert_eq!(field.get(), read.get_baseline().get(sector).unwrap().get());
+		}
+		for (sector, field) in cost_fields.get_scaled().iter() {
+			assert_eq!(field.get(), read.get_scaled().get(sector).unwrap().get());
+		}
+		// the scaled layer has the gap closed off
+		cost_fields.to_csv_dir(directory.clone(), CostLayer::Scaled);
+		let read = SectorCostFields::from_csv_dir(&map_dimensions, directory);
+		let field = read.get_baseline().get(&SectorID::new(0, 0)).unwrap();
+		assert_eq!(255, field.get_field_cell_value(FieldCell::new(4, 4)));
+	}
+	#[test]
+	#[cfg(feature = "csv")]
+	fn sector_cost_fields_csv_dir_layered_round_trip() {
+		let map_dimensions = MapDimensions::new(30, 20, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut floor = SectorCostFields::new(&map_dimensions);
+		floor.set_field_cell_value(
+			SectorID::new(1, 1),
+			255,
+			FieldCell::new(2, 3),
+			&map_dimensions,
+		);
+		cost_fields.insert_sector_layer(1, &floor);
+		let directory = std::env::temp_dir().join("bevy_flowfield_tiles_csv_dir_layered");
+		let _ = std::fs::remove_dir_all(&directory);
+		let directory = directory.into_os_string().into_string().unwrap();
+		cost_fields.to_csv_dir(directory.clone(), CostLayer::Baseline);
+		assert!(std::path::Path::new(&directory).join("1_1_1.csv").exists());
+		let read = SectorCostFields::from_csv_dir(&map_dimensions, directory);
+		assert_eq!(2, read.get_sector_layer_count());
+		for (sector, field) in cost_fields.get_baseline().iter() {
+			assert_eq!(field.get(), read.get_baseline().get(sector).unwrap().get());
+		}
+	}
+	#[test]
+	fn rows_of_sector_layer() {
+		let map_dimensions = MapDimensions::new(20, 10, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut floor = SectorCostFields::new(&map_dimensions);
+		floor.set_field_cell_value(
+			SectorID::new(1, 0),
+			255,
+			FieldCell::new(2, 3),
+			&map_dimensions,
+		);
+		cost_fields.insert_sector_layer(1, &floor);
+		let ground = cost_fields.get_rows(&map_dimensions, CostLayer::Baseline, 0);
+		assert_eq!(1, ground[3][12]);
+		let upper = cost_fields.get_rows(&map_dimensions, CostLayer::Baseline, 1);
+		assert_eq!(255, upper[3][12]);
+	}
+	#[test]
+	#[cfg(feature = "ron")]
+	fn sector_cost_fields_ron_round_trip() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		let changes = vec![
+			(SectorID::new(1, 0), FieldCell::new(0, 2), 255),
+			(SectorID::new(0, 1), FieldCell::new(9, 9), 88),
+		];
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		cost_fields.set_field_cell_values(&changes, &map_dimensions);
+		let path = std::env::temp_dir().join("bevy_flowfield_tiles_round_trip.ron");
+		let path = path.into_os_string().into_string().unwrap();
+		cost_fields.to_ron(path.clone());
+		let read = SectorCostFields::from_ron(path, &map_dimensions);
+		for (sector, field) in cost_fields.get_baseline().iter() {
+			assert_eq!(field.get(), read.get_baseline().get(sector).unwrap().get());
+		}
+		for (sector, field) in cost_fields.get_scaled().iter() {
+			assert_eq!(field.get(), read.get_scaled().get(sector).unwrap().get());
+		}
+	}
+	#[test]
+	#[cfg(feature = "heightmap")]
+	fn sector_cost_fields_heightmap_round_trip() {
+		let map_dimensions = MapDimensions::new(20, 10, 10, 0.5);
+		let changes = vec![
+			(SectorID::new(1, 0), FieldCell::new(0, 2), 255),
+			(SectorID::new(0, 0), FieldCell::new(5, 5), 120),
+		];
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		cost_fields.set_field_cell_values(&changes, &map_dimensions);
+		let path = std::env::temp_dir().join("bevy_flowfield_tiles_round_trip.png");
+		let path = path.into_os_string().into_string().unwrap();
+		cost_fields.to_heightmap(&map_dimensions, path.clone(), CostLayer::Baseline, 0);
+		let read = SectorCostFields::from_heightmap(&map_dimensions, path);
+		for (sector, field) in cost_fields.get_baseline().iter() {
+			assert_eq!(field.get(), read.get_baseline().get(sector).unwrap().get());
+		}
+	}
+	#[test]
+	#[cfg(feature = "heightmap")]
+	fn sector_cost_fields_heightmap_palette() {
+		// a non-square map
+		let map_dimensions = MapDimensions::new(30, 10, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let changes = vec![
+			(SectorID::new(2, 0), FieldCell::new(9, 9), 255),
+			(SectorID::new(1, 0), FieldCell::new(0, 0), 55),
+		];
+		cost_fields.set_field_cell_values(&changes, &map_dimensions);
+		let path = std::env::temp_dir().join("bevy_flowfield_tiles_palette.png");
+		let path = path.into_os_string().into_string().unwrap();
+		cost_fields.to_heightmap(&map_dimensions, path.clone(), CostLayer::Baseline, 0);
+		// greys written by the exporter, 254 is open ground
+		let palette = ColourPalette::new(PaletteMatch::Nearest)
+			.with_colour([254, 254, 254], 3)
+			.with_colour([0, 0, 0], 255)
+			.with_colour([180, 180, 180], 90);
+		let costs = HeightmapCosts::Palette(palette);
+		let read = SectorCostFields::from_heightmap_with_costs(&map_dimensions, path, &costs);
+		let baseline = read.get_baseline();
+		let field = baseline.get(&SectorID::new(2, 0)).unwrap();
+		assert_eq!(255, field.get_field_cell_value(FieldCell::new(9, 9)));
+		assert_eq!(3, field.get_field_cell_value(FieldCell::new(0, 0)));
+		let field = baseline.get(&SectorID::new(1, 0)).unwrap();
+		assert_eq!(90, field.get_field_cell_value(FieldCell::new(0, 0)));
+	}
+	#[test]
+	#[cfg(feature = "3d")]
+	fn sector_cost_fields_3d_meshes() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		let ground = Plane3d::default().mesh().size(20.0, 20.0).build();
+		let wall = Mesh::from(Cuboid::new(1.0, 3.0, 20.0));
+		let meshes = vec![
+			(&ground, Transform::IDENTITY),
+			(&wall, Transform::from_xyz(0.5, 1.5, 0.0)),
+		];
+		let settings = MeshCostSettings::default();
+		let cost_fields =
+			SectorCostFields::from_bevy_3d_meshes(&map_dimensions, &meshes, &settings);
+		let baseline = cost_fields.get_baseline();
+		let field = baseline.get(&SectorID::new(1, 1)).unwrap();
+		assert_eq!(255, field.get_field_cell_value(FieldCell::new(0, 4)));
+		assert_eq!(1, field.get_field_cell_value(FieldCell::new(1, 4)));
+		let field = baseline.get(&SectorID::new(0, 0)).unwrap();
+		assert_eq!(1, field.get_field_cell_value(FieldCell::new(9, 4)));
+	}
+	#[test]
+	#[cfg(feature = "3d")]
+	fn sector_cost_fields_elevation() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		// a gentle ramp along the columns ending in a cliff at column 15
+		let elevation = ElevationField::from_map_cells(&map_dimensions, |column, _| {
+			if column < 15 {
+				column as f32 * 0.2
+			} else {
+				10.0
+			}
+		});
+		let cost_fields =
+			SectorCostFields::from_elevation(&map_dimensions, &elevation, &SlopeCosts::default());
+		let baseline = cost_fields.get_baseline();
+		let field = baseline.get(&SectorID::new(0, 0)).unwrap();
+		// atan(0.2) is roughly 11.3 degrees
+		assert_eq!(65, field.get_field_cell_value(FieldCell::new(5, 5)));
+		let field = baseline.get(&SectorID::new(1, 1)).unwrap();
+		assert_eq!(255, field.get_field_cell_value(FieldCell::new(4, 5)));
+		assert_eq!(255, field.get_field_cell_value(FieldCell::new(5, 5)));
+		assert_eq!(1, field.get_field_cell_value(FieldCell::new(7, 5)));
+	}
+	#[test]
+	fn sector_cost_fields_file_ascii() {
+		let map_dimensions = MapDimensions::new(1920, 1920, 640, 16.0);
+		let path = env!("CARGO_MANIFEST_DIR").to_string() + "/assets/ascii/vis_portals.txt";
+		let legend = AsciiLegend::default();
+		let cost_fields = SectorCostFields::from_ascii(&map_dimensions, path.clone(), &legend);
+		let field = cost_fields
+			.get_baseline()
+			.get(&SectorID::new(0, 0))
+			.unwrap();
+		assert_eq!(1, field.get_field_cell_value(FieldCell::new(0, 0)));
+		assert_eq!(255, field.get_field_cell_value(FieldCell::new(3, 0)));
+		// writing the map back out reproduces the file
+		let out = std::env::temp_dir()
+			.join("bevy_flowfield_tiles_round_trip.txt")
+			.into_os_string()
+			.into_string()
+			.unwrap();
+		cost_fields.to_ascii(
+			&map_dimensions,
+			out.clone(),
+			&legend,
+			CostLayer::Baseline,
+			0,
+		);
+		assert_eq!(
+			std::fs::read_to_string(path).unwrap(),
+			std::fs::read_to_string(out).unwrap()
+		);
+	}
+	#[test]
+	#[should_panic]
+	fn sector_cost_fields_ascii_wrong_size() {
+		let map_dimensions = MapDimensions::new(20, 10, 10, 0.5);
+		let rows = AsciiLegend::default().parse(&"..........\n".repeat(10));
+		SectorCostFields::from_rows(&map_dimensions, &rows, "Ascii map");
+	}
+	#[test]
+	#[cfg(feature = "tiled")]
+	fn sector_cost_fields_file_tiled() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		let path = env!("CARGO_MANIFEST_DIR").to_string() + "/assets/tiled/map.tmx";
+		let cost_fields =
+			SectorCostFields::from_tiled(&map_dimensions, path, &TiledCosts::default());
+		let baseline = cost_fields.get_baseline();
+		assert_eq!(4, baseline.len());
+		let wall = baseline.get(&SectorID::new(1, 0)).unwrap();
+		assert_eq!(255, wall.get_field_cell_value(FieldCell::new(0, 5)));
+		let mud = baseline.get(&SectorID::new(0, 0)).unwrap();
+		assert_eq!(50, mud.get_field_cell_value(FieldCell::new(3, 3)));
+		let rock = baseline.get(&SectorID::new(1, 1)).unwrap();
+		assert_eq!(255, rock.get_field_cell_value(FieldCell::new(5, 5)));
+		assert_eq!(1, rock.get_field_cell_value(FieldCell::new(0, 9)));
+	}
+	#[test]
+	#[cfg(feature = "ldtk")]
+	fn sector_cost_fields_file_ldtk() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		let path = env!("CARGO_MANIFEST_DIR").to_string() + "/assets/ldtk/project.ldtk";
+		let costs = LdtkCosts::default()
+			.with_value_cost(1, 255)
+			.with_value_cost(2, 50);
+		let cost_fields =
+			SectorCostFields::from_ldtk(&map_dimensions, path.clone(), &costs).unwrap();
+		let baseline = cost_fields.get_baseline();
+		let wall = baseline.get(&SectorID::new(1, 0)).unwrap();
+		assert_eq!(255, wall.get_field_cell_value(FieldCell::new(0, 5)));
+		let mud = baseline.get(&SectorID::new(0, 0)).unwrap();
+		assert_eq!(50, mud.get_field_cell_value(FieldCell::new(3, 3)));
+		// the cave level is only 10x10
+		let result = SectorCostFields::from_ldtk(&map_dimensions, path, &costs.with_level("Cave"));
+		assert!(matches!(
+			result,
+			Err(LdtkError::SizeMismatch {
+				expected: (20, 20),
+				found: (10, 10)
+			})
+		));
+	}
+	#[test]
+	fn batched_matches_full_rescale() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 2.0);
+		let changes = vec![
+			(SectorID::new(1, 1), FieldCell::new(0, 4), 255),
+			(SectorID::new(1, 1), FieldCell::new(0, 6), 255),
+			(SectorID::new(0, 1), FieldCell::new(8, 5), 255),
+			(SectorID::new(2, 2), FieldCell::new(3, 3), 20),
+		];
+		let mut full = SectorCostFields::new(&map_dimensions);
+		for (sector, cell, value) in changes.iter() {
+			let field = full.get_baseline_mut().get_mut(sector).unwrap();
+			field.set_field_cell_value(*value, *cell);
+		}
+		full.scale_all_costfields(&map_dimensions);
+		let mut batched = SectorCostFields::new(&map_dimensions);
+		let mut changed = batched.set_field_cell_values(&changes, &map_dimensions);
+		changed.sort();
+		for (sector, field) in full.get_scaled().iter() {
+			assert_eq!(field.get(), batched.get_scaled().get(sector).unwrap().get());
+		}
+		assert!(changed.contains(&SectorID::new(0, 1)));
+		assert!(changed.contains(&SectorID::new(1, 1)));
+		assert!(changed.contains(&SectorID::new(2, 2)));
+		assert!(!changed.contains(&SectorID::new(2, 0)));
+	}
+	#[test]
+	fn batched_restore_clears_scaling() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 2.0);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector_id = SectorID::new(1, 1);
+		let first = FieldCell::new(0, 4);
+		let second = FieldCell::new(0, 6);
+		cost_fields.set_field_cell_values(
+			&[(sector_id, first, 255), (sector_id, second, 255)],
+			&map_dimensions,
+		);
+		// gap closed by scaling
+		let gap = FieldCell::new(0, 5);
+		let scaled = cost_fields.get_scaled().get(&sector_id).unwrap();
+		assert_eq!(255, scaled.get_field_cell_value(gap));
+		// removing one side reopens the gap
+		let changed = cost_fields.set_field_cell_values(&[(sector_id, second, 1)], &map_dimensions);
+		let scaled = cost_fields.get_scaled().get(&sector_id).unwrap();
+		assert_eq!(1, scaled.get_field_cell_value(gap));
+		assert_eq!(1, scaled.get_field_cell_value(second));
+		assert!(changed.contains(&sector_id));
+	}
+	#[test]
 	fn scale_north_one() {
 		let map_dimensions = MapDimensions::new(10, 10, 10, 0.5);
 		let mut cost_fields = SectorCostFields::new(&map_dimensions);
@@ -0,0 +1,198 @@
+//! An optional overlay of [DensityField]s for every sector which can be inserted alongside the [FlowFieldTilesBundle] to make crowded areas more expensive to path through
+//!
+
+use std::collections::BTreeMap;
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Keys represent unique sector IDs and values are the [DensityField] associated with that sector.
+///
+/// Every `refresh_interval` seconds each [DensityField] is reduced by `decay` and then the cells occupied by actors are increased by `cost_per_actor` (up to `max_cost`). When an [IntegrationField] is calculated the density of each cell is added to its [CostField] value, and cached [FlowField]s are rebuilt once the density of their sector has changed by more than `rebuild_threshold`
+#[derive(Component, Clone, Reflect)]
+#[reflect(Component)]
+pub struct SectorDensityFields {
+	/// [DensityField] of each sector
+	fields: BTreeMap<SectorID, DensityField>,
+	/// Amount of density an actor adds to the cell it occupies each refresh
+	cost_per_actor: u8,
+	/// Upper limit of the density of any cell
+	max_cost: u8,
+	/// Amount of density removed from every cell each refresh
+	decay: u8,
+	/// Seconds between each refresh of the density
+	refresh_interval: f32,
+	/// Seconds elapsed since the last refresh
+	time_since_refresh: f32,
+	/// Change in the density of any cell of a sector after which the cached fields crossing it are rebuilt
+	rebuild_threshold: u8,
+	/// The density of each sector when the cached fields crossing it were last rebuilt
+	rebuilt: BTreeMap<SectorID, DensityField>,
+}
+
+impl SectorDensityFields {
+	/// Create a new instance of [SectorDensityFields] with an empty [DensityField] for each sector of every layer of `sector_cost_fields`. By default each actor adds `4` to its cell up to a limit of `32`, every `0.5` seconds all cells decay by `4` and cached fields are rebuilt once the density of a cell has changed by more than `8`
+	pub fn new(sector_cost_fields: &SectorCostFields) -> Self {
+		let fields = sector_cost_fields
+			.get_baseline()
+			.keys()
+			.map(|sector_id| (*sector_id, DensityField::default()))
+			.collect();
+		SectorDensityFields {
+			fields,
+			cost_per_actor: 4,
+			max_cost: 32,
+			decay: 4,
+			refresh_interval: 0.5,
+			time_since_refresh: 0.0,
+			rebuild_threshold: 8,
+			rebuilt: BTreeMap::new(),
+		}
+	}
+	/// Get a reference to the map of sectors and [DensityField]
+	pub fn get(&self) -> &BTreeMap<SectorID, DensityField> {
+		&self.fields
+	}
+	/// Get a mutable reference to the map of sectors and [DensityField]
+	pub fn get_mut(&mut self) -> &mut BTreeMap<SectorID, DensityField> {
+		&mut self.fields
+	}
+	/// Get the amount of density an actor adds to the cell it occupies each refresh
+	pub fn get_cost_per_actor(&self) -> u8 {
+		self.cost_per_actor
+	}
+	/// Set the amount of density an actor adds to the cell it occupies each refresh
+	pub fn set_cost_per_actor(&mut self, cost_per_actor: u8) {
+		self.cost_per_actor = cost_per_actor;
+	}
+	/// Get the upper limit of the density of any cell
+	pub fn get_max_cost(&self) -> u8 {
+		self.max_cost
+	}
+	/// Set the upper limit of the density of any cell
+	pub fn set_max_cost(&mut self, max_cost: u8) {
+		self.max_cost = max_cost;
+	}
+	/// Get the amount of density removed from every cell each refresh
+	pub fn get_decay(&self) -> u8 {
+		self.decay
+	}
+	/// Set the amount of density removed from every cell each refresh
+	pub fn set_decay(&mut self, decay: u8) {
+		self.decay = decay;
+	}
+	/// Get the seconds between each refresh of the density
+	pub fn get_refresh_interval(&self) -> f32 {
+		self.refresh_interval
+	}
+	/// Set the seconds between each refresh of the density
+	pub fn set_refresh_interval(&mut self, refresh_interval: f32) {
+		self.refresh_interval = refresh_interval;
+	}
+	/// Get the change in the density of any cell of a sector after which the cached fields crossing it are rebuilt
+	pub fn get_rebuild_threshold(&self) -> u8 {
+		self.rebuild_threshold
+	}
+	/// Set the change in the density of any cell of a sector after which the cached fields crossing it are rebuilt
+	pub fn set_rebuild_threshold(&mut self, rebuild_threshold: u8) {
+		self.rebuild_threshold = rebuild_threshold;
+	}
+	/// Advance the refresh timer by `delta` seconds, returns `true` when a refresh is due
+	pub fn tick(&mut self, delta: f32) -> bool {
+		self.time_since_refresh += delta;
+		if self.time_since_refresh >= self.refresh_interval {
+			self.time_since_refresh = 0.0;
+			true
+		} else {
+			false
+		}
+	}
+	/// Decay every [DensityField] and then add the density of actors occupying each `(SectorID, FieldCell)` of `occupied`. A sector without a [DensityField], such as one of a layer inserted afterwards, is given one
+	pub fn refresh(&mut self, occupied: &[(SectorID, FieldCell)]) {
+		for field in self.fields.values_mut() {
+			field.decay(self.decay);
+		}
+		for (sector_id, field_cell) in occupied.iter() {
+			self.fields.entry(*sector_id).or_default().add_density(
+				*field_cell,
+				self.cost_per_actor,
+				self.max_cost,
+			);
+		}
+	}
+	/// Get each sector whose density has changed by more than the rebuild threshold since it was last returned, the cached fields crossing these sectors should be rebuilt
+	pub fn take_changed_sectors(&mut self) -> Vec<SectorID> {
+		let mut changed = Vec::new();
+		for (sector_id, field) in self.fields.iter() {
+			let rebuilt = self.rebuilt.entry(*sector_id).or_default();
+			if field.get_max_difference(rebuilt) > self.rebuild_threshold {
+				*rebuilt = *field;
+				changed.push(*sector_id);
+			}
+		}
+		changed
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn tick_interval() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut density = SectorDensityFields::new(&sector_cost_fields);
+		density.set_refresh_interval(1.0);
+		assert!(!density.tick(0.6));
+		assert!(density.tick(0.6));
+		assert!(!density.tick(0.6));
+	}
+	#[test]
+	fn refresh_occupied() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut density = SectorDensityFields::new(&sector_cost_fields);
+		let sector = SectorID::new(1, 2);
+		let cell = FieldCell::new(3, 3);
+		density.refresh(&[(sector, cell), (sector, cell)]);
+		let result = density
+			.get()
+			.get(&sector)
+			.unwrap()
+			.get_field_cell_value(cell);
+		assert_eq!(8, result);
+		// nobody left in the cell so it decays
+		density.refresh(&[]);
+		let result = density
+			.get()
+			.get(&sector)
+			.unwrap()
+			.get_field_cell_value(cell);
+		assert_eq!(4, result);
+	}
+	#[test]
+	fn covers_every_layer() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let floor = SectorCostFields::new(&map_dimensions);
+		sector_cost_fields.insert_sector_layer(1, &floor);
+		let density = SectorDensityFields::new(&sector_cost_fields);
+		assert_eq!(18, density.get().len());
+		assert!(density.get().contains_key(&SectorID::new_layered(2, 2, 1)));
+	}
+	#[test]
+	fn changed_sectors_beyond_threshold() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		let mut density = SectorDensityFields::new(&sector_cost_fields);
+		let sector = SectorID::new(1, 2);
+		let cell = FieldCell::new(3, 3);
+		density.refresh(&[(sector, cell), (sector, cell)]);
+		// 8 doesn't exceed the default threshold
+		assert!(density.take_changed_sectors().is_empty());
+		density.refresh(&[(sector, cell), (sector, cell), (sector, cell)]);
+		assert_eq!(vec![sector], density.take_changed_sectors());
+		// compared with the density when the sector was last returned
+		assert!(density.take_changed_sectors().is_empty());
+	}
+}
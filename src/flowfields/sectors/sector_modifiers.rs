@@ -0,0 +1,347 @@
+//! Temporary adjustments layered on top of the baseline [CostField]s, such as fire, smoke or a collapsed bridge. Each modifier is identified by a [CostModifierHandle] and can optionally expire after a duration
+//!
+
+use std::{
+	collections::BTreeMap,
+	sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Source of unique [CostModifierHandle]s
+static NEXT_MODIFIER_HANDLE: AtomicU64 = AtomicU64::new(0);
+
+/// Unique identifier of a cost modifier, used to remove it later
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Reflect)]
+pub struct CostModifierHandle(u64);
+
+impl CostModifierHandle {
+	/// Create a new unique handle. Handles are ordered by creation so newer modifiers are applied on top of older ones
+	pub fn new() -> Self {
+		CostModifierHandle(NEXT_MODIFIER_HANDLE.fetch_add(1, Ordering::Relaxed))
+	}
+	/// Get the raw ID of the handle
+	pub fn get(&self) -> u64 {
+		self.0
+	}
+}
+
+impl Default for CostModifierHandle {
+	fn default() -> Self {
+		CostModifierHandle::new()
+	}
+}
+
+/// How a modifier changes the cost of the cells it covers
+#[derive(Clone, Copy, PartialEq, Debug, Reflect)]
+pub enum CostModifier {
+	/// Replace the cost, i.e `Set(255)` to make cells impassable
+	Set(u8),
+	/// Add to (or with a negative value subtract from) the cost. The result is kept within `1..=254` so an adjustment alone never makes a cell impassable or makes an impassable cell passable
+	Add(i16),
+}
+
+impl CostModifier {
+	/// Apply the modifier to a cost
+	pub fn apply(&self, cost: u8) -> u8 {
+		match self {
+			CostModifier::Set(value) => *value,
+			CostModifier::Add(amount) => {
+				if cost == 255 {
+					cost
+				} else {
+					(cost as i16 + amount).clamp(1, 254) as u8
+				}
+			}
+		}
+	}
+}
+
+/// A modifier which is currently in effect
+#[derive(Clone)]
+struct ActiveCostModifier {
+	/// Every cell the modifier covers
+	cells: Vec<(SectorID, FieldCell)>,
+	/// Change made to the cost of each cell
+	modifier: CostModifier,
+	/// Seconds until the modifier expires, [None] if it lasts until removed or has already expired
+	remaining: Option<f32>,
+}
+
+/// Tracks the active cost modifiers of a map. Modifiers never alter the baseline [CostField]s of [SectorCostFields], instead the modifiers covering each cell are recorded with [SectorCostFields::set_field_cell_modifiers] and applied on top of the baseline to build the effective [CostField]s which get scaled. Removing a modifier therefore restores exactly the baseline cost, including any permanent change made to the cell while it was modified
+#[derive(Component, Clone, Default)]
+pub struct SectorCostModifiers {
+	/// Modifiers currently in effect
+	modifiers: BTreeMap<CostModifierHandle, ActiveCostModifier>,
+	/// Modifiers covering each cell in the order they are applied
+	cells: BTreeMap<(SectorID, FieldCell), Vec<CostModifierHandle>>,
+}
+
+impl SectorCostModifiers {
+	/// Is the modifier with `handle` in effect
+	pub fn contains(&self, handle: CostModifierHandle) -> bool {
+		self.modifiers.contains_key(&handle)
+	}
+	/// Number of modifiers in effect
+	pub fn len(&self) -> usize {
+		self.modifiers.len()
+	}
+	/// Are there no modifiers in effect
+	pub fn is_empty(&self) -> bool {
+		self.modifiers.is_empty()
+	}
+	/// Is the cell covered by at least one modifier
+	pub fn is_modified(&self, sector_id: SectorID, field_cell: FieldCell) -> bool {
+		self.cells.contains_key(&(sector_id, field_cell))
+	}
+	/// Add a modifier to `cells`, cells in sectors which don't exist are ignored.
+	///
+	/// Returns the IDs of the sectors whose cells are now covered by the modifier, their fields should be rescaled with [SectorCostFields::rescale_costfields]
+	pub fn add(
+		&mut self,
+		handle: CostModifierHandle,
+		cells: Vec<(SectorID, FieldCell)>,
+		modifier: CostModifier,
+		duration: Option<f32>,
+		sector_cost_fields: &mut SectorCostFields,
+	) -> Vec<SectorID> {
+		if self.contains(handle) {
+			warn!("Cost modifier {:?} already exists", handle);
+			return vec![];
+		}
+		let mut covered = Vec::new();
+		for (sector_id, field_cell) in cells.into_iter() {
+			if !sector_cost_fields.get_baseline().contains_key(&sector_id) {
+				continue;
+			}
+			let handles = self.cells.entry((sector_id, field_cell)).or_default();
+			if !handles.contains(&handle) {
+				handles.push(handle);
+				handles.sort();
+				covered.push((sector_id, field_cell));
+			}
+		}
+		self.modifiers.insert(
+			handle,
+			ActiveCostModifier {
+				cells: covered.clone(),
+				modifier,
+				remaining: duration,
+			},
+		);
+		self.update_cells(&covered, sector_cost_fields)
+	}
+	/// Remove a modifier, cells it covered go back to their baseline costs with the remaining modifiers applied.
+	///
+	/// Returns the IDs of the sectors whose cells were covered by the modifier, their fields should be rescaled with [SectorCostFields::rescale_costfields]
+	pub fn remove(
+		&mut self,
+		handle: CostModifierHandle,
+		sector_cost_fields: &mut SectorCostFields,
+	) -> Vec<SectorID> {
+		let Some(active) = self.modifiers.remove(&handle) else {
+			return vec![];
+		};
+		for key in active.cells.iter() {
+			if let Some(handles) = self.cells.get_mut(key) {
+				handles.retain(|h| *h != handle);
+				if handles.is_empty() {
+					self.cells.remove(key);
+				}
+			}
+		}
+		self.update_cells(&active.cells, sector_cost_fields)
+	}
+	/// Advance the duration of every timed modifier by `delta` seconds and return the handles of those which have expired, a modifier with a duration of zero or less expires on the first tick. Expired modifiers are not removed, see [SectorCostModifiers::remove]
+	pub fn tick(&mut self, delta: f32) -> Vec<CostModifierHandle> {
+		let mut expired = Vec::new();
+		for (handle, active) in self.modifiers.iter_mut() {
+			if let Some(remaining) = active.remaining {
+				let remaining = remaining - delta;
+				if remaining <= 0.0 {
+					// only reported once
+					active.remaining = None;
+					expired.push(*handle);
+				} else {
+					active.remaining = Some(remaining);
+				}
+			}
+		}
+		expired
+	}
+	/// Record the modifiers now covering each of `keys` in the [SectorCostFields] and return the IDs of the sectors they lie in
+	fn update_cells(
+		&self,
+		keys: &[(SectorID, FieldCell)],
+		sector_cost_fields: &mut SectorCostFields,
+	) -> Vec<SectorID> {
+		let mut sectors = Vec::new();
+		for (sector_id, field_cell) in keys.iter() {
+			let modifiers = self
+				.cells
+				.get(&(*sector_id, *field_cell))
+				.map(|handles| {
+					handles
+						.iter()
+						.filter_map(|handle| self.modifiers.get(handle))
+						.map(|active| active.modifier)
+						.collect()
+				})
+				.unwrap_or_default();
+			sector_cost_fields.set_field_cell_modifiers(*sector_id, *field_cell, modifiers);
+			if !sectors.contains(sector_id) {
+				sectors.push(*sector_id);
+			}
+		}
+		sectors
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Get the cost of a cell in the effective and baseline [CostField]s
+	fn get_costs(
+		cost_fields: &SectorCostFields,
+		sector_id: SectorID,
+		field_cell: FieldCell,
+	) -> (u8, u8) {
+		(
+			cost_fields
+				.get_effective()
+				.get(&sector_id)
+				.unwrap()
+				.get_field_cell_value(field_cell),
+			cost_fields
+				.get_baseline()
+				.get(&sector_id)
+				.unwrap()
+				.get_field_cell_value(field_cell),
+		)
+	}
+	#[test]
+	fn overlapping_modifiers_restore() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector = SectorID::new(0, 0);
+		let cell = FieldCell::new(4, 4);
+		cost_fields.set_field_cell_value(sector, 7, cell, &map_dimensions);
+		let mut modifiers = SectorCostModifiers::default();
+		let fire = CostModifierHandle::new();
+		let wall = CostModifierHandle::new();
+		let edited = modifiers.add(
+			fire,
+			vec![(sector, cell)],
+			CostModifier::Add(20),
+			None,
+			&mut cost_fields,
+		);
+		assert_eq!(vec![sector], edited);
+		cost_fields.rescale_costfields(&edited, &map_dimensions);
+		assert_eq!((27, 7), get_costs(&cost_fields, sector, cell));
+		let edited = modifiers.add(
+			wall,
+			vec![(sector, cell)],
+			CostModifier::Set(255),
+			None,
+			&mut cost_fields,
+		);
+		cost_fields.rescale_costfields(&edited, &map_dimensions);
+		assert_eq!((255, 7), get_costs(&cost_fields, sector, cell));
+		assert_eq!(
+			255,
+			cost_fields
+				.get_scaled()
+				.get(&sector)
+				.unwrap()
+				.get_field_cell_value(cell)
+		);
+		// removing the older modifier leaves the newer one in place
+		let edited = modifiers.remove(fire, &mut cost_fields);
+		cost_fields.rescale_costfields(&edited, &map_dimensions);
+		assert_eq!((255, 7), get_costs(&cost_fields, sector, cell));
+		let edited = modifiers.remove(wall, &mut cost_fields);
+		cost_fields.rescale_costfields(&edited, &map_dimensions);
+		assert_eq!((7, 7), get_costs(&cost_fields, sector, cell));
+		assert!(modifiers.is_empty());
+		assert!(!modifiers.is_modified(sector, cell));
+	}
+	#[test]
+	fn permanent_change_beneath_modifier() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let sector = SectorID::new(1, 0);
+		let cell = FieldCell::new(0, 9);
+		let mut modifiers = SectorCostModifiers::default();
+		let handle = CostModifierHandle::new();
+		let edited = modifiers.add(
+			handle,
+			vec![(sector, cell)],
+			CostModifier::Add(-5),
+			None,
+			&mut cost_fields,
+		);
+		cost_fields.rescale_costfields(&edited, &map_dimensions);
+		assert_eq!((1, 1), get_costs(&cost_fields, sector, cell));
+		// a direct change to the baseline sits beneath the modifier
+		cost_fields.set_field_cell_value(sector, 20, cell, &map_dimensions);
+		assert_eq!((15, 20), get_costs(&cost_fields, sector, cell));
+		let edited = modifiers.remove(handle, &mut cost_fields);
+		cost_fields.rescale_costfields(&edited, &map_dimensions);
+		assert_eq!((20, 20), get_costs(&cost_fields, sector, cell));
+	}
+	#[test]
+	fn timed_modifier_expires() {
+		let mut modifiers = SectorCostModifiers::default();
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let timed = CostModifierHandle::new();
+		let permanent = CostModifierHandle::new();
+		let cells = vec![(SectorID::new(0, 0), FieldCell::new(0, 0))];
+		modifiers.add(
+			timed,
+			cells.clone(),
+			CostModifier::Set(255),
+			Some(1.0),
+			&mut cost_fields,
+		);
+		modifiers.add(
+			permanent,
+			cells,
+			CostModifier::Add(3),
+			None,
+			&mut cost_fields,
+		);
+		assert!(modifiers.tick(0.6).is_empty());
+		assert_eq!(vec![timed], modifiers.tick(0.6));
+		// only reported once
+		assert!(modifiers.tick(0.6).is_empty());
+	}
+	#[test]
+	fn instant_modifier_expires_on_first_tick() {
+		let mut modifiers = SectorCostModifiers::default();
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		let mut cost_fields = SectorCostFields::new(&map_dimensions);
+		let zero = CostModifierHandle::new();
+		let negative = CostModifierHandle::new();
+		let cells = vec![(SectorID::new(0, 0), FieldCell::new(0, 0))];
+		modifiers.add(
+			zero,
+			cells.clone(),
+			CostModifier::Set(255),
+			Some(0.0),
+			&mut cost_fields,
+		);
+		modifiers.add(
+			negative,
+			cells,
+			CostModifier::Set(255),
+			Some(-1.0),
+			&mut cost_fields,
+		);
+		assert_eq!(vec![zero, negative], modifiers.tick(0.0));
+		assert!(modifiers.tick(0.0).is_empty());
+	}
+}
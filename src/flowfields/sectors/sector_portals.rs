@@ -29,6 +29,32 @@ impl SectorPortals {
 		}
 		SectorPortals(map)
 	}
+	/// Add default [Portals] for every sector of `layer` and calculate them from the [CostField]s of that layer, see [SectorCostFields::insert_sector_layer]
+	pub fn add_sector_layer(
+		&mut self,
+		layer: u32,
+		sector_cost_fields: &SectorCostFields,
+		map_dimensions: &MapDimensions,
+	) -> &mut Self {
+		let column_count = map_dimensions.get_length() / map_dimensions.get_sector_resolution();
+		let row_count = map_dimensions.get_depth() / map_dimensions.get_sector_resolution();
+		for m in 0..column_count {
+			for n in 0..row_count {
+				self.0
+					.insert(SectorID::new_layered(m, n, layer), Portals::default());
+			}
+		}
+		for m in 0..column_count {
+			for n in 0..row_count {
+				let sector_id = SectorID::new_layered(m, n, layer);
+				self.get_mut()
+					.get_mut(&sector_id)
+					.unwrap()
+					.recalculate_portals(sector_cost_fields, &sector_id, map_dimensions);
+			}
+		}
+		self
+	}
 	/// Get a reference the map of [Portals]
 	pub fn get(&self) -> &BTreeMap<SectorID, Portals> {
 		&self.0
@@ -56,6 +82,43 @@ impl SectorPortals {
 		}
 		self
 	}
+	/// Recalculate only the portals along the boundaries whose edge cells have changed. `changed` contains the sectors whose scaled [CostField]s changed and the [FieldCell]s which differ (see [SectorCostFields::rescale_costfields]), portals only depend upon the edge cells of a field so a change to the interior alone leaves the portals untouched. The matching side of each neighbour is recalculated too so that both sectors keep the same portals along the boundary.
+	///
+	/// Returns each `(sector_id, side)` that was recalculated
+	pub fn update_portals_on_boundaries(
+		&mut self,
+		changed: &BTreeMap<SectorID, Vec<FieldCell>>,
+		sector_cost_fields: &SectorCostFields,
+		map_dimensions: &MapDimensions,
+	) -> Vec<(SectorID, Ordinal)> {
+		let mut boundaries = Vec::new();
+		for (sector_id, cells) in changed.iter() {
+			let mut sides: Vec<Ordinal> = cells
+				.iter()
+				.filter(|cell| cell.is_on_boundary())
+				.flat_map(|cell| cell.get_boundary_ordinal_from_field_cell())
+				.collect();
+			sides.sort();
+			sides.dedup();
+			let neighbours = map_dimensions.get_ordinal_and_ids_of_neighbouring_sectors(sector_id);
+			for side in sides.iter() {
+				if let Some((_, neighbour)) = neighbours.iter().find(|(ord, _)| ord == side) {
+					for boundary in [(*sector_id, *side), (*neighbour, side.inverse())] {
+						if !boundaries.contains(&boundary) {
+							boundaries.push(boundary);
+						}
+					}
+				}
+			}
+		}
+		for (sector_id, side) in boundaries.iter() {
+			self.get_mut()
+				.get_mut(sector_id)
+				.unwrap()
+				.recalculate_portals_on_side(sector_cost_fields, sector_id, *side, map_dimensions);
+		}
+		boundaries
+	}
 }
 
 // #[rustfmt::skip]
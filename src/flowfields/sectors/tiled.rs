@@ -0,0 +1,548 @@
+//! Reading of [Tiled](https://www.mapeditor.org/) maps, either in the XML based `.tmx` format or the JSON based `.tmj` format, so that the tiles of a map can be translated into [CostField](crate::prelude::CostField) values.
+//!
+//! Each tile of the map represents a [FieldCell](crate::prelude::FieldCell), orthogonal maps are expected and infinite maps aren't supported
+//!
+
+use std::{
+	collections::BTreeMap,
+	io::Read,
+	path::{Path, PathBuf},
+};
+
+use base64::Engine;
+use quick_xml::{
+	events::{BytesStart, Event},
+	Reader,
+};
+
+/// Tiled stores whether a tile has been flipped or rotated in the highest bits of its global ID, masking them off gives the ID of the tile itself
+const GID_MASK: u32 = 0x0FFF_FFFF;
+
+/// Encoding, compression, text content and `<tile>` elements of the `<data>` of a `.tmx` layer being read
+type XmlLayerData = (Option<String>, Option<String>, String, Vec<u32>);
+
+/// Describes how the tiles of a Tiled map are translated into [CostField](crate::prelude::CostField) values.
+///
+/// A tile is given a cost from, in order of precedence, a cost assigned to its global ID with [TiledCosts::with_tile_cost], the value of its cost property (named `cost` by default) or the default cost. When several tile layers are read the most expensive tile of each cell is used
+#[derive(Clone, Debug)]
+pub struct TiledCosts {
+	/// Name of the custom tile property holding the cost of a tile
+	property: String,
+	/// Costs of tiles by their global ID
+	tile_ids: BTreeMap<u32, u8>,
+	/// Cost of tiles without a cost of their own
+	default_cost: u8,
+	/// Cost of cells which don't contain a tile in any layer
+	empty_cost: u8,
+	/// Name of the only tile layer to read, all tile layers are read if [None]
+	layer: Option<String>,
+}
+
+impl Default for TiledCosts {
+	fn default() -> Self {
+		TiledCosts {
+			property: "cost".to_string(),
+			tile_ids: BTreeMap::new(),
+			default_cost: 1,
+			empty_cost: 1,
+			layer: None,
+		}
+	}
+}
+
+impl TiledCosts {
+	/// Read the cost of a tile from the custom property `name` rather than `cost`
+	pub fn with_property(mut self, name: &str) -> Self {
+		self.property = name.to_string();
+		self
+	}
+	/// Give every tile with the global ID `gid` a cost, the global ID is the `firstgid` of the tileset plus the ID of the tile within the tileset
+	pub fn with_tile_cost(mut self, gid: u32, cost: u8) -> Self {
+		self.tile_ids.insert(gid, cost);
+		self
+	}
+	/// Set the cost of tiles without a cost of their own
+	pub fn with_default_cost(mut self, cost: u8) -> Self {
+		self.default_cost = cost;
+		self
+	}
+	/// Set the cost of cells which don't contain a tile
+	pub fn with_empty_cost(mut self, cost: u8) -> Self {
+		self.empty_cost = cost;
+		self
+	}
+	/// Only read the tile layer called `name`
+	pub fn with_layer(mut self, name: &str) -> Self {
+		self.layer = Some(name.to_string());
+		self
+	}
+	/// Get the name of the property holding the cost of a tile
+	pub fn get_property(&self) -> &str {
+		&self.property
+	}
+	/// Get the cost of tiles without a cost of their own
+	pub fn get_default_cost(&self) -> u8 {
+		self.default_cost
+	}
+	/// Get the cost of cells which don't contain a tile
+	pub fn get_empty_cost(&self) -> u8 {
+		self.empty_cost
+	}
+	/// Get the name of the only tile layer to read
+	pub fn get_layer(&self) -> Option<&str> {
+		self.layer.as_deref()
+	}
+}
+
+/// A tile layer of a Tiled map
+#[derive(Clone, Debug)]
+struct TiledLayer {
+	/// Name of the layer
+	name: String,
+	/// Global tile IDs of the layer in rows from the top left of the map, `0` means there isn't a tile
+	data: Vec<u32>,
+}
+
+/// The parts of a Tiled map needed to find the cost of each tile
+#[derive(Clone, Debug, Default)]
+pub struct TiledMap {
+	/// Number of tile columns
+	width: usize,
+	/// Number of tile rows
+	height: usize,
+	/// Tile layers in the order they are drawn
+	layers: Vec<TiledLayer>,
+	/// Custom properties of tiles by their global ID
+	properties: BTreeMap<u32, BTreeMap<String, String>>,
+}
+
+impl TiledMap {
+	/// Read a Tiled map, files ending in `.tmx` are read as XML and anything else as JSON. External tilesets are read relative to the map
+	pub fn from_file(path: &str) -> Self {
+		let contents = std::fs::read_to_string(path).expect("Failed opening Tiled map");
+		let directory = Path::new(path)
+			.parent()
+			.map(Path::to_path_buf)
+			.unwrap_or_default();
+		let mut map = TiledMap::default();
+		if path.ends_with(".tmx") {
+			map.read_xml(&contents, &directory, None);
+		} else {
+			map.read_json(&contents, &directory);
+		}
+		for layer in map.layers.iter() {
+			if layer.data.len() != map.width * map.height {
+				panic!(
+					"Tiled layer {} has {} tiles, expected {}",
+					layer.name,
+					layer.data.len(),
+					map.width * map.height
+				);
+			}
+		}
+		map
+	}
+	/// Get the number of tile columns
+	pub fn get_width(&self) -> usize {
+		self.width
+	}
+	/// Get the number of tile rows
+	pub fn get_height(&self) -> usize {
+		self.height
+	}
+	/// Get the names of the tile layers
+	pub fn get_layer_names(&self) -> Vec<&str> {
+		self.layers.iter().map(|l| l.name.as_str()).collect()
+	}
+	/// Get the cost of the tile at `(column, row)` from the top left of the map
+	pub fn get_cost(&self, column: usize, row: usize, costs: &TiledCosts) -> u8 {
+		let mut cost: Option<u8> = None;
+		for layer in self.layers.iter() {
+			if costs.layer.as_ref().is_some_and(|name| *name != layer.name) {
+				continue;
+			}
+			let gid = layer.data[row * self.width + column] & GID_MASK;
+			if gid == 0 {
+				continue;
+			}
+			let tile_cost = costs
+				.tile_ids
+				.get(&gid)
+				.copied()
+				.or_else(|| {
+					let value = self.properties.get(&gid)?.get(&costs.property)?;
+					let value: f32 = value.parse().unwrap_or_else(|_| {
+						panic!("Tile {} has a non-numeric cost property {}", gid, value)
+					});
+					Some(value.clamp(1.0, 255.0) as u8)
+				})
+				.unwrap_or(costs.default_cost);
+			cost = Some(cost.map_or(tile_cost, |c| c.max(tile_cost)));
+		}
+		cost.unwrap_or(costs.empty_cost)
+	}
+	/// Read a `.tmx` map or, when `external_firstgid` is provided, an external `.tsx` tileset
+	fn read_xml(&mut self, xml: &str, directory: &Path, external_firstgid: Option<u32>) {
+		let mut reader = Reader::from_str(xml);
+		// global ID of the first tile in the tileset being read
+		let mut firstgid = external_firstgid;
+		// global ID of the tile whose properties are being read
+		let mut tile: Option<u32> = None;
+		let mut layer_name = String::new();
+		let mut data: Option<XmlLayerData> = None;
+		loop {
+			let (element, is_empty) = match reader.read_event().expect("Failed reading Tiled XML") {
+				Event::Start(e) => (e, false),
+				Event::Empty(e) => (e, true),
+				Event::Text(t) => {
+					if let Some((_, _, text, _)) = data.as_mut() {
+						text.push_str(&t.decode().expect("Failed decoding Tiled XML"));
+					}
+					continue;
+				}
+				Event::End(e) => {
+					match e.local_name().as_ref() {
+						b"data" => {
+							if let Some((encoding, compression, text, tiles)) = data.take() {
+								let gids = match encoding {
+									Some(encoding) => {
+										decode_data(&text, &encoding, compression.as_deref())
+									}
+									None => tiles,
+								};
+								self.layers.push(TiledLayer {
+									name: layer_name.clone(),
+									data: gids,
+								});
+							}
+						}
+						b"tile" => tile = None,
+						b"tileset" => firstgid = external_firstgid,
+						_ => {}
+					}
+					continue;
+				}
+				Event::Eof => break,
+				_ => continue,
+			};
+			match element.local_name().as_ref() {
+				b"map" => {
+					if get_attribute(&element, "infinite").as_deref() == Some("1") {
+						panic!("Infinite Tiled maps are not supported");
+					}
+					self.width = parse_attribute(&element, "width");
+					self.height = parse_attribute(&element, "height");
+				}
+				b"tileset" => match get_attribute(&element, "source") {
+					Some(source) => {
+						let gid = parse_attribute(&element, "firstgid");
+						self.read_tileset(&directory.join(source), gid);
+					}
+					None => {
+						if let Some(gid) = get_attribute(&element, "firstgid") {
+							firstgid = Some(gid.parse().expect("Invalid Tiled firstgid"));
+						}
+					}
+				},
+				b"tile" => {
+					if let Some((_, _, _, tiles)) = data.as_mut() {
+						tiles.push(
+							get_attribute(&element, "gid")
+								.map_or(0, |gid| gid.parse().expect("Invalid Tiled tile gid")),
+						);
+					} else if let (Some(gid), false) = (firstgid, is_empty) {
+						tile = Some(gid + parse_attribute::<u32>(&element, "id"));
+					}
+				}
+				b"property" => {
+					if let (Some(gid), Some(name), Some(value)) = (
+						tile,
+						get_attribute(&element, "name"),
+						get_attribute(&element, "value"),
+					) {
+						self.properties.entry(gid).or_default().insert(name, value);
+					}
+				}
+				b"layer" => {
+					layer_name = get_attribute(&element, "name").unwrap_or_default();
+				}
+				b"data" => {
+					if get_attribute(&element, "encoding").is_none() && is_empty {
+						continue;
+					}
+					data = Some((
+						get_attribute(&element, "encoding"),
+						get_attribute(&element, "compression"),
+						String::new(),
+						Vec::new(),
+					));
+				}
+				_ => {}
+			}
+		}
+	}
+	/// Read a `.tmj` map
+	fn read_json(&mut self, json: &str, directory: &Path) {
+		let map: JsonMap = serde_json::from_str(json).expect("Failed deserializing Tiled map");
+		if map.infinite {
+			panic!("Infinite Tiled maps are not supported");
+		}
+		self.width = map.width;
+		self.height = map.height;
+		for tileset in map.tilesets.iter() {
+			match &tileset.source {
+				Some(source) => self.read_tileset(&directory.join(source), tileset.firstgid),
+				None => self.add_json_tiles(tileset.firstgid, &tileset.tiles),
+			}
+		}
+		self.add_json_layers(&map.layers);
+	}
+	/// Read the tile layers of a `.tmj` map, including those nested within group layers
+	fn add_json_layers(&mut self, layers: &[JsonLayer]) {
+		for layer in layers.iter() {
+			match layer.kind.as_str() {
+				"tilelayer" => {
+					let data = match &layer.data {
+						Some(serde_json::Value::Array(values)) => values
+							.iter()
+							.map(|v| v.as_u64().expect("Invalid Tiled tile gid") as u32)
+							.collect(),
+						Some(serde_json::Value::String(text)) => decode_data(
+							text,
+							layer.encoding.as_deref().unwrap_or("base64"),
+							layer.compression.as_deref(),
+						),
+						_ => panic!("Tiled layer {} has no tile data", layer.name),
+					};
+					self.layers.push(TiledLayer {
+						name: layer.name.clone(),
+						data,
+					});
+				}
+				"group" => self.add_json_layers(&layer.layers),
+				_ => {}
+			}
+		}
+	}
+	/// Record the properties of the tiles of a tileset
+	fn add_json_tiles(&mut self, firstgid: u32, tiles: &[JsonTile]) {
+		for tile in tiles.iter() {
+			for property in tile.properties.iter() {
+				let value = match &property.value {
+					serde_json::Value::String(s) => s.clone(),
+					other => other.to_string(),
+				};
+				self.properties
+					.entry(firstgid + tile.id)
+					.or_default()
+					.insert(property.name.clone(), value);
+			}
+		}
+	}
+	/// Read an external tileset, `.tsx` files are read as XML and anything else as JSON
+	fn read_tileset(&mut self, path: &PathBuf, firstgid: u32) {
+		let contents = std::fs::read_to_string(path).expect("Failed opening Tiled tileset");
+		if path.extension().is_some_and(|e| e == "tsx") {
+			self.read_xml(&contents, Path::new(""), Some(firstgid));
+		} else {
+			let tileset: JsonTileset =
+				serde_json::from_str(&contents).expect("Failed deserializing Tiled tileset");
+			self.add_json_tiles(firstgid, &tileset.tiles);
+		}
+	}
+}
+
+/// Get the value of an XML attribute
+fn get_attribute(element: &BytesStart, name: &str) -> Option<String> {
+	element
+		.try_get_attribute(name)
+		.expect("Invalid Tiled XML attribute")
+		.map(|a| {
+			a.normalized_value(quick_xml::XmlVersion::Implicit1_0)
+				.expect("Invalid Tiled XML attribute")
+				.into_owned()
+		})
+}
+
+/// Get the value of a required XML attribute
+fn parse_attribute<T: std::str::FromStr>(element: &BytesStart, name: &str) -> T {
+	get_attribute(element, name)
+		.and_then(|v| v.parse().ok())
+		.unwrap_or_else(|| panic!("Tiled XML is missing a valid {} attribute", name))
+}
+
+/// Decode the global tile IDs of a layer stored as `csv` or as little-endian `base64` with optional `zlib` or `gzip` compression
+fn decode_data(text: &str, encoding: &str, compression: Option<&str>) -> Vec<u32> {
+	match encoding {
+		"csv" => text
+			.split(',')
+			.map(str::trim)
+			.filter(|v| !v.is_empty())
+			.map(|v| v.parse().expect("Invalid Tiled tile gid"))
+			.collect(),
+		"base64" => {
+			let raw = base64::engine::general_purpose::STANDARD
+				.decode(text.trim())
+				.expect("Invalid base64 Tiled layer data");
+			let mut bytes = Vec::new();
+			match compression {
+				None | Some("") => bytes = raw,
+				Some("zlib") => {
+					flate2::read::ZlibDecoder::new(raw.as_slice())
+						.read_to_end(&mut bytes)
+						.expect("Failed decompressing Tiled layer data");
+				}
+				Some("gzip") => {
+					flate2::read::GzDecoder::new(raw.as_slice())
+						.read_to_end(&mut bytes)
+						.expect("Failed decompressing Tiled layer data");
+				}
+				Some(other) => panic!("Unsupported Tiled layer compression {}", other),
+			}
+			bytes
+				.chunks_exact(4)
+				.map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+				.collect()
+		}
+		other => panic!("Unsupported Tiled layer encoding {}", other),
+	}
+}
+
+/// A `.tmj` map
+#[derive(serde::Deserialize)]
+struct JsonMap {
+	/// Number of tile columns
+	width: usize,
+	/// Number of tile rows
+	height: usize,
+	/// Whether the map is made of chunks rather than a fixed size
+	#[serde(default)]
+	infinite: bool,
+	/// Layers of the map
+	layers: Vec<JsonLayer>,
+	/// Tilesets used by the map
+	#[serde(default)]
+	tilesets: Vec<JsonTilesetRef>,
+}
+
+/// A layer of a `.tmj` map
+#[derive(serde::Deserialize)]
+struct JsonLayer {
+	/// Kind of layer, such as `tilelayer` or `group`
+	#[serde(rename = "type")]
+	kind: String,
+	/// Name of the layer
+	#[serde(default)]
+	name: String,
+	/// Global tile IDs as an array, or as a string when encoded
+	data: Option<serde_json::Value>,
+	/// Encoding of the tile data
+	encoding: Option<String>,
+	/// Compression of the tile data
+	compression: Option<String>,
+	/// Layers within a group layer
+	#[serde(default)]
+	layers: Vec<JsonLayer>,
+}
+
+/// A tileset used by a `.tmj` map, either embedded or referring to an external file
+#[derive(serde::Deserialize)]
+struct JsonTilesetRef {
+	/// Global ID of the first tile in the tileset
+	firstgid: u32,
+	/// Path of an external tileset
+	source: Option<String>,
+	/// Tiles of an embedded tileset which have properties
+	#[serde(default)]
+	tiles: Vec<JsonTile>,
+}
+
+/// An external `.tsj` tileset
+#[derive(serde::Deserialize)]
+struct JsonTileset {
+	/// Tiles which have properties
+	#[serde(default)]
+	tiles: Vec<JsonTile>,
+}
+
+/// A tile of a tileset
+#[derive(serde::Deserialize)]
+struct JsonTile {
+	/// ID of the tile within the tileset
+	id: u32,
+	/// Custom properties of the tile
+	#[serde(default)]
+	properties: Vec<JsonProperty>,
+}
+
+/// A custom property
+#[derive(serde::Deserialize)]
+struct JsonProperty {
+	/// Name of the property
+	name: String,
+	/// Value of the property
+	value: serde_json::Value,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Path of a fixture map
+	fn fixture(name: &str) -> String {
+		env!("CARGO_MANIFEST_DIR").to_string() + "/assets/tiled/" + name
+	}
+	#[test]
+	fn tmx_costs() {
+		let map = TiledMap::from_file(&fixture("map.tmx"));
+		let costs = TiledCosts::default();
+		assert_eq!((20, 20), (map.get_width(), map.get_height()));
+		assert_eq!(vec!["Ground", "Objects"], map.get_layer_names());
+		// grass
+		assert_eq!(1, map.get_cost(0, 0, &costs));
+		// wall
+		assert_eq!(255, map.get_cost(10, 5, &costs));
+		// mud
+		assert_eq!(50, map.get_cost(3, 3, &costs));
+		// rock from the external tileset, one of which is flipped
+		assert_eq!(255, map.get_cost(15, 15, &costs));
+		assert_eq!(255, map.get_cost(16, 15, &costs));
+	}
+	#[test]
+	fn tmj_matches_tmx() {
+		let tmx = TiledMap::from_file(&fixture("map.tmx"));
+		let tmj = TiledMap::from_file(&fixture("map.tmj"));
+		let costs = TiledCosts::default();
+		assert_eq!(tmx.get_layer_names(), tmj.get_layer_names());
+		for row in 0..20 {
+			for column in 0..20 {
+				assert_eq!(
+					tmx.get_cost(column, row, &costs),
+					tmj.get_cost(column, row, &costs)
+				);
+			}
+		}
+	}
+	#[test]
+	fn tile_ids_and_layers() {
+		let map = TiledMap::from_file(&fixture("map.tmj"));
+		// mud by global ID, rocks are on another layer
+		let costs = TiledCosts::default()
+			.with_tile_cost(3, 200)
+			.with_layer("Ground");
+		assert_eq!(200, map.get_cost(3, 3, &costs));
+		assert_eq!(1, map.get_cost(15, 15, &costs));
+		// only the rocks
+		let costs = TiledCosts::default()
+			.with_layer("Objects")
+			.with_empty_cost(7);
+		assert_eq!(7, map.get_cost(10, 5, &costs));
+		assert_eq!(255, map.get_cost(15, 15, &costs));
+	}
+	#[test]
+	fn decode_base64() {
+		// gids 1 and 2 little-endian
+		let data = decode_data("AQAAAAIAAAA=", "base64", None);
+		assert_eq!(vec![1, 2], data);
+	}
+}
@@ -278,27 +278,31 @@ impl Ordinal {
 		let sector_column_limit = map_length / sector_resolution - 1;
 		let sector_row_limit = map_depth / sector_resolution - 1;
 		if sector_id.get_row() > 0 {
-			neighbours.push(SectorID::new(
+			neighbours.push(SectorID::new_layered(
 				sector_id.get_column(),
 				sector_id.get_row() - 1,
+				sector_id.get_layer(),
 			)); // northern sector coords
 		}
 		if sector_id.get_column() < sector_column_limit {
-			neighbours.push(SectorID::new(
+			neighbours.push(SectorID::new_layered(
 				sector_id.get_column() + 1,
 				sector_id.get_row(),
+				sector_id.get_layer(),
 			)); // eastern sector coords
 		}
 		if sector_id.get_row() < sector_row_limit {
-			neighbours.push(SectorID::new(
+			neighbours.push(SectorID::new_layered(
 				sector_id.get_column(),
 				sector_id.get_row() + 1,
+				sector_id.get_layer(),
 			)); // southern sector coords
 		}
 		if sector_id.get_column() > 0 {
-			neighbours.push(SectorID::new(
+			neighbours.push(SectorID::new_layered(
 				sector_id.get_column() - 1,
 				sector_id.get_row(),
+				sector_id.get_layer(),
 			)); // western sector coords
 		}
 		neighbours
@@ -383,25 +387,41 @@ impl Ordinal {
 		if sector_id.get_row() > 0 {
 			neighbours.push((
 				Ordinal::North,
-				SectorID::new(sector_id.get_column(), sector_id.get_row() - 1),
+				SectorID::new_layered(
+					sector_id.get_column(),
+					sector_id.get_row() - 1,
+					sector_id.get_layer(),
+				),
 			)); // northern sector coords
 		}
 		if sector_id.get_column() < sector_x_column_limit {
 			neighbours.push((
 				Ordinal::East,
-				SectorID::new(sector_id.get_column() + 1, sector_id.get_row()),
+				SectorID::new_layered(
+					sector_id.get_column() + 1,
+					sector_id.get_row(),
+					sector_id.get_layer(),
+				),
 			)); // eastern sector coords
 		}
 		if sector_id.get_row() < sector_z_row_limit {
 			neighbours.push((
 				Ordinal::South,
-				SectorID::new(sector_id.get_column(), sector_id.get_row() + 1),
+				SectorID::new_layered(
+					sector_id.get_column(),
+					sector_id.get_row() + 1,
+					sector_id.get_layer(),
+				),
 			)); // southern sector coords
 		}
 		if sector_id.get_column() > 0 {
 			neighbours.push((
 				Ordinal::West,
-				SectorID::new(sector_id.get_column() - 1, sector_id.get_row()),
+				SectorID::new_layered(
+					sector_id.get_column() - 1,
+					sector_id.get_row(),
+					sector_id.get_layer(),
+				),
 			)); // western sector coords
 		}
 		neighbours
@@ -441,8 +461,12 @@ impl Ordinal {
 			),
 		}
 	}
-	/// For two sectors next to each other it can be useful to find the [Ordinal] from the `source` to the `target`. If they are not adjacent None is returned
+	/// For two sectors next to each other it can be useful to find the [Ordinal] from the `source` to the `target`. If they are not adjacent or sit on different layers None is returned
 	pub fn sector_to_sector_direction(target: SectorID, source: SectorID) -> Option<Self> {
+		// sectors on different layers are only joined by a [LayerConnection] and have no direction between them
+		if target.get_layer() != source.get_layer() {
+			return None;
+		}
 		let i32_target = (target.get_column() as i32, target.get_row() as i32);
 		let i32_source = (source.get_column() as i32, source.get_row() as i32);
 
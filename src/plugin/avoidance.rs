@@ -0,0 +1,328 @@
+//! An optional local avoidance layer which adjusts the desired velocity of [FlowFieldAgent]s so that crowds following the same [FlowField] spread out rather than stacking up at portals and corners.
+//!
+//! Each agent with a [LocalAvoidance] component blends its [FlowField] velocity with the classic flocking behaviours:
+//!
+//! * separation - push away from neighbours that are overlapping
+//! * alignment - match the heading of neighbours
+//! * cohesion - drift towards the centre of neighbours
+//!
+//! Neighbours are found with a spatial hash built over the [FieldCell] grid of the [MapDimensions], no physics engine is required. Agents on different sector layers are never neighbours.
+//!
+
+use crate::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
+
+/// Weightings of the local avoidance behaviours
+#[derive(Resource, Debug, Clone, Copy, Reflect)]
+pub struct AvoidanceSettings {
+	/// Multiplier of an agents [LocalAvoidance] radius used to determine how far it looks for neighbours
+	pub neighbour_range: f32,
+	/// Strength of the push away from overlapping neighbours
+	pub separation_weight: f32,
+	/// Strength of matching the velocity of neighbours
+	pub alignment_weight: f32,
+	/// Strength of moving towards the centre of neighbours
+	pub cohesion_weight: f32,
+}
+
+impl Default for AvoidanceSettings {
+	fn default() -> Self {
+		AvoidanceSettings {
+			neighbour_range: 3.0,
+			separation_weight: 1.5,
+			alignment_weight: 0.3,
+			cohesion_weight: 0.1,
+		}
+	}
+}
+
+/// Opts a [FlowFieldAgent] into local avoidance
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct LocalAvoidance {
+	/// The size of the agent, two agents closer than the sum of their radii will be pushed apart
+	radius: f32,
+}
+
+impl LocalAvoidance {
+	/// Create a new instance of [LocalAvoidance] for an agent of `radius`
+	pub fn new(radius: f32) -> Self {
+		LocalAvoidance { radius }
+	}
+	/// Get the radius of the agent
+	pub fn get_radius(&self) -> f32 {
+		self.radius
+	}
+}
+
+/// Buckets items by the [FieldCell] they sit in across the whole map so that nearby items can be found without comparing against every other item. Items on different sector layers are never nearby
+#[derive(Default)]
+pub struct SpatialHash {
+	/// Items keyed by the `(layer, column, row)` of the [FieldCell] across the whole map
+	buckets: HashMap<(u32, usize, usize), Vec<usize>>,
+}
+
+impl SpatialHash {
+	/// Convert a sector and field cell into a `(layer, column, row)` across the whole map
+	fn get_global_cell(sector: SectorID, field_cell: FieldCell) -> (u32, usize, usize) {
+		(
+			sector.get_layer(),
+			sector.get_column() as usize * FIELD_RESOLUTION + field_cell.get_column(),
+			sector.get_row() as usize * FIELD_RESOLUTION + field_cell.get_row(),
+		)
+	}
+	/// Record an `item` as sitting within `sector` and `field_cell`
+	pub fn insert(&mut self, item: usize, sector: SectorID, field_cell: FieldCell) {
+		let key = SpatialHash::get_global_cell(sector, field_cell);
+		self.buckets.entry(key).or_default().push(item);
+	}
+	/// Find all items within `ring` [FieldCell]s of `sector` and `field_cell` on the same layer
+	pub fn get_nearby(&self, sector: SectorID, field_cell: FieldCell, ring: usize) -> Vec<usize> {
+		let (layer, column, row) = SpatialHash::get_global_cell(sector, field_cell);
+		let mut nearby = Vec::new();
+		for c in column.saturating_sub(ring)..=column + ring {
+			for r in row.saturating_sub(ring)..=row + ring {
+				if let Some(items) = self.buckets.get(&(layer, c, r)) {
+					nearby.extend_from_slice(items);
+				}
+			}
+		}
+		nearby
+	}
+}
+
+/// Adds local avoidance to [FlowFieldAgent]s with a [LocalAvoidance] component, requires the [FlowFieldSteeringPlugin] to also be added
+#[derive(Default)]
+pub struct FlowFieldAvoidancePlugin {
+	/// Weightings of the avoidance behaviours
+	pub settings: AvoidanceSettings,
+}
+
+impl Plugin for FlowFieldAvoidancePlugin {
+	#[cfg(not(tarpaulin_include))]
+	fn build(&self, app: &mut App) {
+		app.register_type::<LocalAvoidance>()
+			.insert_resource(self.settings)
+			.add_systems(
+				PreUpdate,
+				apply_local_avoidance
+					.after(steer_agents)
+					.in_set(SteeringSet),
+			);
+	}
+}
+
+/// Snapshot of an agent used while calculating avoidance
+struct AgentSnapshot {
+	/// World position
+	position: Vec3,
+	/// Size of the agent
+	radius: f32,
+	/// Velocity from following the [FlowField]
+	desired_velocity: Vec3,
+	/// Location in the grid if it is within the world
+	location: Option<(SectorID, FieldCell)>,
+}
+
+/// Blend the desired velocity of each moving agent with separation, alignment and cohesion from its neighbours. An adjusted velocity that would steer an agent into an impassable [FieldCell] is discarded in favour of the original [FlowField] velocity
+#[cfg(not(tarpaulin_include))]
+pub fn apply_local_avoidance(
+	mut agents: Query<(&Transform, &LocalAvoidance, &mut FlowFieldAgent)>,
+	map_q: Query<(&MapDimensions, &SectorCostFields)>,
+	plane: Res<SteeringPlane>,
+	settings: Res<AvoidanceSettings>,
+) {
+	let Ok((map_dimensions, sector_cost_fields)) = map_q.get_single() else {
+		return;
+	};
+	// snapshot every agent so that the adjustments are based on the velocities from this frame
+	let mut snapshots = Vec::new();
+	let mut hash = SpatialHash::default();
+	for (i, (tform, avoidance, agent)) in agents.iter().enumerate() {
+		let location = plane.get_sector_and_field_cell_on_layer(
+			map_dimensions,
+			tform.translation,
+			agent.get_layer(),
+		);
+		if let Some((sector, field_cell)) = location {
+			hash.insert(i, sector, field_cell);
+		}
+		snapshots.push(AgentSnapshot {
+			position: tform.translation,
+			radius: avoidance.get_radius(),
+			desired_velocity: agent.get_desired_velocity(),
+			location,
+		});
+	}
+	let cell_size = map_dimensions.get_field_cell_unit_size().max(f32::EPSILON);
+	for (i, (_, _, mut agent)) in agents.iter_mut().enumerate() {
+		let me = &snapshots[i];
+		let Some((sector, field_cell)) = me.location else {
+			continue;
+		};
+		// agents which have arrived or are waiting on a route stay put
+		if agent.get_target().is_none() || me.desired_velocity == Vec3::ZERO {
+			continue;
+		}
+		let range = me.radius * settings.neighbour_range;
+		let ring = (range / cell_size).ceil().max(1.0) as usize;
+		let mut separation = Vec3::ZERO;
+		let mut velocity_sum = Vec3::ZERO;
+		let mut position_sum = Vec3::ZERO;
+		let mut count = 0;
+		for j in hash.get_nearby(sector, field_cell, ring) {
+			if i == j {
+				continue;
+			}
+			let other = &snapshots[j];
+			let offset = plane.flatten(me.position - other.position);
+			let distance = offset.length();
+			if distance > range {
+				continue;
+			}
+			let min_distance = me.radius + other.radius;
+			if distance < min_distance {
+				// agents on top of each other are pushed apart along x based on their order
+				let away = if distance > f32::EPSILON {
+					offset / distance
+				} else if i < j {
+					Vec3::NEG_X
+				} else {
+					Vec3::X
+				};
+				separation += away * (min_distance - distance) / min_distance;
+			}
+			velocity_sum += other.desired_velocity;
+			position_sum += other.position;
+			count += 1;
+		}
+		if count == 0 {
+			continue;
+		}
+		let max_speed = agent.get_max_speed();
+		let alignment = velocity_sum / count as f32 - me.desired_velocity;
+		let cohesion = plane
+			.flatten(position_sum / count as f32 - me.position)
+			.normalize_or_zero();
+		let velocity = (me.desired_velocity
+			+ separation * settings.separation_weight * max_speed
+			+ alignment * settings.alignment_weight
+			+ cohesion * settings.cohesion_weight * max_speed)
+			.clamp_length_max(max_speed);
+		// don't let the crowd push an agent into a wall
+		let probe = me.position + velocity.normalize_or_zero() * cell_size;
+		if let Some((probe_sector, probe_cell)) =
+			plane.get_sector_and_field_cell_on_layer(map_dimensions, probe, agent.get_layer())
+		{
+			if let Some(cost_field) = sector_cost_fields.get_scaled().get(&probe_sector) {
+				if cost_field.get_field_cell_value(probe_cell) == 255 {
+					continue;
+				}
+			}
+		} else {
+			continue;
+		}
+		agent.set_desired_velocity(velocity);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bevy::ecs::system::RunSystemOnce;
+
+	#[test]
+	fn spatial_hash_nearby() {
+		let mut hash = SpatialHash::default();
+		hash.insert(0, SectorID::new(0, 0), FieldCell::new(9, 9));
+		hash.insert(1, SectorID::new(1, 1), FieldCell::new(0, 0));
+		hash.insert(2, SectorID::new(1, 1), FieldCell::new(5, 5));
+		hash.insert(3, SectorID::new(1, 1).with_layer(1), FieldCell::new(0, 0));
+		let mut result = hash.get_nearby(SectorID::new(1, 1), FieldCell::new(0, 0), 1);
+		result.sort();
+		// items across a sector boundary are found
+		assert_eq!(vec![0, 1], result);
+		let mut result = hash.get_nearby(SectorID::new(1, 1), FieldCell::new(0, 0), 5);
+		result.sort();
+		assert_eq!(vec![0, 1, 2], result);
+		// only items of the same layer are found
+		let result = hash.get_nearby(SectorID::new(1, 1).with_layer(1), FieldCell::new(0, 0), 5);
+		assert_eq!(vec![3], result);
+	}
+	/// Position of an agent where `x` and `w` are the world coordinates in the steering plane
+	fn position(plane: SteeringPlane, x: f32, w: f32) -> Vec3 {
+		match plane {
+			#[cfg(feature = "2d")]
+			SteeringPlane::XY => Vec3::new(x, w, 0.0),
+			#[cfg(feature = "3d")]
+			SteeringPlane::XZ => Vec3::new(x, 0.0, w),
+		}
+	}
+	/// Create a [World] with a 30x30 map and two agents heading east which overlap each other
+	fn setup_world() -> (World, Entity, Entity) {
+		let mut world = World::new();
+		let plane = SteeringPlane::default();
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		world.spawn((map_dimensions, sector_cost_fields));
+		world.insert_resource(plane);
+		world.insert_resource(AvoidanceSettings::default());
+		let mut spawn_agent = |x: f32| {
+			let mut agent = FlowFieldAgent::new(2.0, 0.5);
+			agent.set_target(position(plane, 12.0, 0.0));
+			agent.set_desired_velocity(Vec3::X * 2.0);
+			world
+				.spawn((
+					Transform::from_translation(position(plane, x, 0.0)),
+					LocalAvoidance::new(0.5),
+					agent,
+				))
+				.id()
+		};
+		let a = spawn_agent(0.0);
+		let b = spawn_agent(0.4);
+		(world, a, b)
+	}
+	#[test]
+	fn overlapping_agents_separate() {
+		let (mut world, a, b) = setup_world();
+		world.run_system_once(apply_local_avoidance);
+		let a_velocity = world
+			.get::<FlowFieldAgent>(a)
+			.unwrap()
+			.get_desired_velocity();
+		let b_velocity = world
+			.get::<FlowFieldAgent>(b)
+			.unwrap()
+			.get_desired_velocity();
+		// agent behind slows down while the one in front keeps going
+		assert!(a_velocity.x < b_velocity.x);
+		assert!(a_velocity.length() <= 2.0 + f32::EPSILON);
+		assert!(b_velocity.length() <= 2.0 + f32::EPSILON);
+	}
+	#[test]
+	fn stationary_agents_unaffected() {
+		let (mut world, a, _) = setup_world();
+		world.get_mut::<FlowFieldAgent>(a).unwrap().clear_target();
+		world.run_system_once(apply_local_avoidance);
+		let a_velocity = world
+			.get::<FlowFieldAgent>(a)
+			.unwrap()
+			.get_desired_velocity();
+		assert_eq!(Vec3::ZERO, a_velocity);
+	}
+	#[test]
+	fn agents_on_other_layers_ignored() {
+		let (mut world, a, b) = setup_world();
+		// the second agent is on the floor above
+		world.get_mut::<FlowFieldAgent>(b).unwrap().set_layer(1);
+		world.run_system_once(apply_local_avoidance);
+		for agent in [a, b] {
+			let velocity = world
+				.get::<FlowFieldAgent>(agent)
+				.unwrap()
+				.get_desired_velocity();
+			assert_eq!(Vec3::X * 2.0, velocity);
+		}
+	}
+}
@@ -0,0 +1,183 @@
+//! Static [avian2d] colliders in the world are rasterised into impassable [CostField] cells so that the walls of a scene only need to be authored once, as physics geometry.
+//!
+//! Any entity with a [Collider] and a [RigidBody::Static] that isn't a [Sensor] is scanned when it is spawned and again whenever its collider, body or `GlobalTransform` changes or it becomes a [Sensor]. The cells a collider covers are stamped onto sector layer `0` in the same way as a [FlowFieldObstacle], see [CostStamps]
+//!
+
+use crate::prelude::*;
+use avian2d::prelude::{AnyCollider, Collider, Position, RigidBody, Sensor};
+use bevy::prelude::*;
+
+/// Find the area of the map covered by `collider` at `transform`. A [FieldCell] is covered when its centre lies inside the shape of the collider, scaled and rotated by the `GlobalTransform`
+pub fn get_collider_region(
+	collider: &Collider,
+	transform: &GlobalTransform,
+	map_dimensions: &MapDimensions,
+) -> CostRegion {
+	let transform = transform.compute_transform();
+	let mut collider = collider.clone();
+	collider.set_scale(transform.scale.truncate(), 10);
+	let translation = transform.translation.truncate();
+	let aabb = collider.aabb(translation, transform.rotation);
+	// rows of the grid grow downwards so the top of the aabb is the first row
+	let min = map_dimensions.get_grid_position_from_xy(Vec2::new(aabb.min.x, aabb.max.y));
+	let max = map_dimensions.get_grid_position_from_xy(Vec2::new(aabb.max.x, aabb.min.y));
+	let columns = map_dimensions.get_total_field_cell_columns();
+	let rows = map_dimensions.get_total_field_cell_rows();
+	let cell_size = map_dimensions.get_sector_resolution() as f32 / FIELD_RESOLUTION as f32;
+	let mut cells = Vec::new();
+	for column in (min.x.max(0.0) as usize)..(max.x.ceil().max(0.0) as usize).min(columns) {
+		for row in (min.y.max(0.0) as usize)..(max.y.ceil().max(0.0) as usize).min(rows) {
+			let centre = Vec2::new(
+				(column as f32 + 0.5) * cell_size - map_dimensions.get_length() as f32 / 2.0,
+				map_dimensions.get_depth() as f32 / 2.0 - (row as f32 + 0.5) * cell_size,
+			);
+			if collider.contains_point(Position(translation), transform.rotation, centre) {
+				cells.push((column, row));
+			}
+		}
+	}
+	CostRegion::Cells(cells)
+}
+
+/// Filter for colliders which have been added, moved, had their shape or body changed or been made a [Sensor]
+type ChangedColliderFilter = (
+	With<Collider>,
+	Or<(
+		Changed<Collider>,
+		Changed<RigidBody>,
+		Changed<GlobalTransform>,
+		Added<Sensor>,
+	)>,
+);
+
+/// Rasterise new or moved static [Collider]s into impassable cells of the [CostField]s and clear the cells of any that have been despawned, lost their body or are no longer static
+#[cfg(not(tarpaulin_include))]
+pub fn stamp_static_colliders(
+	colliders: Query<(&Collider, &RigidBody, &GlobalTransform), Without<Sensor>>,
+	changed: Query<Entity, ChangedColliderFilter>,
+	mut removed: RemovedComponents<Collider>,
+	mut removed_bodies: RemovedComponents<RigidBody>,
+	map_q: Query<&MapDimensions>,
+	mut stamps: Local<CostStamps>,
+	mut events: CostStampEvents,
+) {
+	for entity in removed.read().chain(removed_bodies.read()) {
+		stamps.clear(entity, &mut events);
+	}
+	let Ok(map_dimensions) = map_q.get_single() else {
+		return;
+	};
+	for entity in changed.iter() {
+		match colliders.get(entity) {
+			Ok((collider, RigidBody::Static, transform)) => {
+				let region = get_collider_region(collider, transform, map_dimensions);
+				stamps.stamp(entity, region, 255, 0, &mut events);
+			}
+			_ => stamps.clear(entity, &mut events),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::plugin::obstacle_layer::tests::{get_cost, setup_app};
+
+	#[test]
+	fn scaled_rectangle_region() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let collider = Collider::rectangle(1.0, 1.0);
+		let transform = GlobalTransform::from(
+			Transform::from_xyz(-13.5, 14.5, 0.0).with_scale(Vec3::new(3.0, 1.0, 1.0)),
+		);
+		let CostRegion::Cells(cells) = get_collider_region(&collider, &transform, &map_dimensions)
+		else {
+			panic!("Collider region should be a list of cells");
+		};
+		assert_eq!(vec![(0, 0), (1, 0), (2, 0)], cells);
+	}
+	#[test]
+	fn static_colliders_stamped_and_cleared() {
+		let mut app = setup_app();
+		let wall = app
+			.world_mut()
+			.spawn((
+				RigidBody::Static,
+				Collider::circle(1.0),
+				TransformBundle::from_transform(Transform::from_xyz(0.0, 0.0, 0.0)),
+			))
+			.id();
+		app.world_mut().spawn((
+			RigidBody::Dynamic,
+			Collider::circle(1.0),
+			TransformBundle::from_transform(Transform::from_xyz(-10.0, 10.0, 0.0)),
+		));
+		app.world_mut().spawn((
+			RigidBody::Static,
+			Collider::circle(1.0),
+			Sensor,
+			TransformBundle::from_transform(Transform::from_xyz(10.0, -10.0, 0.0)),
+		));
+		app.update();
+		app.update();
+		let sector = SectorID::new(1, 1);
+		for cell in [(4, 4), (4, 5), (5, 4), (5, 5)] {
+			assert_eq!(
+				255,
+				get_cost(&mut app, sector, FieldCell::new(cell.0, cell.1))
+			);
+		}
+		assert_eq!(
+			1,
+			get_cost(&mut app, SectorID::new(0, 0), FieldCell::new(5, 5))
+		);
+		assert_eq!(
+			1,
+			get_cost(&mut app, SectorID::new(2, 2), FieldCell::new(5, 5))
+		);
+		app.world_mut().despawn(wall);
+		app.update();
+		assert_eq!(1, get_cost(&mut app, sector, FieldCell::new(4, 4)));
+	}
+	#[test]
+	fn sensors_and_bodiless_colliders_cleared() {
+		let mut app = setup_app();
+		let sensor = app
+			.world_mut()
+			.spawn((
+				RigidBody::Static,
+				Collider::circle(1.0),
+				TransformBundle::from_transform(Transform::from_xyz(0.0, 0.0, 0.0)),
+			))
+			.id();
+		let bodiless = app
+			.world_mut()
+			.spawn((
+				RigidBody::Static,
+				Collider::circle(1.0),
+				TransformBundle::from_transform(Transform::from_xyz(-10.0, 10.0, 0.0)),
+			))
+			.id();
+		app.update();
+		app.update();
+		assert_eq!(
+			255,
+			get_cost(&mut app, SectorID::new(1, 1), FieldCell::new(4, 4))
+		);
+		assert_eq!(
+			255,
+			get_cost(&mut app, SectorID::new(0, 0), FieldCell::new(5, 5))
+		);
+		app.world_mut().entity_mut(sensor).insert(Sensor);
+		app.world_mut().entity_mut(bodiless).remove::<RigidBody>();
+		app.update();
+		assert_eq!(
+			1,
+			get_cost(&mut app, SectorID::new(1, 1), FieldCell::new(4, 4))
+		);
+		assert_eq!(
+			1,
+			get_cost(&mut app, SectorID::new(0, 0), FieldCell::new(5, 5))
+		);
+	}
+}
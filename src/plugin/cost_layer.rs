@@ -3,6 +3,8 @@
 //! by the cost change
 //!
 
+use std::collections::{btree_map::Entry, BTreeMap, BTreeSet};
+
 use crate::prelude::*;
 use bevy::prelude::*;
 
@@ -41,157 +43,1480 @@ impl EventUpdateCostfieldsCell {
 	}
 }
 
-/// Read [EventUpdateCostfieldsCell] and update the values within [CostField]
+/// An area of the map measured in [FieldCell]s across the whole map, i.e `(column, row)` where `(0, 0)` is the top left [FieldCell] of the top left sector. Used to update many [CostField] values in a single batch
+#[derive(Clone)]
+pub enum CostRegion {
+	/// All cells from `min` to `max` inclusive, any part of the rectangle outside of the map is ignored
+	Rectangle {
+		/// Top left `(column, row)`
+		min: (usize, usize),
+		/// Bottom right `(column, row)`
+		max: (usize, usize),
+	},
+	/// All cells whose centre lies within `radius` cells of `centre`. The centre of cell `(column, row)` is `(column + 0.5, row + 0.5)`
+	Circle {
+		/// Centre of the circle in cell units
+		centre: Vec2,
+		/// Radius of the circle in cell units
+		radius: f32,
+	},
+	/// All cells whose centre lies within the polygon described by a list of vertices in cell units
+	Polygon(Vec<Vec2>),
+	/// An explicit list of `(column, row)` cells, any outside of the map are ignored
+	Cells(Vec<(usize, usize)>),
+	/// Stamp the values of a [CostField] onto the map with its top left cell at `origin`. A mask value of `0` leaves the underlying cell untouched
+	Mask {
+		/// The `(column, row)` the top left cell of the mask is placed at
+		origin: (usize, usize),
+		/// The costs to stamp
+		mask: CostField,
+	},
+}
+
+impl CostRegion {
+	/// Find the sector and [FieldCell] of every cell covered by the region along with the cost it should be assigned. `cell_value` is used for all shapes except a [CostRegion::Mask] which supplies its own costs
+	pub fn get_cell_values(
+		&self,
+		cell_value: u8,
+		map_dimensions: &MapDimensions,
+	) -> Vec<(SectorID, FieldCell, u8)> {
+		self.get_cell_values_on_layer(cell_value, 0, map_dimensions)
+	}
+	/// Find the sector of a sector `layer` and the [FieldCell] of every cell covered by the region along with the cost it should be assigned, see [CostRegion::get_cell_values]
+	pub fn get_cell_values_on_layer(
+		&self,
+		cell_value: u8,
+		layer: u32,
+		map_dimensions: &MapDimensions,
+	) -> Vec<(SectorID, FieldCell, u8)> {
+		let columns = map_dimensions.get_total_field_cell_columns();
+		let rows = map_dimensions.get_total_field_cell_rows();
+		let mut cells = Vec::new();
+		let mut push = |column: usize, row: usize, value: u8| {
+			if let Some((sector_id, field_cell)) =
+				map_dimensions.get_sector_and_field_cell_from_grid_cell(column, row)
+			{
+				cells.push((sector_id.with_layer(layer), field_cell, value));
+			}
+		};
+		match self {
+			CostRegion::Rectangle { min, max } => {
+				for column in min.0..=max.0.min(columns.saturating_sub(1)) {
+					for row in min.1..=max.1.min(rows.saturating_sub(1)) {
+						push(column, row, cell_value);
+					}
+				}
+			}
+			CostRegion::Circle { centre, radius } => {
+				let (min, max) = CostRegion::get_bounds(
+					*centre - Vec2::splat(*radius),
+					*centre + Vec2::splat(*radius),
+					columns,
+					rows,
+				);
+				for column in min.0..=max.0 {
+					for row in min.1..=max.1 {
+						let cell_centre = Vec2::new(column as f32 + 0.5, row as f32 + 0.5);
+						if cell_centre.distance(*centre) <= *radius {
+							push(column, row, cell_value);
+						}
+					}
+				}
+			}
+			CostRegion::Polygon(vertices) => {
+				if vertices.len() < 3 {
+					return cells;
+				}
+				let lower = vertices.iter().fold(Vec2::MAX, |acc, v| acc.min(*v));
+				let upper = vertices.iter().fold(Vec2::MIN, |acc, v| acc.max(*v));
+				let (min, max) = CostRegion::get_bounds(lower, upper, columns, rows);
+				for column in min.0..=max.0 {
+					for row in min.1..=max.1 {
+						let cell_centre = Vec2::new(column as f32 + 0.5, row as f32 + 0.5);
+						if is_point_in_polygon(cell_centre, vertices) {
+							push(column, row, cell_value);
+						}
+					}
+				}
+			}
+			CostRegion::Cells(list) => {
+				for (column, row) in list.iter() {
+					push(*column, *row, cell_value);
+				}
+			}
+			CostRegion::Mask { origin, mask } => {
+				for (i, mask_column) in mask.get().iter().enumerate() {
+					for (j, value) in mask_column.iter().enumerate() {
+						if *value != 0 {
+							push(origin.0 + i, origin.1 + j, *value);
+						}
+					}
+				}
+			}
+		}
+		cells
+	}
+	/// Clamp an area in cell units to the inclusive `(column, row)` bounds of the map
+	fn get_bounds(
+		lower: Vec2,
+		upper: Vec2,
+		columns: usize,
+		rows: usize,
+	) -> ((usize, usize), (usize, usize)) {
+		let clamp = |v: f32, limit: usize| (v.max(0.0) as usize).min(limit.saturating_sub(1));
+		(
+			(clamp(lower.x, columns), clamp(lower.y, rows)),
+			(clamp(upper.x, columns), clamp(upper.y, rows)),
+		)
+	}
+}
+
+/// Even-odd test of whether `point` lies within the polygon described by `vertices`
+fn is_point_in_polygon(point: Vec2, vertices: &[Vec2]) -> bool {
+	let mut inside = false;
+	let mut j = vertices.len() - 1;
+	for i in 0..vertices.len() {
+		let (a, b) = (vertices[i], vertices[j]);
+		if (a.y > point.y) != (b.y > point.y)
+			&& point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+		{
+			inside = !inside;
+		}
+		j = i;
+	}
+	inside
+}
+
+/// Used to update all [CostField] cells within a [CostRegion] in a single batch
+#[derive(Event)]
+pub struct EventUpdateCostfieldsRegion {
+	/// Area of the map to update
+	region: CostRegion,
+	/// The value each field cell should be assigned, ignored by [CostRegion::Mask]
+	cell_value: u8,
+	/// The sector layer to update
+	layer: u32,
+}
+
+impl EventUpdateCostfieldsRegion {
+	/// Create a new instance of [EventUpdateCostfieldsRegion] which updates sector layer `0`
+	#[cfg(not(tarpaulin_include))]
+	pub fn new(region: CostRegion, cell_value: u8) -> Self {
+		EventUpdateCostfieldsRegion {
+			region,
+			cell_value,
+			layer: 0,
+		}
+	}
+	/// Update the sectors of another sector `layer`
+	#[cfg(not(tarpaulin_include))]
+	pub fn with_layer(mut self, layer: u32) -> Self {
+		self.layer = layer;
+		self
+	}
+	#[cfg(not(tarpaulin_include))]
+	pub fn get_layer(&self) -> u32 {
+		self.layer
+	}
+	#[cfg(not(tarpaulin_include))]
+	pub fn get_region(&self) -> &CostRegion {
+		&self.region
+	}
+	#[cfg(not(tarpaulin_include))]
+	pub fn get_cost_value(&self) -> u8 {
+		self.cell_value
+	}
+}
+
+/// A shape in world space used to update every [CostField] cell it covers. In 2d coordinates are `(x, y)` and in 3d they are `(x, z)`
+#[cfg(any(feature = "2d", feature = "3d"))]
+#[derive(Clone, Debug)]
+pub enum WorldCostShape {
+	/// All cells whose centre lies within the axis aligned rectangle between two opposite corners
+	Rectangle {
+		/// One corner of the rectangle
+		min: Vec2,
+		/// The opposite corner of the rectangle
+		max: Vec2,
+	},
+	/// All cells whose centre lies within `radius` of `centre`
+	Circle {
+		/// Centre of the circle
+		centre: Vec2,
+		/// Radius of the circle
+		radius: f32,
+	},
+	/// All cells whose centre lies within the polygon described by a list of vertices
+	Polygon(Vec<Vec2>),
+}
+
+/// The world axes a [WorldCostShape] is measured in
+#[cfg(any(feature = "2d", feature = "3d"))]
+#[derive(Clone, Copy, Debug)]
+enum WorldAxes {
+	/// 2d `(x, y)`
+	#[cfg(feature = "2d")]
+	XY,
+	/// 3d `(x, z)`
+	#[cfg(feature = "3d")]
+	XZ,
+}
+
+/// Used to update all [CostField] cells covered by a shape in world space, the shape may span any number of sectors
+#[cfg(any(feature = "2d", feature = "3d"))]
+#[derive(Event)]
+pub struct EventUpdateCostfieldsWorld {
+	/// Area of the world to update
+	shape: WorldCostShape,
+	/// Axes the shape is measured in
+	axes: WorldAxes,
+	/// The value each field cell should be assigned
+	cell_value: u8,
+	/// The sector layer to update
+	layer: u32,
+}
+
+#[cfg(any(feature = "2d", feature = "3d"))]
+impl EventUpdateCostfieldsWorld {
+	/// Create a new instance of [EventUpdateCostfieldsWorld] from a shape in 2d `(x, y)` coordinates
+	#[cfg(feature = "2d")]
+	pub fn new_xy(shape: WorldCostShape, cell_value: u8) -> Self {
+		EventUpdateCostfieldsWorld {
+			shape,
+			axes: WorldAxes::XY,
+			cell_value,
+			layer: 0,
+		}
+	}
+	/// Create a new instance of [EventUpdateCostfieldsWorld] from a shape in 3d `(x, z)` coordinates
+	#[cfg(feature = "3d")]
+	pub fn new_xyz(shape: WorldCostShape, cell_value: u8) -> Self {
+		EventUpdateCostfieldsWorld {
+			shape,
+			axes: WorldAxes::XZ,
+			cell_value,
+			layer: 0,
+		}
+	}
+	/// Update the sectors of another sector `layer`, by default layer `0` is updated
+	#[cfg(not(tarpaulin_include))]
+	pub fn with_layer(mut self, layer: u32) -> Self {
+		self.layer = layer;
+		self
+	}
+	#[cfg(not(tarpaulin_include))]
+	pub fn get_layer(&self) -> u32 {
+		self.layer
+	}
+	#[cfg(not(tarpaulin_include))]
+	pub fn get_shape(&self) -> &WorldCostShape {
+		&self.shape
+	}
+	#[cfg(not(tarpaulin_include))]
+	pub fn get_cost_value(&self) -> u8 {
+		self.cell_value
+	}
+	/// Convert the world shape into a [CostRegion] measured in [FieldCell]s across the whole map
+	pub fn get_region(&self, map_dimensions: &MapDimensions) -> CostRegion {
+		let to_grid = |point: Vec2| match self.axes {
+			#[cfg(feature = "2d")]
+			WorldAxes::XY => map_dimensions.get_grid_position_from_xy(point),
+			#[cfg(feature = "3d")]
+			WorldAxes::XZ => map_dimensions.get_grid_position_from_xyz(Vec3::new(point.x, 0.0, point.y)),
+		};
+		match &self.shape {
+			WorldCostShape::Rectangle { min, max } => {
+				let a = to_grid(*min);
+				let b = to_grid(*max);
+				CostRegion::Polygon(vec![
+					Vec2::new(a.x, a.y),
+					Vec2::new(b.x, a.y),
+					Vec2::new(b.x, b.y),
+					Vec2::new(a.x, b.y),
+				])
+			}
+			WorldCostShape::Circle { centre, radius } => {
+				let cell_size =
+					map_dimensions.get_sector_resolution() as f32 / FIELD_RESOLUTION as f32;
+				CostRegion::Circle {
+					centre: to_grid(*centre),
+					radius: radius / cell_size,
+				}
+			}
+			WorldCostShape::Polygon(vertices) => {
+				CostRegion::Polygon(vertices.iter().map(|v| to_grid(*v)).collect())
+			}
+		}
+	}
+}
+
+/// Used to apply a [CostModifier] to every cell within a [CostRegion] until it is removed with [EventRemoveCostModifier] or its duration expires
+#[derive(Event)]
+pub struct EventAddCostModifier {
+	/// Identifies the modifier so that it can be removed
+	handle: CostModifierHandle,
+	/// Area of the map the modifier covers, for a [CostRegion::Mask] every non-zero cell is covered
+	region: CostRegion,
+	/// Change made to the cost of each cell
+	modifier: CostModifier,
+	/// Seconds until the modifier is removed, [None] if it lasts until removed
+	duration: Option<f32>,
+	/// The sector layer the modifier covers
+	layer: u32,
+}
+
+impl EventAddCostModifier {
+	/// Create a new instance of [EventAddCostModifier] with a new unique handle which can be retrieved with [EventAddCostModifier::get_handle] before sending the event
+	#[cfg(not(tarpaulin_include))]
+	pub fn new(region: CostRegion, modifier: CostModifier, duration: Option<f32>) -> Self {
+		EventAddCostModifier {
+			handle: CostModifierHandle::new(),
+			region,
+			modifier,
+			duration,
+			layer: 0,
+		}
+	}
+	/// Cover the sectors of another sector `layer`, by default layer `0` is covered
+	#[cfg(not(tarpaulin_include))]
+	pub fn with_layer(mut self, layer: u32) -> Self {
+		self.layer = layer;
+		self
+	}
+	#[cfg(not(tarpaulin_include))]
+	pub fn get_layer(&self) -> u32 {
+		self.layer
+	}
+	#[cfg(not(tarpaulin_include))]
+	pub fn get_handle(&self) -> CostModifierHandle {
+		self.handle
+	}
+	#[cfg(not(tarpaulin_include))]
+	pub fn get_region(&self) -> &CostRegion {
+		&self.region
+	}
+	#[cfg(not(tarpaulin_include))]
+	pub fn get_modifier(&self) -> CostModifier {
+		self.modifier
+	}
+	#[cfg(not(tarpaulin_include))]
+	pub fn get_duration(&self) -> Option<f32> {
+		self.duration
+	}
+}
+
+/// Used to remove a cost modifier, restoring the costs the cells would have without it
+#[derive(Event)]
+pub struct EventRemoveCostModifier(pub CostModifierHandle);
+
+/// Advance the duration of timed cost modifiers and remove any which have expired
+#[cfg(not(tarpaulin_include))]
+pub fn tick_cost_modifiers(
+	time: Res<Time>,
+	mut query: Query<&mut SectorCostModifiers>,
+	mut event_remove: EventWriter<EventRemoveCostModifier>,
+) {
+	for mut modifiers in query.iter_mut() {
+		if modifiers.is_empty() {
+			continue;
+		}
+		for handle in modifiers.tick(time.delta_seconds()) {
+			event_remove.send(EventRemoveCostModifier(handle));
+		}
+	}
+}
+
+/// Read [EventUpdateCostfieldsCell], [EventUpdateCostfieldsRegion], `EventUpdateCostfieldsWorld`, [EventAddCostModifier] and [EventRemoveCostModifier] and update the values within [CostField]. All of the cost changes within a frame are applied first and then each affected sector has its scaled field rebuilt once. Portals and their [PortalGraph] nodes are only recalculated along the sector boundaries whose edge cells changed
 #[cfg(not(tarpaulin_include))]
+#[allow(clippy::too_many_arguments)]
 pub fn process_costfields_updates(
 	mut events: EventReader<EventUpdateCostfieldsCell>,
+	mut region_events: EventReader<EventUpdateCostfieldsRegion>,
+	#[cfg(any(feature = "2d", feature = "3d"))] mut world_events: EventReader<
+		EventUpdateCostfieldsWorld,
+	>,
+	mut add_modifier_events: EventReader<EventAddCostModifier>,
+	mut remove_modifier_events: EventReader<EventRemoveCostModifier>,
 	mut query: Query<(
 		&mut PortalGraph,
 		&mut SectorPortals,
 		&mut SectorCostFields,
 		&MapDimensions,
+		Option<&mut SectorCostModifiers>,
 	)>,
 	mut event_cache_clean: EventWriter<EventCleanCaches>,
 ) {
-	// coalesce events to avoid processing duplicates
-	let mut coalesced_sectors = Vec::new();
+	let mut changes = Vec::new();
 	for event in events.read() {
-		let field_cell = event.get_cell();
-		let sector_id = event.get_sector();
-		let cost = event.get_cost_value();
-		for (_portal_graph, mut sector_portals, mut sector_cost_fields, dimensions) in
-			query.iter_mut()
-		{
-			sector_cost_fields.set_field_cell_value(sector_id, cost, field_cell, dimensions);
-			// update the portals of the sector and around it
-			sector_portals.update_portals(sector_id, sector_cost_fields.as_ref(), dimensions);
-		}
-		if !coalesced_sectors.contains(&sector_id) {
-			coalesced_sectors.push(sector_id);
-		}
-	}
-	for sector_id in coalesced_sectors.iter() {
-		debug!("Rebuilding fields of {:?}", sector_id.get());
-		for (mut portal_graph, sector_portals, sector_cost_fields, dimensions) in query.iter_mut() {
-			// update the graph
-			portal_graph.update_graph(
-				*sector_id,
-				sector_portals.as_ref(),
-				sector_cost_fields.as_ref(),
+		changes.push((event.get_sector(), event.get_cell(), event.get_cost_value()));
+	}
+	let regions: Vec<&EventUpdateCostfieldsRegion> = region_events.read().collect();
+	#[cfg(any(feature = "2d", feature = "3d"))]
+	let world_shapes: Vec<&EventUpdateCostfieldsWorld> = world_events.read().collect();
+	#[cfg(any(feature = "2d", feature = "3d"))]
+	let has_world_shapes = !world_shapes.is_empty();
+	#[cfg(not(any(feature = "2d", feature = "3d")))]
+	let has_world_shapes = false;
+	let added: Vec<&EventAddCostModifier> = add_modifier_events.read().collect();
+	let removed: Vec<CostModifierHandle> = remove_modifier_events.read().map(|e| e.0).collect();
+	if changes.is_empty()
+		&& regions.is_empty()
+		&& !has_world_shapes
+		&& added.is_empty()
+		&& removed.is_empty()
+	{
+		return;
+	}
+	// coalesce the changes of every map to avoid processing duplicates
+	let mut coalesced: BTreeMap<SectorID, (Vec<FieldCell>, Vec<Ordinal>, bool)> = BTreeMap::new();
+	for (mut portal_graph, mut sector_portals, mut sector_cost_fields, dimensions, mut modifiers) in
+		query.iter_mut()
+	{
+		let mut map_changes = changes.clone();
+		for event in regions.iter() {
+			map_changes.extend(event.get_region().get_cell_values_on_layer(
+				event.get_cost_value(),
+				event.get_layer(),
 				dimensions,
-			);
+			));
 		}
-		event_cache_clean.send(EventCleanCaches(*sector_id));
+		#[cfg(any(feature = "2d", feature = "3d"))]
+		for event in world_shapes.iter() {
+			map_changes.extend(event.get_region(dimensions).get_cell_values_on_layer(
+				event.get_cost_value(),
+				event.get_layer(),
+				dimensions,
+			));
+		}
+		// permanent changes are made to the baseline, beneath any modifiers
+		let mut edited = sector_cost_fields.set_baseline_field_cell_values(&map_changes);
+		if let Some(modifiers) = modifiers.as_mut() {
+			for handle in removed.iter() {
+				edited.extend(modifiers.remove(*handle, &mut sector_cost_fields));
+			}
+			for event in added.iter() {
+				let cells = event
+					.get_region()
+					.get_cell_values_on_layer(0, event.get_layer(), dimensions)
+					.into_iter()
+					.map(|(sector_id, field_cell, _)| (sector_id, field_cell))
+					.collect();
+				edited.extend(modifiers.add(
+					event.get_handle(),
+					cells,
+					event.get_modifier(),
+					event.get_duration(),
+					&mut sector_cost_fields,
+				));
+			}
+		} else if !added.is_empty() {
+			warn!("Cost modifiers require the SectorCostModifiers component, modifiers have not been applied");
+		}
+		edited.sort();
+		edited.dedup();
+		// the scaled fields which may be rescaled, used to find which cells became cheaper
+		let mut previous = BTreeMap::new();
+		for sector_id in edited.iter() {
+			let mut ids = dimensions.get_ids_of_neighbouring_sectors(sector_id);
+			ids.push(*sector_id);
+			for id in ids.into_iter() {
+				if let Some(field) = sector_cost_fields.get_scaled().get(&id) {
+					previous.entry(id).or_insert_with(|| field.clone());
+				}
+			}
+		}
+		let changed = sector_cost_fields.rescale_costfields(&edited, dimensions);
+		if changed.is_empty() {
+			continue;
+		}
+		let lowered: Vec<SectorID> = changed
+			.keys()
+			.filter(|sector_id| {
+				match (
+					previous.get(*sector_id),
+					sector_cost_fields.get_scaled().get(*sector_id),
+				) {
+					(Some(old), Some(new)) => !old.get_lowered_cells(new).is_empty(),
+					_ => true,
+				}
+			})
+			.cloned()
+			.collect();
+		// update only the portals along boundaries whose edge cells changed
+		let boundaries = sector_portals.update_portals_on_boundaries(
+			&changed,
+			sector_cost_fields.as_ref(),
+			dimensions,
+		);
+		let changed_sectors: Vec<SectorID> = changed.keys().cloned().collect();
+		debug!("Rebuilding fields of {:?}", changed_sectors);
+		// update the graph
+		portal_graph.update_graph_boundaries(
+			&changed_sectors,
+			&boundaries,
+			sector_portals.as_ref(),
+			sector_cost_fields.as_ref(),
+			dimensions,
+		);
+		for (sector_id, field_cells) in changed.into_iter() {
+			let entry = coalesced.entry(sector_id).or_default();
+			entry.2 |= lowered.contains(&sector_id);
+			for field_cell in field_cells {
+				if !entry.0.contains(&field_cell) {
+					entry.0.push(field_cell);
+				}
+			}
+		}
+		for (sector_id, side) in boundaries.into_iter() {
+			let entry = coalesced.entry(sector_id).or_default();
+			if !entry.1.contains(&side) {
+				entry.1.push(side);
+			}
+		}
+	}
+	for (sector_id, (field_cells, sides, has_lowered_costs)) in coalesced.into_iter() {
+		event_cache_clean.send(
+			EventCleanCaches::new(sector_id, field_cells, sides)
+				.with_lowered_costs(has_lowered_costs),
+		);
 	}
 }
 
-/// For the given sector any route or [FlowField] making use of it needs to have the cached entry removed and a new request made to regenerate the route
+/// Describes how the scaled [CostField] of a sector has changed so that any route or [FlowField] making use of it can be checked and updated
 #[derive(Event)]
-pub struct EventCleanCaches(SectorID);
+pub struct EventCleanCaches {
+	/// The sector which changed
+	sector_id: SectorID,
+	/// Each [FieldCell] of the scaled [CostField] whose value changed
+	field_cells: Vec<FieldCell>,
+	/// Sides of the sector whose portals were recalculated
+	sides: Vec<Ordinal>,
+	/// Whether any [FieldCell] of the scaled [CostField] became cheaper
+	has_lowered_costs: bool,
+}
 
-/// Lookup any cached data records making use of sectors that have had their [CostField] adjusted and remove them from the cache
+impl EventCleanCaches {
+	/// Create a new instance of [EventCleanCaches]
+	pub fn new(sector_id: SectorID, field_cells: Vec<FieldCell>, sides: Vec<Ordinal>) -> Self {
+		EventCleanCaches {
+			sector_id,
+			field_cells,
+			sides,
+			has_lowered_costs: false,
+		}
+	}
+	/// Set whether any [FieldCell] of the sector became cheaper, only then can a route through the sector become cheaper without its portals changing
+	pub fn with_lowered_costs(mut self, has_lowered_costs: bool) -> Self {
+		self.has_lowered_costs = has_lowered_costs;
+		self
+	}
+	/// Get the sector which changed
+	pub fn get_sector_id(&self) -> SectorID {
+		self.sector_id
+	}
+	/// Get the cells whose cost changed
+	pub fn get_field_cells(&self) -> &Vec<FieldCell> {
+		&self.field_cells
+	}
+	/// Get the sides of the sector whose portals were recalculated
+	pub fn get_sides(&self) -> &Vec<Ordinal> {
+		&self.sides
+	}
+	/// Get whether any cell of the sector became cheaper
+	pub fn has_lowered_costs(&self) -> bool {
+		self.has_lowered_costs
+	}
+}
+
+/// Caches of a map along with the data needed to rebuild them
+type CacheQueryData = (
+	&'static mut FlowFieldCache,
+	&'static mut RouteCache,
+	&'static PortalGraph,
+	&'static SectorPortals,
+	&'static SectorCostFields,
+	&'static MapDimensions,
+	Option<&'static SectorDensityFields>,
+);
+
+/// Lookup any cached routes and [FlowField]s making use of sectors that have had their [CostField] adjusted and work out whether the change could affect them.
+///
+/// - A cached [FlowField] is only recalculated when one of the changed cells was reachable from its goals, or borders a cell which was (see [FlowField::is_affected_by]), or when the portal it leads to was recalculated. The [FlowField] is rebuilt in place from a fresh [IntegrationField] of just that sector, every other [FlowField] of the route is kept
+/// - A route is removed and requested again when it can no longer be followed, i.e one of its portals has been removed or the rebuilt [IntegrationField] of a sector no longer reaches the point where the route enters it
+/// - A route which can still be followed is also requested again when the change means it is no longer the best route, such as lowered costs or a new or widened portal opening up a cheaper one. Only changes which lowered costs or recalculated portals can do this and only routes that could pass through such a sector for less than they cost now are looked up again with [find_route], the route is replaced when the new one is strictly cheaper (see `get_route_cost`)
 #[cfg(not(tarpaulin_include))]
 pub fn clean_cache(
 	mut events: EventReader<EventCleanCaches>,
-	mut q_flow: Query<&mut FlowFieldCache>,
-	mut q_route: Query<&mut RouteCache>,
+	mut q_cache: Query<CacheQueryData>,
 	mut event_path_request: EventWriter<EventPathRequest>,
 ) {
-	let mut sectors = Vec::new();
+	let mut edits: BTreeMap<SectorID, (Vec<FieldCell>, Vec<Ordinal>)> = BTreeMap::new();
+	// sectors which any route through could now be cheaper
+	let mut cheaper = BTreeSet::new();
 	for event in events.read() {
-		sectors.push(event.0);
-	}
-	if !sectors.is_empty() {
-		for mut flow_cache in q_flow.iter_mut() {
-			// purge invalid queued integratrion fields
-			let mut to_purge = Vec::new();
-			let map = flow_cache.get_queue_mut();
-			for id in sectors.iter() {
-				'next: for (metadata, builder) in map.iter() {
-					let path = builder.get_path();
-					for (route_sector, _) in path.iter() {
-						if *id == *route_sector {
-							to_purge.push(*metadata);
-							continue 'next;
+		let entry = edits.entry(event.sector_id).or_default();
+		entry.0.extend(event.field_cells.iter());
+		entry.1.extend(event.sides.iter());
+		if event.has_lowered_costs || !event.sides.is_empty() {
+			cheaper.insert(event.sector_id);
+		}
+	}
+	if edits.is_empty() {
+		return;
+	}
+	for (
+		mut flow_cache,
+		mut route_cache,
+		portal_graph,
+		sector_portals,
+		sector_cost_fields,
+		map_dimensions,
+		density,
+	) in q_cache.iter_mut()
+	{
+		let mut to_request = Vec::new();
+		// purge queued routes which cross a portal that no longer exists
+		let mut to_purge = Vec::new();
+		for (metadata, route) in route_cache.get_queue().iter() {
+			if !is_route_portals_valid(route, &edits, sector_portals) {
+				to_purge.push(*metadata);
+			}
+		}
+		for purge_me in to_purge.into_iter() {
+			route_cache.remove_queued_route(purge_me);
+			to_request.push(purge_me);
+		}
+		// check each route and update the flow fields along it
+		let routes: Vec<(RouteMetadata, Vec<(SectorID, FieldCell)>)> = route_cache
+			.get()
+			.iter()
+			.filter(|(_, route)| route.iter().any(|(s, _)| edits.contains_key(s)))
+			.map(|(metadata, route)| (*metadata, route.clone()))
+			.collect();
+		// flow fields are shared between routes, only rebuild each one once
+		let mut rebuilt: BTreeMap<(SectorID, FieldCell), IntegrationField> = BTreeMap::new();
+		for (metadata, route) in routes.iter() {
+			let mut is_valid = is_route_portals_valid(route, &edits, sector_portals);
+			if is_valid {
+				let affected = find_affected_route_sectors(route, &edits, &flow_cache);
+				for (i, (sector_id, goal)) in route.iter().enumerate() {
+					if !affected[i] {
+						continue;
+					}
+					if let Entry::Vacant(entry) = rebuilt.entry((*sector_id, *goal)) {
+						let Some((flow_field, int_field)) = rebuild_route_flow_field(
+							route,
+							i,
+							sector_portals,
+							sector_cost_fields,
+							map_dimensions,
+							density,
+						) else {
+							continue;
+						};
+						if let Some(field) = flow_cache.get_field_mut(*sector_id, *goal) {
+							*field = flow_field;
+						}
+						entry.insert(int_field);
+					}
+					// the route is blocked if the sector can no longer be crossed from where the route enters it
+					let entry = if i == 0 {
+						(metadata.get_source_sector() == *sector_id)
+							.then_some(metadata.get_source_field_cell())
+					} else {
+						Ordinal::sector_to_sector_direction(*sector_id, route[i - 1].0)
+							.map(|ord| get_entry_field_cell(route[i - 1].1, ord))
+					};
+					if let Some(entry) = entry {
+						let int_field = rebuilt.get(&(*sector_id, *goal)).unwrap();
+						if int_field.get_field_cell_value(entry) == u16::MAX {
+							is_valid = false;
+							break;
 						}
 					}
 				}
 			}
-			for purge_me in to_purge.iter() {
-				flow_cache.remove_queue_item(*purge_me);
+			if !is_valid {
+				route_cache.remove_route(*metadata);
+				to_request.push(*metadata);
 			}
-			// purge invalid flow fields
-			let mut to_purge = Vec::new();
-			let map = flow_cache.get_mut();
-			for id in sectors.iter() {
-				for metadata in map.keys() {
-					if *id == metadata.get_sector_id() {
-						to_purge.push(*metadata);
-					}
+		}
+		// the change may have opened up a cheaper route, replace any route that is no longer the best
+		let mut superseded = Vec::new();
+		if !cheaper.is_empty() {
+			let has_layer_connections = !portal_graph.get_layer_connections().is_empty();
+			for (metadata, route) in route_cache.get().iter() {
+				let cost = get_route_cost(metadata, route, portal_graph, sector_cost_fields)
+					.unwrap_or(u32::MAX);
+				if !could_route_be_cheaper(metadata, route, cost, &cheaper, has_layer_connections) {
+					continue;
+				}
+				let is_cheaper =
+					find_route(metadata, portal_graph, sector_portals, sector_cost_fields)
+						.and_then(|best| {
+							get_route_cost(metadata, &best, portal_graph, sector_cost_fields)
+						})
+						.is_some_and(|best_cost| best_cost < cost);
+				if is_cheaper {
+					superseded.push(*metadata);
 				}
 			}
-			for purge_me in to_purge.iter() {
-				flow_cache.remove_field(*purge_me);
+		}
+		for metadata in superseded.into_iter() {
+			route_cache.remove_route(metadata);
+			to_request.push(metadata);
+		}
+		// free the flow fields only used by routes that are being regenerated, the new routes build their own
+		for metadata in to_request.iter() {
+			flow_cache.remove_route_user(*metadata);
+		}
+		// queued integration fields are recalculated from the new costs, or removed along with their route
+		let mut to_purge = Vec::new();
+		for (metadata, builder) in flow_cache.get_queue_mut().iter_mut() {
+			if to_request.contains(metadata) {
+				to_purge.push(*metadata);
+			} else if builder
+				.get_path()
+				.iter()
+				.any(|(s, _)| edits.contains_key(s))
+			{
+				builder.reset_integration_fields();
 			}
 		}
-		for mut route_cache in q_route.iter_mut() {
-			// purge queued routes
-			let mut to_purge = Vec::new();
-			let map = route_cache.get_queue_mut();
-			for id in sectors.iter() {
-				'next: for (metadata, route) in map.iter() {
-					if *id == metadata.get_source_sector() {
-						to_purge.push(*metadata);
-						continue 'next;
-					}
-					if *id == metadata.get_target_sector() {
-						to_purge.push(*metadata);
-						continue 'next;
-					}
-					for (route_sector, _) in route.iter() {
-						if *id == *route_sector {
-							to_purge.push(*metadata);
-							continue 'next;
-						}
-					}
-				}
+		for purge_me in to_purge.into_iter() {
+			flow_cache.remove_queue_item(purge_me);
+		}
+		// send events to regenerate routes, reusing the existing handles so that actors find the new route
+		for metadata in to_request.iter() {
+			let mut request = EventPathRequest::new(
+				metadata.get_source_sector(),
+				metadata.get_source_field_cell(),
+				metadata.get_target_sector(),
+				metadata.get_target_goal(),
+			)
+			.with_priority(metadata.get_priority());
+			if let Some(handle) = route_cache.get_handles(metadata).first() {
+				request = request.with_handle(*handle);
 			}
-			for purge_me in to_purge.iter() {
-				route_cache.remove_queued_route(*purge_me);
-			}
-			// purge invalid routes
-			let mut to_purge = Vec::new();
-			let map = route_cache.get_mut();
-			for id in sectors.iter() {
-				'next: for (metadata, route) in map.iter() {
-					if *id == metadata.get_source_sector() {
-						to_purge.push(*metadata);
-						continue 'next;
-					}
-					if *id == metadata.get_target_sector() {
-						to_purge.push(*metadata);
-						continue 'next;
-					}
-					for (route_sector, _) in route.iter() {
-						if *id == *route_sector {
-							to_purge.push(*metadata);
-							continue 'next;
+			event_path_request.send(request);
+		}
+	}
+}
+
+/// Measure how expensive it is to follow a `route` (ordered from actor to goal) from the source of its `metadata`. Each sector is crossed by the cheapest series of [FieldCell]s from where the route enters it to where it leaves (see [CostField::get_cell_path_cost]), stepping across a portal costs the same as any other orthogonal step and a [LayerConnection] adds its own cost. Returns [None] if any part of the route can't be crossed
+fn get_route_cost(
+	metadata: &RouteMetadata,
+	route: &[(SectorID, FieldCell)],
+	portal_graph: &PortalGraph,
+	sector_cost_fields: &SectorCostFields,
+) -> Option<u32> {
+	let mut current = (
+		metadata.get_source_sector(),
+		metadata.get_source_field_cell(),
+	);
+	let mut cost = 0;
+	for (i, (sector_id, exit)) in route.iter().enumerate() {
+		if *sector_id != current.0 {
+			return None;
+		}
+		let cost_field = sector_cost_fields.get_scaled().get(sector_id)?;
+		let path = cost_field.find_cell_path(current.1, *exit)?;
+		cost += cost_field.get_cell_path_cost(&path);
+		let Some((next_sector_id, _)) = route.get(i + 1) else {
+			break;
+		};
+		if let Some(ord) = Ordinal::sector_to_sector_direction(*next_sector_id, *sector_id) {
+			let entry = get_entry_field_cell(*exit, ord);
+			let next_cost_field = sector_cost_fields.get_scaled().get(next_sector_id)?;
+			cost += 10 * next_cost_field.get_field_cell_value(entry) as u32;
+			current = (*next_sector_id, entry);
+		} else {
+			// the sectors are on different layers and joined by a connection
+			let (entry, connection_cost) =
+				portal_graph
+					.get_layer_connections()
+					.iter()
+					.find_map(|connection| {
+						if connection.get_from() == (*sector_id, *exit)
+							&& connection.get_to().0 == *next_sector_id
+						{
+							Some((connection.get_to(), connection.get_cost()))
+						} else if connection.is_bidirectional()
+							&& connection.get_to() == (*sector_id, *exit)
+							&& connection.get_from().0 == *next_sector_id
+						{
+							Some((connection.get_from(), connection.get_cost()))
+						} else {
+							None
 						}
-					}
-				}
+					})?;
+			cost += connection_cost.max(0) as u32;
+			current = entry;
+		}
+	}
+	Some(cost)
+}
+
+/// Whether a `route` costing `cost` could be beaten by a route through one of the `cheaper` sectors. A sector along the route always could, any other sector only when the shortest distance from the source through the sector to the target is cheaper than the route. Every step onto a cell costs at least 10 (see [CostField::get_cell_path_cost]) so the distance in cells is a lower bound of any route through the sector. A [LayerConnection] may join distant cells so sectors on another layer, or any map with layer connections, always could
+fn could_route_be_cheaper(
+	metadata: &RouteMetadata,
+	route: &[(SectorID, FieldCell)],
+	cost: u32,
+	cheaper: &BTreeSet<SectorID>,
+	has_layer_connections: bool,
+) -> bool {
+	if route
+		.iter()
+		.any(|(sector_id, _)| cheaper.contains(sector_id))
+	{
+		return true;
+	}
+	let to_grid = |sector_id: SectorID, field_cell: FieldCell| {
+		(
+			sector_id.get_column() * FIELD_RESOLUTION as u32 + field_cell.get_column() as u32,
+			sector_id.get_row() * FIELD_RESOLUTION as u32 + field_cell.get_row() as u32,
+		)
+	};
+	let source_sector = metadata.get_source_sector();
+	let target_sector = metadata.get_target_sector();
+	let source = to_grid(source_sector, metadata.get_source_field_cell());
+	let target = to_grid(target_sector, metadata.get_target_goal());
+	for sector_id in cheaper.iter() {
+		if has_layer_connections
+			|| sector_id.get_layer() != source_sector.get_layer()
+			|| sector_id.get_layer() != target_sector.get_layer()
+		{
+			return true;
+		}
+		let min = to_grid(*sector_id, FieldCell::new(0, 0));
+		let max = to_grid(
+			*sector_id,
+			FieldCell::new(FIELD_RESOLUTION - 1, FIELD_RESOLUTION - 1),
+		);
+		// the fewest steps from a cell into the sector
+		let steps_to = |(column, row): (u32, u32)| {
+			let dx = min
+				.0
+				.saturating_sub(column)
+				.max(column.saturating_sub(max.0));
+			let dy = min.1.saturating_sub(row).max(row.saturating_sub(max.1));
+			dx.max(dy)
+		};
+		if 10 * (steps_to(source) + steps_to(target)) < cost {
+			return true;
+		}
+	}
+	false
+}
+
+/// Check that each portal a `route` (ordered from actor to goal) leaves a sector by still exists along any boundary whose portals were recalculated
+fn is_route_portals_valid(
+	route: &[(SectorID, FieldCell)],
+	edits: &BTreeMap<SectorID, (Vec<FieldCell>, Vec<Ordinal>)>,
+	sector_portals: &SectorPortals,
+) -> bool {
+	for pair in route.windows(2) {
+		let ((sector_id, portal), (next_sector_id, _)) = (pair[0], pair[1]);
+		// a connection onto another layer isn't a portal, it's checked when the flow field is rebuilt
+		if sector_id.get_layer() != next_sector_id.get_layer() {
+			continue;
+		}
+		let Some(ord) = Ordinal::sector_to_sector_direction(next_sector_id, sector_id) else {
+			return false;
+		};
+		let is_recalculated = edits
+			.get(&sector_id)
+			.is_some_and(|(_, sides)| sides.contains(&ord))
+			|| edits
+				.get(&next_sector_id)
+				.is_some_and(|(_, sides)| sides.contains(&ord.inverse()));
+		if is_recalculated {
+			let Some(portals) = sector_portals.get().get(&sector_id) else {
+				return false;
+			};
+			if !portals.get(&ord).contains(&portal) {
+				return false;
 			}
-			for purge_me in to_purge.iter() {
-				route_cache.remove_route(*purge_me);
+		}
+	}
+	true
+}
+
+/// For each sector of a `route` (ordered from actor to goal) find whether its cached [FlowField] needs rebuilding. A [FlowField] is affected by changes to its own cells, by its portal being recalculated (which can change how far the portal goals are expanded) or by the sector towards the goal being affected as the portal goals are pointed using the [IntegrationField] of that sector
+fn find_affected_route_sectors(
+	route: &[(SectorID, FieldCell)],
+	edits: &BTreeMap<SectorID, (Vec<FieldCell>, Vec<Ordinal>)>,
+	flow_cache: &FlowFieldCache,
+) -> Vec<bool> {
+	let mut affected = vec![false; route.len()];
+	for (i, (sector_id, goal)) in route.iter().enumerate().rev() {
+		let Some(flow_field) = flow_cache.get_field(*sector_id, *goal) else {
+			continue;
+		};
+		if let Some((field_cells, _)) = edits.get(sector_id) {
+			if flow_field.is_affected_by(field_cells) {
+				affected[i] = true;
+				continue;
 			}
-			// send events to regenerate routes
-			for metadata in to_purge.iter() {
-				event_path_request.send(EventPathRequest::new(
-					metadata.get_source_sector(),
-					metadata.get_source_field_cell(),
-					metadata.get_target_sector(),
-					metadata.get_target_goal(),
-				));
+		}
+		if let Some((next_sector_id, _)) = route.get(i + 1) {
+			if affected[i + 1] {
+				affected[i] = true;
+				continue;
+			}
+			if let Some(ord) = Ordinal::sector_to_sector_direction(*next_sector_id, *sector_id) {
+				affected[i] = edits
+					.get(sector_id)
+					.is_some_and(|(_, sides)| sides.contains(&ord))
+					|| edits
+						.get(next_sector_id)
+						.is_some_and(|(_, sides)| sides.contains(&ord.inverse()));
+			}
+		}
+	}
+	affected
+}
+
+/// Find the [FieldCell] an actor arrives at when crossing a boundary from `exit` (a cell along the edge of the previous sector) in the direction `ord`
+fn get_entry_field_cell(exit: FieldCell, ord: Ordinal) -> FieldCell {
+	match ord {
+		Ordinal::North => FieldCell::new(exit.get_column(), FIELD_RESOLUTION - 1),
+		Ordinal::East => FieldCell::new(0, exit.get_row()),
+		Ordinal::South => FieldCell::new(exit.get_column(), 0),
+		Ordinal::West => FieldCell::new(FIELD_RESOLUTION - 1, exit.get_row()),
+		_ => exit,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bevy::{ecs::system::RunSystemOnce, utils::Duration};
+
+	/// Get the `(column, row)` across the whole map of each cell from a list of region changes
+	fn to_grid_cells(
+		changes: &[(SectorID, FieldCell, u8)],
+		map_dimensions: &MapDimensions,
+	) -> Vec<(usize, usize)> {
+		let mut cells: Vec<(usize, usize)> = changes
+			.iter()
+			.map(|(sector, cell, _)| {
+				map_dimensions.get_grid_cell_from_sector_and_field_cell(*sector, *cell)
+			})
+			.collect();
+		cells.sort();
+		cells
+	}
+	/// Create a [World] with a 30x30 map and the events read by [process_costfields_updates]
+	fn setup_world() -> World {
+		setup_world_with_bundle(FlowFieldTilesBundle::new(30, 30, 10, 0.5))
+	}
+	/// Create a [World] with the map of `bundle` and the events read by [process_costfields_updates]
+	fn setup_world_with_bundle(bundle: FlowFieldTilesBundle) -> World {
+		let mut world = World::new();
+		world.init_resource::<Events<EventUpdateCostfieldsCell>>();
+		world.init_resource::<Events<EventUpdateCostfieldsRegion>>();
+		#[cfg(any(feature = "2d", feature = "3d"))]
+		world.init_resource::<Events<EventUpdateCostfieldsWorld>>();
+		world.init_resource::<Events<EventAddCostModifier>>();
+		world.init_resource::<Events<EventRemoveCostModifier>>();
+		world.init_resource::<Events<EventCleanCaches>>();
+		world.spawn(bundle);
+		world
+	}
+	/// Run [process_costfields_updates] and then clear the events it reads so they aren't read again by the next run
+	fn run_updates(world: &mut World) {
+		world.run_system_once(process_costfields_updates);
+		world
+			.resource_mut::<Events<EventUpdateCostfieldsCell>>()
+			.clear();
+		world
+			.resource_mut::<Events<EventUpdateCostfieldsRegion>>()
+			.clear();
+		world.resource_mut::<Events<EventAddCostModifier>>().clear();
+		world
+			.resource_mut::<Events<EventRemoveCostModifier>>()
+			.clear();
+	}
+	#[test]
+	fn region_rectangle_clipped() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		let region = CostRegion::Rectangle {
+			min: (18, 8),
+			max: (25, 11),
+		};
+		let changes = region.get_cell_values(255, &map_dimensions);
+		let result = to_grid_cells(&changes, &map_dimensions);
+		let actual = vec![
+			(18, 8),
+			(18, 9),
+			(18, 10),
+			(18, 11),
+			(19, 8),
+			(19, 9),
+			(19, 10),
+			(19, 11),
+		];
+		assert_eq!(actual, result);
+		assert!(changes.iter().all(|(_, _, v)| *v == 255));
+	}
+	#[test]
+	fn region_circle() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		let region = CostRegion::Circle {
+			centre: Vec2::new(10.0, 10.0),
+			radius: 1.0,
+		};
+		let changes = region.get_cell_values(50, &map_dimensions);
+		let result = to_grid_cells(&changes, &map_dimensions);
+		// the four cells meeting at the centre, spread over four sectors
+		let actual = vec![(9, 9), (9, 10), (10, 9), (10, 10)];
+		assert_eq!(actual, result);
+	}
+	#[test]
+	fn region_polygon() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		// right angled triangle
+		let region = CostRegion::Polygon(vec![
+			Vec2::new(0.0, 0.0),
+			Vec2::new(3.5, 0.0),
+			Vec2::new(0.0, 3.5),
+		]);
+		let changes = region.get_cell_values(255, &map_dimensions);
+		let result = to_grid_cells(&changes, &map_dimensions);
+		let actual = vec![(0, 0), (0, 1), (0, 2), (1, 0), (1, 1), (2, 0)];
+		assert_eq!(actual, result);
+	}
+	#[test]
+	fn region_mask() {
+		let map_dimensions = MapDimensions::new(20, 20, 10, 0.5);
+		let mut mask = CostField::new_with_cost(0);
+		mask.set_field_cell_value(255, FieldCell::new(0, 0));
+		mask.set_field_cell_value(7, FieldCell::new(1, 2));
+		// falls outside of the map
+		mask.set_field_cell_value(7, FieldCell::new(9, 9));
+		let region = CostRegion::Mask {
+			origin: (14, 12),
+			mask,
+		};
+		let mut changes = region.get_cell_values(1, &map_dimensions);
+		changes.sort_by_key(|(_, _, v)| *v);
+		let actual = vec![
+			(SectorID::new(1, 1), FieldCell::new(5, 4), 7),
+			(SectorID::new(1, 1), FieldCell::new(4, 2), 255),
+		];
+		assert_eq!(actual, changes);
+	}
+	#[test]
+	fn region_update_blocks_path() {
+		let mut world = setup_world();
+		// wall off the middle of the map
+		world.send_event(EventUpdateCostfieldsRegion::new(
+			CostRegion::Rectangle {
+				min: (15, 0),
+				max: (15, 29),
+			},
+			255,
+		));
+		world.run_system_once(process_costfields_updates);
+
+		let mut q = world.query::<(&PortalGraph, &SectorPortals, &SectorCostFields)>();
+		let (graph, portals, costs) = q.single(&world);
+		let source = (SectorID::new(0, 0), FieldCell::new(0, 0));
+		let target = (SectorID::new(2, 0), FieldCell::new(5, 5));
+		assert!(graph
+			.find_best_path(source, target, portals, costs)
+			.is_none());
+		let mut cleaned: Vec<SectorID> = world
+			.resource_mut::<Events<EventCleanCaches>>()
+			.drain()
+			.map(|e| e.get_sector_id())
+			.collect();
+		cleaned.sort();
+		let actual = vec![
+			SectorID::new(1, 0),
+			SectorID::new(1, 1),
+			SectorID::new(1, 2),
+		];
+		assert_eq!(actual, cleaned);
+	}
+	#[test]
+	#[cfg(feature = "2d")]
+	fn world_rectangle_xy_spans_sectors() {
+		let map_dimensions = MapDimensions::new(1920, 1920, 640, 16.0);
+		// 2x2 sprites straddling the boundary between sectors (0, 1) and (1, 1) at x = -320
+		let event = EventUpdateCostfieldsWorld::new_xy(
+			WorldCostShape::Rectangle {
+				min: Vec2::new(-384.0, -64.0),
+				max: Vec2::new(-256.0, 64.0),
+			},
+			255,
+		);
+		let changes = event
+			.get_region(&map_dimensions)
+			.get_cell_values(event.get_cost_value(), &map_dimensions);
+		let result = to_grid_cells(&changes, &map_dimensions);
+		let actual = vec![(9, 14), (9, 15), (10, 14), (10, 15)];
+		assert_eq!(actual, result);
+		let mut sectors: Vec<SectorID> = changes.iter().map(|(s, _, _)| *s).collect();
+		sectors.sort();
+		sectors.dedup();
+		assert_eq!(vec![SectorID::new(0, 1), SectorID::new(1, 1)], sectors);
+	}
+	#[test]
+	#[cfg(feature = "3d")]
+	fn world_circle_xyz_spans_sectors() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		// centred on the corner shared by sectors (0, 0), (1, 0), (0, 1) and (1, 1)
+		let event = EventUpdateCostfieldsWorld::new_xyz(
+			WorldCostShape::Circle {
+				centre: Vec2::new(-5.0, -5.0),
+				radius: 1.0,
+			},
+			255,
+		);
+		let changes = event
+			.get_region(&map_dimensions)
+			.get_cell_values(event.get_cost_value(), &map_dimensions);
+		let mut result: Vec<(SectorID, FieldCell)> =
+			changes.iter().map(|(s, c, _)| (*s, *c)).collect();
+		result.sort();
+		let actual = vec![
+			(SectorID::new(0, 0), FieldCell::new(9, 9)),
+			(SectorID::new(0, 1), FieldCell::new(9, 0)),
+			(SectorID::new(1, 0), FieldCell::new(0, 9)),
+			(SectorID::new(1, 1), FieldCell::new(0, 0)),
+		];
+		assert_eq!(actual, result);
+	}
+	#[test]
+	#[cfg(feature = "3d")]
+	fn world_update_applied() {
+		let mut world = setup_world();
+		world.send_event(EventUpdateCostfieldsWorld::new_xyz(
+			WorldCostShape::Polygon(vec![
+				Vec2::new(-6.0, -6.0),
+				Vec2::new(-4.0, -6.0),
+				Vec2::new(-4.0, -4.0),
+				Vec2::new(-6.0, -4.0),
+			]),
+			40,
+		));
+		world.run_system_once(process_costfields_updates);
+
+		let mut q = world.query::<&SectorCostFields>();
+		let costs = q.single(&world);
+		for (sector, cell) in [
+			(SectorID::new(0, 0), FieldCell::new(9, 9)),
+			(SectorID::new(0, 1), FieldCell::new(9, 0)),
+			(SectorID::new(1, 0), FieldCell::new(0, 9)),
+			(SectorID::new(1, 1), FieldCell::new(0, 0)),
+		] {
+			let result = costs
+				.get_baseline()
+				.get(&sector)
+				.unwrap()
+				.get_field_cell_value(cell);
+			assert_eq!(40, result);
+		}
+		let cleaned = world.resource::<Events<EventCleanCaches>>().len();
+		assert_eq!(4, cleaned);
+	}
+	#[test]
+	fn modifier_added_and_removed() {
+		let mut world = setup_world();
+		let wall = CostRegion::Rectangle {
+			min: (15, 0),
+			max: (15, 29),
+		};
+		// a permanent cost change beneath the modifier
+		let sector = SectorID::new(1, 1);
+		let cell = FieldCell::new(5, 5);
+		world.send_event(EventUpdateCostfieldsCell::new(cell, sector, 9));
+		run_updates(&mut world);
+		let event = EventAddCostModifier::new(wall, CostModifier::Set(255), None);
+		let handle = event.get_handle();
+		world.send_event(event);
+		run_updates(&mut world);
+		let source = (SectorID::new(0, 0), FieldCell::new(0, 0));
+		let target = (SectorID::new(2, 0), FieldCell::new(5, 5));
+		let mut q = world.query::<(&PortalGraph, &SectorPortals, &SectorCostFields)>();
+		let (graph, portals, costs) = q.single(&world);
+		assert!(graph
+			.find_best_path(source, target, portals, costs)
+			.is_none());
+
+		world.send_event(EventRemoveCostModifier(handle));
+		run_updates(&mut world);
+		let (graph, portals, costs) = q.single(&world);
+		assert!(graph
+			.find_best_path(source, target, portals, costs)
+			.is_some());
+		let field = costs.get_effective().get(&sector).unwrap();
+		assert_eq!(9, field.get_field_cell_value(cell));
+		assert_eq!(1, field.get_field_cell_value(FieldCell::new(5, 4)));
+	}
+	#[test]
+	fn updates_on_layer() {
+		let bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		let map_dimensions = *bundle.get_map_dimensions();
+		let mut world =
+			setup_world_with_bundle(bundle.with_layer(SectorCostFields::new(&map_dimensions)));
+		let region = CostRegion::Rectangle {
+			min: (0, 0),
+			max: (1, 1),
+		};
+		world.send_event(EventUpdateCostfieldsRegion::new(region, 255).with_layer(1));
+		let cells = CostRegion::Cells(vec![(5, 5)]);
+		world.send_event(
+			EventAddCostModifier::new(cells, CostModifier::Set(50), None).with_layer(1),
+		);
+		run_updates(&mut world);
+		let mut q = world.query::<&SectorCostFields>();
+		let costs = q.single(&world).get_effective();
+		let ground = costs.get(&SectorID::new(0, 0)).unwrap();
+		let upper = costs.get(&SectorID::new_layered(0, 0, 1)).unwrap();
+		for cell in [FieldCell::new(0, 0), FieldCell::new(1, 1)] {
+			assert_eq!(1, ground.get_field_cell_value(cell));
+			assert_eq!(255, upper.get_field_cell_value(cell));
+		}
+		assert_eq!(1, ground.get_field_cell_value(FieldCell::new(5, 5)));
+		assert_eq!(50, upper.get_field_cell_value(FieldCell::new(5, 5)));
+	}
+	/// Snapshot each cached [FlowField] along with when it was generated
+	fn get_flow_fields(app: &mut App) -> Vec<(SectorID, Duration, [[u8; 10]; 10])> {
+		let mut q = app.world_mut().query::<&FlowFieldCache>();
+		q.single(app.world())
+			.get()
+			.iter()
+			.map(|(m, f)| (m.get_sector_id(), m.get_time_generated(), *f.get()))
+			.collect()
+	}
+	/// Get each route in the [RouteCache]
+	fn get_routes(app: &mut App) -> Vec<Vec<(SectorID, FieldCell)>> {
+		let mut q = app.world_mut().query::<&RouteCache>();
+		q.single(app.world()).get().values().cloned().collect()
+	}
+	#[test]
+	fn clean_cache_keeps_unaffected_fields() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, FlowFieldTilesPlugin));
+		app.world_mut()
+			.spawn(FlowFieldTilesBundle::new(30, 30, 10, 0.5));
+		// box in the grid cell (17, 7) of sector (1, 0)
+		let mut ring = Vec::new();
+		for column in 16..19 {
+			for row in 6..9 {
+				if (column, row) != (17, 7) {
+					ring.push((column, row));
+				}
+			}
+		}
+		app.world_mut().send_event(EventUpdateCostfieldsRegion::new(
+			CostRegion::Cells(ring),
+			255,
+		));
+		app.update();
+		app.world_mut().send_event(EventPathRequest::new(
+			SectorID::new(0, 0),
+			FieldCell::new(4, 4),
+			SectorID::new(2, 0),
+			FieldCell::new(5, 5),
+		));
+		for _ in 0..5 {
+			app.update();
+		}
+		let original = get_flow_fields(&mut app);
+		assert_eq!(3, original.len());
+		let routes = get_routes(&mut app);
+		assert_eq!(1, routes.len());
+		// a change within the box cannot be reached so nothing is rebuilt
+		app.world_mut().send_event(EventUpdateCostfieldsCell::new(
+			FieldCell::new(7, 7),
+			SectorID::new(1, 0),
+			50,
+		));
+		app.update();
+		assert_eq!(original, get_flow_fields(&mut app));
+		assert_eq!(routes, get_routes(&mut app));
+		// an expensive cell on the route rebuilds the fields in place, the field of the goal sector is untouched
+		app.world_mut().send_event(EventUpdateCostfieldsCell::new(
+			FieldCell::new(2, 4),
+			SectorID::new(1, 0),
+			200,
+		));
+		app.update();
+		let result = get_flow_fields(&mut app);
+		for (before, after) in original.iter().zip(result.iter()) {
+			assert_eq!(before.0, after.0);
+			assert_eq!(before.1, after.1);
+			if before.0 == SectorID::new(1, 0) {
+				assert_ne!(before.2, after.2);
+			} else if before.0 == SectorID::new(2, 0) {
+				assert_eq!(before.2, after.2);
 			}
 		}
+		assert_eq!(routes, get_routes(&mut app));
+		// walling off the middle of the map makes the route invalid
+		app.world_mut().send_event(EventUpdateCostfieldsRegion::new(
+			CostRegion::Rectangle {
+				min: (15, 0),
+				max: (15, 29),
+			},
+			255,
+		));
+		app.update();
+		assert!(!get_routes(&mut app).contains(&routes[0]));
+	}
+	#[test]
+	fn clean_events_report_lowered_costs() {
+		let mut world = setup_world();
+		let get_lowered = |world: &mut World| -> Vec<(SectorID, bool)> {
+			let mut events = world.resource_mut::<Events<EventCleanCaches>>();
+			let lowered = events
+				.drain()
+				.map(|e| (e.get_sector_id(), e.has_lowered_costs()))
+				.collect();
+			lowered
+		};
+		world.send_event(EventUpdateCostfieldsCell::new(
+			FieldCell::new(4, 4),
+			SectorID::new(1, 1),
+			50,
+		));
+		run_updates(&mut world);
+		assert_eq!(vec![(SectorID::new(1, 1), false)], get_lowered(&mut world));
+		world.send_event(EventUpdateCostfieldsCell::new(
+			FieldCell::new(4, 4),
+			SectorID::new(1, 1),
+			7,
+		));
+		run_updates(&mut world);
+		assert_eq!(vec![(SectorID::new(1, 1), true)], get_lowered(&mut world));
+	}
+	#[test]
+	fn route_cost() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		sector_cost_fields.set_field_cell_value(
+			SectorID::new(1, 0),
+			5,
+			FieldCell::new(0, 4),
+			&map_dimensions,
+		);
+		let portal_graph = PortalGraph::default();
+		let metadata = RouteMetadata::new(
+			SectorID::new(0, 0),
+			FieldCell::new(8, 5),
+			SectorID::new(1, 0),
+			FieldCell::new(1, 5),
+			Duration::default(),
+		);
+		let straight = vec![
+			(SectorID::new(0, 0), FieldCell::new(9, 5)),
+			(SectorID::new(1, 0), FieldCell::new(1, 5)),
+		];
+		let result = get_route_cost(&metadata, &straight, &portal_graph, &sector_cost_fields);
+		assert_eq!(Some(30), result);
+		// crossing the portal onto the expensive cell
+		let expensive = vec![
+			(SectorID::new(0, 0), FieldCell::new(9, 4)),
+			(SectorID::new(1, 0), FieldCell::new(1, 5)),
+		];
+		let result = get_route_cost(&metadata, &expensive, &portal_graph, &sector_cost_fields);
+		assert_eq!(Some(14 + 50 + 14), result);
+		// a route that doesn't start in the source sector can't be followed
+		let result = get_route_cost(
+			&metadata,
+			&straight[1..],
+			&portal_graph,
+			&sector_cost_fields,
+		);
+		assert!(result.is_none());
+	}
+	#[test]
+	fn route_could_be_cheaper() {
+		let metadata = RouteMetadata::new(
+			SectorID::new(0, 0),
+			FieldCell::new(4, 4),
+			SectorID::new(2, 0),
+			FieldCell::new(5, 5),
+			Duration::default(),
+		);
+		let route = vec![
+			(SectorID::new(0, 0), FieldCell::new(4, 9)),
+			(SectorID::new(0, 1), FieldCell::new(4, 9)),
+			(SectorID::new(0, 2), FieldCell::new(9, 5)),
+			(SectorID::new(1, 2), FieldCell::new(9, 5)),
+			(SectorID::new(2, 2), FieldCell::new(5, 0)),
+			(SectorID::new(2, 1), FieldCell::new(5, 0)),
+			(SectorID::new(2, 0), FieldCell::new(5, 5)),
+		];
+		let cost = 600;
+		// along the route
+		let cheaper = BTreeSet::from([SectorID::new(1, 2)]);
+		assert!(could_route_be_cheaper(
+			&metadata, &route, cost, &cheaper, false
+		));
+		// a shortcut through the middle of the map
+		let cheaper = BTreeSet::from([SectorID::new(1, 0)]);
+		assert!(could_route_be_cheaper(
+			&metadata, &route, cost, &cheaper, false
+		));
+		// too far away to shorten the route
+		let cheaper = BTreeSet::from([SectorID::new(1, 6)]);
+		assert!(!could_route_be_cheaper(
+			&metadata, &route, cost, &cheaper, false
+		));
+		// another layer may be reached by a connection
+		let cheaper = BTreeSet::from([SectorID::new(1, 6).with_layer(1)]);
+		assert!(could_route_be_cheaper(
+			&metadata, &route, cost, &cheaper, false
+		));
+		let cheaper = BTreeSet::from([SectorID::new(1, 6)]);
+		assert!(could_route_be_cheaper(
+			&metadata, &route, cost, &cheaper, true
+		));
+	}
+	#[test]
+	fn clean_cache_replaces_route_when_cheaper_opens() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, FlowFieldTilesPlugin));
+		app.world_mut()
+			.spawn(FlowFieldTilesBundle::new(30, 30, 10, 0.5));
+		// wall off the middle of the map apart from the bottom row
+		app.world_mut().send_event(EventUpdateCostfieldsRegion::new(
+			CostRegion::Rectangle {
+				min: (15, 0),
+				max: (15, 28),
+			},
+			255,
+		));
+		app.update();
+		app.world_mut().send_event(EventPathRequest::new(
+			SectorID::new(0, 0),
+			FieldCell::new(4, 4),
+			SectorID::new(2, 0),
+			FieldCell::new(5, 5),
+		));
+		for _ in 0..5 {
+			app.update();
+		}
+		let routes = get_routes(&mut app);
+		assert_eq!(1, routes.len());
+		// the detour runs through the bottom row of sectors
+		assert!(routes[0].iter().any(|(s, _)| s.get_row() == 2));
+		// opening a gap at the top of the wall doesn't invalidate the detour but gives a shorter route
+		app.world_mut().send_event(EventUpdateCostfieldsCell::new(
+			FieldCell::new(5, 4),
+			SectorID::new(1, 0),
+			1,
+		));
+		for _ in 0..5 {
+			app.update();
+		}
+		let result = get_routes(&mut app);
+		assert_eq!(1, result.len());
+		assert!(result[0].iter().all(|(s, _)| s.get_row() == 0));
 	}
 }
@@ -0,0 +1,171 @@
+//! Logic relating to the optional [SectorDensityFields] overlay
+//!
+
+use std::collections::BTreeSet;
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Marks an actor whose position should contribute to the [SectorDensityFields] of the map
+#[derive(Component, Default, Debug, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct DensityContributor;
+
+/// Density overlay of a map along with the caches and data needed to rebuild its fields
+type DensityQueryData = (
+	&'static mut SectorDensityFields,
+	&'static MapDimensions,
+	&'static RouteCache,
+	&'static mut FlowFieldCache,
+	&'static SectorPortals,
+	&'static SectorCostFields,
+);
+
+/// Refresh the [SectorDensityFields] from the positions of [DensityContributor]s at the rate defined by its refresh interval. Positions are read in the plane of the [SteeringPlane] resource if it exists, otherwise the default plane is used. A contributor which is also a [FlowFieldAgent] adds density to the sector layer of the agent, any other contributor to layer `0`.
+///
+/// Once the density of a sector has changed by more than the rebuild threshold each cached [FlowField] of a route crossing it is rebuilt in place, so actors already following a route react to crowding
+#[cfg(not(tarpaulin_include))]
+pub fn update_density_fields(
+	mut density_q: Query<DensityQueryData>,
+	actors: Query<(&Transform, Option<&FlowFieldAgent>), With<DensityContributor>>,
+	plane: Option<Res<SteeringPlane>>,
+	time: Res<Time>,
+) {
+	let plane = plane.map(|p| *p).unwrap_or_default();
+	for (
+		mut density,
+		map_dimensions,
+		route_cache,
+		mut flow_cache,
+		sector_portals,
+		sector_cost_fields,
+	) in &mut density_q
+	{
+		if !density.tick(time.delta_seconds()) {
+			continue;
+		}
+		let occupied: Vec<(SectorID, FieldCell)> = actors
+			.iter()
+			.filter_map(|(tform, agent)| {
+				let layer = agent.map_or(0, |agent| agent.get_layer());
+				plane.get_sector_and_field_cell_on_layer(map_dimensions, tform.translation, layer)
+			})
+			.collect();
+		density.refresh(&occupied);
+		let changed = density.take_changed_sectors();
+		if changed.is_empty() {
+			continue;
+		}
+		// flow fields are shared between routes, only rebuild each one once
+		let mut rebuilt = BTreeSet::new();
+		for route in route_cache.get().values() {
+			for (i, (sector_id, goal)) in route.iter().enumerate() {
+				if !changed.contains(sector_id) || !rebuilt.insert((*sector_id, *goal)) {
+					continue;
+				}
+				let Some((flow_field, _)) = rebuild_route_flow_field(
+					route,
+					i,
+					sector_portals,
+					sector_cost_fields,
+					map_dimensions,
+					Some(&density),
+				) else {
+					continue;
+				};
+				if let Some(field) = flow_cache.get_field_mut(*sector_id, *goal) {
+					*field = flow_field;
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	#[test]
+	fn crowding_rebuilds_cached_fields() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, FlowFieldTilesPlugin));
+		let bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		let map_dimensions = *bundle.get_map_dimensions();
+		let mut density = SectorDensityFields::new(bundle.get_sector_cost_fields());
+		density.set_refresh_interval(0.0);
+		density.set_decay(0);
+		app.world_mut().spawn((bundle, density));
+		app.world_mut().send_event(EventPathRequest::new(
+			SectorID::new(0, 0),
+			FieldCell::new(4, 4),
+			SectorID::new(2, 0),
+			FieldCell::new(5, 5),
+		));
+		for _ in 0..5 {
+			app.update();
+		}
+		let get_field = |app: &mut App| {
+			let mut q = app.world_mut().query::<&FlowFieldCache>();
+			q.single(app.world())
+				.get()
+				.iter()
+				.find(|(m, _)| m.get_sector_id() == SectorID::new(1, 0))
+				.map(|(_, f)| *f.get())
+				.unwrap()
+		};
+		let original = get_field(&mut app);
+		// crowd a column of the middle sector
+		let plane = SteeringPlane::default();
+		for row in 2..8 {
+			let position = plane
+				.get_position(&map_dimensions, SectorID::new(1, 0), FieldCell::new(5, row))
+				.unwrap();
+			app.world_mut()
+				.spawn((Transform::from_translation(position), DensityContributor));
+		}
+		for _ in 0..10 {
+			app.update();
+		}
+		assert_ne!(original, get_field(&mut app));
+	}
+	#[test]
+	fn agents_crowd_their_layer() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, FlowFieldTilesPlugin));
+		let bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		let map_dimensions = *bundle.get_map_dimensions();
+		let bundle = bundle.with_layer(SectorCostFields::new(&map_dimensions));
+		let mut density = SectorDensityFields::new(bundle.get_sector_cost_fields());
+		density.set_refresh_interval(0.0);
+		density.set_decay(0);
+		app.world_mut().spawn((bundle, density));
+		let plane = SteeringPlane::default();
+		let sector = SectorID::new(1, 1);
+		let upper_cell = FieldCell::new(2, 2);
+		let ground_cell = FieldCell::new(7, 7);
+		// an agent on the floor above and an actor on the ground
+		let mut agent = FlowFieldAgent::new(2.0, 0.5);
+		agent.set_layer(1);
+		let position = plane
+			.get_position(&map_dimensions, sector, upper_cell)
+			.unwrap();
+		app.world_mut().spawn((
+			Transform::from_translation(position),
+			DensityContributor,
+			agent,
+		));
+		let position = plane
+			.get_position(&map_dimensions, sector, ground_cell)
+			.unwrap();
+		app.world_mut()
+			.spawn((Transform::from_translation(position), DensityContributor));
+		app.update();
+		let mut q = app.world_mut().query::<&SectorDensityFields>();
+		let density = q.single(app.world()).get();
+		let ground = density.get(&sector).unwrap();
+		let upper = density.get(&sector.with_layer(1)).unwrap();
+		assert_eq!(0, ground.get_field_cell_value(upper_cell));
+		assert_ne!(0, upper.get_field_cell_value(upper_cell));
+		assert_ne!(0, ground.get_field_cell_value(ground_cell));
+		assert_eq!(0, upper.get_field_cell_value(ground_cell));
+	}
+}
@@ -2,7 +2,7 @@
 //!
 
 use crate::prelude::*;
-use bevy::prelude::*;
+use bevy::{ecs::entity::Entities, prelude::*};
 
 /// A request to queue up an attempt at generating a Route and a series of
 /// [FlowField]s describing a path from the source to target
@@ -16,9 +16,16 @@ pub struct EventPathRequest {
 	target_sector: SectorID,
 	/// The field cell in the target sector to find a path to
 	target_goal: FieldCell,
+	/// Stable identifier the route can be looked up by
+	handle: RouteHandle,
+	/// Urgency of the request, higher priorities are serviced first
+	priority: u8,
+	/// The entity making the request, used to cancel every request it has made
+	requester: Option<Entity>,
 }
 
 impl EventPathRequest {
+	/// Create a new request, a unique [RouteHandle] is assigned to it
 	pub fn new(
 		source_sector: SectorID,
 		source_field_cell: FieldCell,
@@ -30,11 +37,78 @@ impl EventPathRequest {
 			source_field_cell,
 			target_sector,
 			target_goal,
+			handle: RouteHandle::new(),
+			priority: 0,
+			requester: None,
 		}
 	}
+	/// Use an existing [RouteHandle] for the request, i.e when regenerating a route that actors already refer to
+	pub fn with_handle(mut self, handle: RouteHandle) -> Self {
+		self.handle = handle;
+		self
+	}
+	/// Get the [RouteHandle] the route can be looked up by with [RouteCache::get_route_by_handle]
+	pub fn get_handle(&self) -> RouteHandle {
+		self.handle
+	}
+	/// Set the priority of the request, i.e player commanded units above ambient AI. Requests are serviced highest priority first and then oldest first, the default is `0`
+	pub fn with_priority(mut self, priority: u8) -> Self {
+		self.priority = priority;
+		self
+	}
+	/// Get the priority of the request
+	pub fn get_priority(&self) -> u8 {
+		self.priority
+	}
+	/// Record the entity making the request so that it can be cancelled with [EventCancelPathRequest::Requester]
+	pub fn with_requester(mut self, requester: Entity) -> Self {
+		self.requester = Some(requester);
+		self
+	}
+	/// Get the entity making the request
+	pub fn get_requester(&self) -> Option<Entity> {
+		self.requester
+	}
+	/// Set the layers the source and target sit on, by default both are on the layer of the sectors the request was created with. Routes between layers cross the [LayerConnection]s of the [PortalGraph]
+	pub fn with_layers(mut self, source_layer: u32, target_layer: u32) -> Self {
+		self.source_sector = self.source_sector.with_layer(source_layer);
+		self.target_sector = self.target_sector.with_layer(target_layer);
+		self
+	}
+}
+
+/// Withdraw [EventPathRequest]s which are no longer needed, i.e when a player reissues an order or an actor has arrived. Once no other [RouteHandle] refers to a route it is removed from the queues of the [RouteCache] and [FlowFieldCache], or if it has already been generated the route is removed along with any [FlowField]s no other route uses
+#[derive(Event, Debug, Clone, Copy)]
+pub enum EventCancelPathRequest {
+	/// Cancel the request with this handle
+	Handle(RouteHandle),
+	/// Cancel every request made by this entity
+	Requester(Entity),
+}
+
+/// Limits how much pathfinding work is done each frame. Requests beyond the budget wait in the queues of the [RouteCache] and [FlowFieldCache] so that under heavy load low priority requests are delayed rather than dropped
+#[derive(Resource, Debug, Clone, Copy, Reflect)]
+pub struct PathRequestBudget {
+	/// Number of new high-level routes calculated per frame, requests for routes which already exist are answered straight away and don't count towards it
+	pub routes_per_frame: usize,
+	/// Number of queued routes that have their [IntegrationField]s, and separately their [FlowField]s, built per frame
+	pub fields_per_frame: usize,
 }
 
-/// Process [EventPathRequest] and generate Routes to go into the [RouteCache] queue
+impl Default for PathRequestBudget {
+	fn default() -> Self {
+		PathRequestBudget {
+			routes_per_frame: 1,
+			fields_per_frame: 1,
+		}
+	}
+}
+
+/// Emitted when the route a [RouteHandle] refers to has been generated, regenerated after a [CostField] change or could not be generated. The current route (if any) can be read with [RouteCache::get_route_by_handle]. When the route could not be generated the handle, and its requester, are released once notified so [RouteCache::get_handle_metadata] returns [None] for it
+#[derive(Event, Debug)]
+pub struct EventRouteChanged(pub RouteHandle);
+
+/// Process [EventPathRequest] and generate Routes to go into the [RouteCache] queue. Requests are serviced highest priority first and then oldest first within the [PathRequestBudget]
 #[cfg(not(tarpaulin_include))]
 pub fn event_insert_route_queue(
 	mut events: EventReader<EventPathRequest>,
@@ -45,76 +119,146 @@ pub fn event_insert_route_queue(
 		&SectorCostFields,
 	)>,
 	time: Res<Time>,
+	budget: Res<PathRequestBudget>,
+	mut event_route_changed: EventWriter<EventRouteChanged>,
 ) {
-	// several actors may send requests at once, instead of stepping through the events one at time
-	// blitz thorugh duplicates so only a fresh request gets processed each tick - this is critical to perf
-	let mut is_duplicate = true;
-	while is_duplicate {
-		if let Some(event) = events.read().next() {
-			for (mut cache, graph, sector_portals, sector_cost_fields_scaled) in cache_q.iter_mut()
-			{
-				// only run if the cache doesn't contain the route already
-				let rm = RouteMetadata::new(
-					event.source_sector,
-					event.source_field_cell,
-					event.target_sector,
-					event.target_goal,
-					time.elapsed(),
-				);
-				if !cache.get().contains_key(&rm) {
-					is_duplicate = false;
-					if let Some(mut path) = graph.find_best_path(
-						(event.source_sector, event.source_field_cell),
-						(event.target_sector, event.target_goal),
-						sector_portals,
-						sector_cost_fields_scaled,
-					) {
-						debug!("Portal path found");
-						if !path.is_empty() {
-							filter_path(&mut path, event.target_goal);
-						}
-						cache.add_to_queue(
-							event.source_sector,
-							event.source_field_cell,
-							event.target_sector,
-							event.target_goal,
-							time.elapsed(),
-							path,
-						);
-					} else {
-						// a portal based route could not be found or the actor
-						// is within the same sector as the goal
-						debug!(
-						"No portal path found, either local sector movement or just doesn't exist"
-					);
-						if let Some(cost_field) = sector_cost_fields_scaled
-							.get_scaled()
-							.get(&event.target_sector)
-						{
-							let vis = cost_field
-								.is_cell_pair_reachable(event.source_field_cell, event.target_goal);
-							// if the two cells are reachable from within the same sector
-							// then there is a local route
-							if vis {
-								cache.add_to_queue(
-									event.source_sector,
-									event.source_field_cell,
-									event.target_sector,
-									event.target_goal,
-									time.elapsed(),
-									vec![(event.target_sector, event.target_goal)],
-								);
-							}
-						}
-					}
+	let events: Vec<&EventPathRequest> = events.read().collect();
+	for (mut cache, graph, sector_portals, sector_cost_fields_scaled) in cache_q.iter_mut() {
+		for event in events.iter() {
+			let rm = RouteMetadata::new(
+				event.source_sector,
+				event.source_field_cell,
+				event.target_sector,
+				event.target_goal,
+				time.elapsed(),
+			)
+			.with_priority(event.priority);
+			if cache.get().contains_key(&rm) {
+				// the route already exists, let the requester know it can be used
+				cache.insert_handle(event.handle, rm);
+				event_route_changed.send(EventRouteChanged(event.handle));
+			} else {
+				cache.add_request(event.handle, rm);
+			}
+			if let Some(requester) = event.requester {
+				cache.insert_requester(event.handle, requester);
+			}
+		}
+		// several actors may send requests at once, only a few fresh requests get processed each tick - this is critical to perf
+		for _ in 0..budget.routes_per_frame {
+			let Some(request) = cache.pop_next_request() else {
+				break;
+			};
+			let rm = RouteMetadata::new(
+				request.get_source_sector(),
+				request.get_source_field_cell(),
+				request.get_target_sector(),
+				request.get_target_goal(),
+				time.elapsed(),
+			)
+			.with_priority(request.get_priority());
+			if let Some(path) = find_route(&rm, graph, sector_portals, sector_cost_fields_scaled) {
+				cache.add_to_queue_with_metadata(rm, path);
+			} else {
+				// notify every actor waiting on the route that it doesn't exist and stop tracking their handles
+				for handle in cache.get_handles(&rm) {
+					event_route_changed.send(EventRouteChanged(handle));
 				}
+				cache.remove_handles(&rm);
 			}
-		} else {
-			is_duplicate = false;
 		}
 	}
 }
 
+/// Process [EventCancelPathRequest]. Requests are cancelled before new ones are read so an entity can cancel its previous requests and make a new one in the same frame
+#[cfg(not(tarpaulin_include))]
+pub fn event_cancel_path_requests(
+	mut events: EventReader<EventCancelPathRequest>,
+	mut cache_q: Query<(&mut RouteCache, &mut FlowFieldCache)>,
+) {
+	for event in events.read() {
+		for (mut route_cache, mut flow_cache) in &mut cache_q {
+			let handles = match event {
+				EventCancelPathRequest::Handle(handle) => vec![*handle],
+				EventCancelPathRequest::Requester(requester) => {
+					route_cache.get_requester_handles(*requester)
+				}
+			};
+			for handle in handles {
+				release_route(&mut route_cache, &mut flow_cache, handle);
+			}
+		}
+	}
+}
+
+/// Cancel the requests of entities which have been despawned so that the routes and [FlowField]s they were using are freed
+#[cfg(not(tarpaulin_include))]
+pub fn release_despawned_requesters(
+	mut cache_q: Query<(&mut RouteCache, &mut FlowFieldCache)>,
+	entities: &Entities,
+) {
+	for (mut route_cache, mut flow_cache) in &mut cache_q {
+		let despawned: Vec<RouteHandle> = route_cache
+			.get_requesters()
+			.iter()
+			.filter(|(_, requester)| !entities.contains(**requester))
+			.map(|(handle, _)| *handle)
+			.collect();
+		for handle in despawned {
+			release_route(&mut route_cache, &mut flow_cache, handle);
+		}
+	}
+}
+
+/// Cancel the request of `handle`. If nothing else refers to its route then the route is dropped from the queues and caches along with any [FlowField]s that only it used
+fn release_route(
+	route_cache: &mut RouteCache,
+	flow_cache: &mut FlowFieldCache,
+	handle: RouteHandle,
+) {
+	let Some(metadata) = route_cache.cancel_request(handle) else {
+		return;
+	};
+	flow_cache.remove_queue_item(metadata);
+	route_cache.remove_route(metadata);
+	flow_cache.remove_route_user(metadata);
+}
+
+/// Find the high-level route of a request, a series of sectors and the [FieldCell]s an actor leaves them by ending with the target goal. Returns [None] if the target cannot be reached
+pub fn find_route(
+	route_metadata: &RouteMetadata,
+	graph: &PortalGraph,
+	sector_portals: &SectorPortals,
+	sector_cost_fields: &SectorCostFields,
+) -> Option<Vec<(SectorID, FieldCell)>> {
+	let rm = route_metadata;
+	if let Some(mut path) = graph.find_best_path(
+		(rm.get_source_sector(), rm.get_source_field_cell()),
+		(rm.get_target_sector(), rm.get_target_goal()),
+		sector_portals,
+		sector_cost_fields,
+	) {
+		debug!("Portal path found");
+		if !path.is_empty() {
+			filter_path(&mut path, rm.get_target_goal());
+		}
+		return Some(path);
+	}
+	// a portal based route could not be found or the actor
+	// is within the same sector as the goal
+	debug!("No portal path found, either local sector movement or just doesn't exist");
+	let is_same_layer = rm.get_source_sector().get_layer() == rm.get_target_sector().get_layer();
+	let cost_field = sector_cost_fields
+		.get_scaled()
+		.get(&rm.get_target_sector())
+		.filter(|_| is_same_layer)?;
+	// if the two cells are reachable from within the same sector
+	// then there is a local route
+	cost_field
+		.is_cell_pair_reachable(rm.get_source_field_cell(), rm.get_target_goal())
+		.then(|| vec![(rm.get_target_sector(), rm.get_target_goal())])
+}
+
 /// Generated portal-portal routes contain two elements for each sector, one
 /// for an actors entry and when for an actors exit, we only need to know
 /// about the elements which an actor would use to exit the sector so we filter
@@ -142,9 +286,15 @@ pub fn filter_path(path: &mut Vec<(SectorID, FieldCell)>, target_goal: FieldCell
 /// which an actor can use as a high-level pathfinding route while publishing a
 /// new item into the [FlowFieldCache] queue
 #[cfg(not(tarpaulin_include))]
-pub fn process_route_queue(mut cache_q: Query<(&mut RouteCache, &mut FlowFieldCache)>) {
+pub fn process_route_queue(
+	mut cache_q: Query<(&mut RouteCache, &mut FlowFieldCache)>,
+	mut event_route_changed: EventWriter<EventRouteChanged>,
+) {
 	for (mut r_cache, mut f_cache) in &mut cache_q {
 		while let Some((metadata, path_to_goal)) = r_cache.get_queue_mut().pop_first() {
+			for handle in r_cache.get_handles(&metadata) {
+				event_route_changed.send(EventRouteChanged(handle));
+			}
 			let mut path_from_goal = path_to_goal.clone();
 			path_from_goal.reverse();
 			// store a route from actor to goal so that can actor can use it for high-level pathfinding while the more accurate flowfield representation gets built in the background
@@ -155,8 +305,9 @@ pub fn process_route_queue(mut cache_q: Query<(&mut RouteCache, &mut FlowFieldCa
 	}
 }
 
-/// Inspect the [FlowFieldCache] queue and if the [IntegrationField]s of the
-/// first entry haven't been created then calculate them
+/// Inspect the [FlowFieldCache] queue and calculate the [IntegrationField]s
+/// of the entries that haven't been created yet, highest priority first and
+/// then oldest first within the [PathRequestBudget]
 #[cfg(not(tarpaulin_include))]
 pub fn create_queued_integration_fields(
 	mut cache_q: Query<(
@@ -164,22 +315,36 @@ pub fn create_queued_integration_fields(
 		&SectorPortals,
 		&SectorCostFields,
 		&MapDimensions,
+		Option<&SectorDensityFields>,
 	)>,
+	budget: Res<PathRequestBudget>,
 ) {
-	for (mut f_cache, sector_portals, sector_cost_fields_scaled, map_dimensions) in &mut cache_q {
-		if let Some(mut entry) = f_cache.get_queue_mut().first_entry() {
-			if entry.get().is_pending() {
-				let sectors_expanded_goals = exapnd_sector_portals(
-					entry.get().get_path(),
-					sector_portals,
-					sector_cost_fields_scaled,
-					map_dimensions,
-				);
-				// build the integration fields
-				let sector_int_fields =
-					build_integration_fields(&sectors_expanded_goals, sector_cost_fields_scaled);
-				entry.get_mut().add_integration_fields(sector_int_fields);
-			}
+	for (mut f_cache, sector_portals, sector_cost_fields_scaled, map_dimensions, density) in
+		&mut cache_q
+	{
+		let pending: Vec<RouteMetadata> = f_cache
+			.get_queue_by_priority()
+			.into_iter()
+			.filter(|metadata| f_cache.get_queue()[metadata].is_pending())
+			.take(budget.fields_per_frame)
+			.collect();
+		for metadata in pending.iter() {
+			let Some(int_builder) = f_cache.get_queue_mut().get_mut(metadata) else {
+				continue;
+			};
+			let sectors_expanded_goals = exapnd_sector_portals(
+				int_builder.get_path(),
+				sector_portals,
+				sector_cost_fields_scaled,
+				map_dimensions,
+			);
+			// build the integration fields
+			let sector_int_fields = build_integration_fields(
+				&sectors_expanded_goals,
+				sector_cost_fields_scaled,
+				density,
+			);
+			int_builder.add_integration_fields(sector_int_fields);
 		}
 	}
 }
@@ -221,11 +386,13 @@ fn exapnd_sector_portals(
 }
 
 /// Iterate over each sector with the expanded portal goals and calculate the
-/// [IntegrationField] for it
+/// [IntegrationField] for it, including the dynamic cost of the
+/// [SectorDensityFields] if the map has them
 #[cfg(not(tarpaulin_include))]
 fn build_integration_fields(
 	sectors_expanded_goals: &[(SectorID, Vec<FieldCell>)],
 	sector_cost_fields_scaled: &SectorCostFields,
+	density: Option<&SectorDensityFields>,
 ) -> Vec<(SectorID, Vec<FieldCell>, IntegrationField)> {
 	let mut sector_int_fields = Vec::new();
 	for (sector_id, goals) in sectors_expanded_goals.iter() {
@@ -234,77 +401,160 @@ fn build_integration_fields(
 			.get_scaled()
 			.get(sector_id)
 			.unwrap();
-		int_field.calculate_field(goals, cost_field);
+		match density.and_then(|d| d.get().get(sector_id)) {
+			Some(density_field) => {
+				int_field.calculate_field_with_density(goals, cost_field, density_field)
+			}
+			None => int_field.calculate_field(goals, cost_field),
+		}
 		sector_int_fields.push((*sector_id, goals.clone(), int_field));
 	}
 	sector_int_fields
 }
 
-/// When a queued item has had its [IntegrationField]s built generate the
-/// [FlowField]s for it
+/// Recalculate the [FlowField] of a single sector along a `route` (ordered from actor to goal, as stored in the [RouteCache]) so that a cached field can be updated in place after a [CostField] change. `index` is the position of the sector within the `route`.
+///
+/// The [IntegrationField] of the next sector towards the goal is also calculated so that the directional optimisation over the portal goals matches a [FlowField] built by [create_flow_fields]. Returns the new [FlowField] along with the [IntegrationField] of the sector, [None] if `index` is out of bounds
+pub fn rebuild_route_flow_field(
+	route: &[(SectorID, FieldCell)],
+	index: usize,
+	sector_portals: &SectorPortals,
+	sector_cost_fields_scaled: &SectorCostFields,
+	map_dimensions: &MapDimensions,
+	density: Option<&SectorDensityFields>,
+) -> Option<(FlowField, IntegrationField)> {
+	if index >= route.len() {
+		return None;
+	}
+	// the sector and the next one towards the goal in the goal to actor order used by [exapnd_sector_portals]
+	let end = (index + 2).min(route.len());
+	let mut path_from_goal = route[index..end].to_vec();
+	path_from_goal.reverse();
+	let mut sectors_expanded_goals = exapnd_sector_portals(
+		&path_from_goal,
+		sector_portals,
+		sector_cost_fields_scaled,
+		map_dimensions,
+	);
+	// the first element is only the true goal at the end of the route, otherwise it's the portal of the next sector and needs expanding towards the sector after that
+	if end < route.len() {
+		let (next_sector, next_goal) = route[index + 1];
+		sectors_expanded_goals[0].1 = sector_portals
+			.get()
+			.get(&next_sector)
+			.unwrap()
+			.expand_portal_into_goals(
+				sector_cost_fields_scaled,
+				&next_sector,
+				&next_goal,
+				&route[end].0,
+				map_dimensions,
+			);
+	}
+	let sector_int_fields =
+		build_integration_fields(&sectors_expanded_goals, sector_cost_fields_scaled, density);
+	let (sector_id, goals, int_field) = sector_int_fields.last().unwrap();
+	let mut flow_field = FlowField::default();
+	if sector_int_fields.len() == 1 {
+		flow_field.calculate(goals, None, int_field);
+	} else {
+		let (prev_sector, _, prev_int_field) = &sector_int_fields[0];
+		if prev_sector.get_layer() != sector_id.get_layer() {
+			flow_field.calculate_to_layer_connection(goals, int_field);
+		} else {
+			let dir_prev_sector = Ordinal::sector_to_sector_direction(*prev_sector, *sector_id)?;
+			flow_field.calculate(goals, Some((dir_prev_sector, prev_int_field)), int_field);
+		}
+	}
+	Some((flow_field, *int_field))
+}
+
+/// When queued items have had their [IntegrationField]s built generate the
+/// [FlowField]s for them, highest priority first and then oldest first within
+/// the [PathRequestBudget]
 #[cfg(not(tarpaulin_include))]
-pub fn create_flow_fields(mut cache_q: Query<&mut FlowFieldCache>, time: Res<Time>) {
+pub fn create_flow_fields(
+	mut cache_q: Query<&mut FlowFieldCache>,
+	time: Res<Time>,
+	budget: Res<PathRequestBudget>,
+) {
 	for mut field_cache in &mut cache_q {
-		if let Some(mut entry) = field_cache.get_queue_mut().first_entry() {
-			// if the integration fields havbe been created then remove form queue and calculate flowfields
-			if !entry.get_mut().is_pending() {
-				let int = entry.remove();
-				let sector_int_fields = int.get_integration_fields().as_deref().unwrap();
-				let path = int.get_path();
-				// build the flow fields
-				for (i, (sector_id, goals, int_field)) in sector_int_fields.iter().enumerate() {
-					let mut flow_field = FlowField::default();
-					// first element is end target, therefore has no info about previous sector for
-					// direction optimisations
-					if i == 0 {
-						flow_field.calculate(goals, None, int_field);
-						field_cache.insert_field(*sector_id, path[i].1, time.elapsed(), flow_field);
-					} else if let Some(dir_prev_sector) =
-						Ordinal::sector_to_sector_direction(sector_int_fields[i - 1].0, *sector_id)
-					{
-						let prev_int_field = &sector_int_fields[i - 1].2;
-						flow_field.calculate(
-							goals,
-							Some((dir_prev_sector, prev_int_field)),
-							int_field,
-						);
-						//TODO by using the portal goal from path[i].1 actors criss-crossing from two seperate routes means one will use the others route in a sector which may be less efficient then using thier own?
-						field_cache.insert_field(*sector_id, path[i].1, time.elapsed(), flow_field);
-					} else {
-						error!("Route from goal to actor {:?}", path);
-					};
-				}
+		let built: Vec<RouteMetadata> = field_cache
+			.get_queue_by_priority()
+			.into_iter()
+			.filter(|metadata| !field_cache.get_queue()[metadata].is_pending())
+			.take(budget.fields_per_frame)
+			.collect();
+		// the integration fields have been created so remove from queue and calculate flowfields
+		for metadata in built.iter() {
+			let Some(int) = field_cache.get_queue_mut().remove(metadata) else {
+				continue;
+			};
+			// a regenerated route may pass through different sectors, free fields only its old path used
+			field_cache.remove_route_user(*metadata);
+			let sector_int_fields = int.get_integration_fields().as_deref().unwrap();
+			let path = int.get_path();
+			// build the flow fields
+			for (i, (sector_id, goals, int_field)) in sector_int_fields.iter().enumerate() {
+				let mut flow_field = FlowField::default();
+				// first element is end target, therefore has no info about previous sector for
+				// direction optimisations
+				if i == 0 {
+					flow_field.calculate(goals, None, int_field);
+					field_cache.insert_field(*sector_id, path[i].1, time.elapsed(), flow_field);
+					field_cache.add_field_user(*sector_id, path[i].1, *metadata);
+				} else if sector_int_fields[i - 1].0.get_layer() != sector_id.get_layer() {
+					// the route leaves this sector through a connection onto another layer
+					flow_field.calculate_to_layer_connection(goals, int_field);
+					field_cache.insert_field(*sector_id, path[i].1, time.elapsed(), flow_field);
+					field_cache.add_field_user(*sector_id, path[i].1, *metadata);
+				} else if let Some(dir_prev_sector) =
+					Ordinal::sector_to_sector_direction(sector_int_fields[i - 1].0, *sector_id)
+				{
+					let prev_int_field = &sector_int_fields[i - 1].2;
+					flow_field.calculate(goals, Some((dir_prev_sector, prev_int_field)), int_field);
+					//TODO by using the portal goal from path[i].1 actors criss-crossing from two seperate routes means one will use the others route in a sector which may be less efficient then using thier own?
+					field_cache.insert_field(*sector_id, path[i].1, time.elapsed(), flow_field);
+					field_cache.add_field_user(*sector_id, path[i].1, *metadata);
+				} else {
+					error!("Route from goal to actor {:?}", path);
+				};
 			}
 		}
 	}
 }
 
-/// Purge any routes older than 15 minutes
+/// Purge any routes older than 15 minutes unless an entity which requested them still needs them, [FlowField]s no other route uses are freed with them
 #[cfg(not(tarpaulin_include))]
-pub fn cleanup_old_routes(mut q_route_cache: Query<&mut RouteCache>, time: Res<Time>) {
-	for mut cache in q_route_cache.iter_mut() {
+pub fn cleanup_old_routes(
+	mut q_route_cache: Query<(&mut RouteCache, &mut FlowFieldCache)>,
+	time: Res<Time>,
+) {
+	for (mut cache, mut flow_cache) in q_route_cache.iter_mut() {
 		let mut routes_to_purge = Vec::new();
-		for data in cache.get_mut().keys() {
+		for data in cache.get().keys() {
 			let elapsed = time.elapsed();
 			let diff = elapsed.saturating_sub(data.get_time_generated());
-			if diff.as_secs() > 900 {
+			if diff.as_secs() > 900 && !cache.has_requester(data) {
 				routes_to_purge.push(*data);
 			}
 		}
 		for purge in routes_to_purge.iter() {
 			cache.remove_route(*purge);
+			cache.remove_handles(purge);
+			flow_cache.remove_route_user(*purge);
 		}
 	}
 }
-/// Purge any [FlowField]s older than 15 minutes
+/// Purge any [FlowField]s older than 15 minutes which aren't used by a route
 #[cfg(not(tarpaulin_include))]
 pub fn cleanup_old_flowfields(mut q_flow_cache: Query<&mut FlowFieldCache>, time: Res<Time>) {
 	for mut cache in q_flow_cache.iter_mut() {
 		let mut routes_to_purge = Vec::new();
-		for data in cache.get_mut().keys() {
+		for data in cache.get().keys() {
 			let elapsed = time.elapsed();
 			let diff = elapsed.saturating_sub(data.get_time_generated());
-			if diff.as_secs() > 900 {
+			if diff.as_secs() > 900 && !cache.is_field_used(data) {
 				routes_to_purge.push(*data);
 			}
 		}
@@ -317,6 +567,7 @@ pub fn cleanup_old_flowfields(mut q_flow_cache: Query<&mut FlowFieldCache>, time
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use bevy::ecs::event::ManualEventReader;
 
 	#[test]
 	fn filter_graph_route() {
@@ -374,4 +625,181 @@ mod tests {
 		assert_eq!(actual, path);
 	}
 
+	/// Run `frames` updates collecting the [RouteHandle] of each [EventRouteChanged] after every one. Events are cleared by fixed timestep ticks which depend upon real time so a slow run could drop them if they were only read at the end
+	fn update_route_changes(app: &mut App, reader: &mut ManualEventReader<EventRouteChanged>, frames: usize) -> Vec<RouteHandle> {
+		let mut changes = Vec::new();
+		for _ in 0..frames {
+			app.update();
+			let events = app.world().resource::<Events<EventRouteChanged>>();
+			changes.extend(reader.read(events).map(|e| e.0));
+		}
+		changes
+	}
+	/// Get the route a handle refers to
+	fn get_route(app: &mut App, handle: RouteHandle) -> Option<Vec<(SectorID, FieldCell)>> {
+		let mut q = app.world_mut().query::<&RouteCache>();
+		q.single(app.world()).get_route_by_handle(handle).map(|(_, route)| route.clone())
+	}
+	#[test]
+	fn route_handle_survives_regeneration() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, FlowFieldTilesPlugin));
+		app.world_mut().spawn(FlowFieldTilesBundle::new(30, 30, 10, 0.5));
+		let request = EventPathRequest::new(SectorID::new(0, 0), FieldCell::new(4, 4), SectorID::new(2, 0), FieldCell::new(5, 5));
+		let handle = request.get_handle();
+		app.world_mut().send_event(request);
+		let mut reader = ManualEventReader::<EventRouteChanged>::default();
+		assert_eq!(vec![handle], update_route_changes(&mut app, &mut reader, 5));
+		let original = get_route(&mut app, handle).unwrap();
+		assert!(original.iter().all(|(sector, _)| sector.get_row() == 0));
+		// a second request for the same route is told about it straight away
+		let duplicate = EventPathRequest::new(SectorID::new(0, 0), FieldCell::new(4, 4), SectorID::new(2, 0), FieldCell::new(5, 5));
+		let duplicate_handle = duplicate.get_handle();
+		app.world_mut().send_event(duplicate);
+		assert_eq!(vec![duplicate_handle], update_route_changes(&mut app, &mut reader, 1));
+		assert_eq!(Some(original.clone()), get_route(&mut app, duplicate_handle));
+		// wall off the middle of the map apart from the bottom row
+		app.world_mut().send_event(EventUpdateCostfieldsRegion::new(CostRegion::Rectangle { min: (15, 0), max: (15, 28) }, 255));
+		let mut changes = update_route_changes(&mut app, &mut reader, 5);
+		changes.sort();
+		assert_eq!(vec![handle, duplicate_handle], changes);
+		let regenerated = get_route(&mut app, handle).unwrap();
+		assert_ne!(original, regenerated);
+		assert!(regenerated.iter().any(|(sector, _)| sector.get_row() == 2));
+		assert_eq!(Some(regenerated), get_route(&mut app, duplicate_handle));
+	}
+	#[test]
+	fn unreachable_route_releases_handle() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, FlowFieldTilesPlugin));
+		app.world_mut().spawn(FlowFieldTilesBundle::new(30, 30, 10, 0.5));
+		let player = app.world_mut().spawn_empty().id();
+		// wall off the goal
+		app.world_mut().send_event(EventUpdateCostfieldsRegion::new(CostRegion::Rectangle { min: (24, 4), max: (26, 6) }, 255));
+		app.update();
+		let request = EventPathRequest::new(SectorID::new(0, 0), FieldCell::new(4, 4), SectorID::new(2, 0), FieldCell::new(5, 5)).with_requester(player);
+		let handle = request.get_handle();
+		app.world_mut().send_event(request);
+		let mut reader = ManualEventReader::<EventRouteChanged>::default();
+		assert_eq!(vec![handle], update_route_changes(&mut app, &mut reader, 1));
+		let mut q = app.world_mut().query::<&RouteCache>();
+		let route_cache = q.single(app.world());
+		assert!(route_cache.get_handle_metadata(handle).is_none());
+		assert!(route_cache.get_requester_handles(player).is_empty());
+		assert!(route_cache.get().is_empty());
+	}
+	#[test]
+	fn requests_serviced_by_priority() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, FlowFieldTilesPlugin));
+		app.world_mut().spawn(FlowFieldTilesBundle::new(30, 30, 10, 0.5));
+		let ambient = EventPathRequest::new(SectorID::new(0, 0), FieldCell::new(4, 4), SectorID::new(2, 2), FieldCell::new(5, 5));
+		let ambient_handle = ambient.get_handle();
+		let player = EventPathRequest::new(SectorID::new(0, 2), FieldCell::new(4, 4), SectorID::new(2, 0), FieldCell::new(5, 5)).with_priority(10);
+		let player_handle = player.get_handle();
+		app.world_mut().send_event(ambient);
+		app.world_mut().send_event(player);
+		let mut reader = ManualEventReader::<EventRouteChanged>::default();
+		let mut changes = update_route_changes(&mut app, &mut reader, 1);
+		// only one route is calculated per frame, the ambient request waits
+		let mut q = app.world_mut().query::<&RouteCache>();
+		let route_cache = q.single(app.world());
+		let waiting: Vec<RouteMetadata> = route_cache.get_requests().clone();
+		assert_eq!(vec![*route_cache.get_handle_metadata(ambient_handle).unwrap()], waiting);
+		changes.extend(update_route_changes(&mut app, &mut reader, 5));
+		assert_eq!(vec![player_handle, ambient_handle], changes);
+		assert!(get_route(&mut app, ambient_handle).is_some());
+		assert!(get_route(&mut app, player_handle).is_some());
+	}
+	#[test]
+	fn cancel_requests_of_requester() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, FlowFieldTilesPlugin));
+		// routes are calculated but their fields never get built
+		app.insert_resource(PathRequestBudget { routes_per_frame: 1, fields_per_frame: 0 });
+		app.world_mut().spawn(FlowFieldTilesBundle::new(30, 30, 10, 0.5));
+		let player = app.world_mut().spawn_empty().id();
+		for goal in [FieldCell::new(5, 5), FieldCell::new(6, 6)] {
+			app.world_mut().send_event(EventPathRequest::new(SectorID::new(0, 0), FieldCell::new(4, 4), SectorID::new(2, 2), goal).with_requester(player));
+		}
+		let other = EventPathRequest::new(SectorID::new(0, 2), FieldCell::new(4, 4), SectorID::new(2, 0), FieldCell::new(5, 5));
+		let other_handle = other.get_handle();
+		app.world_mut().send_event(other);
+		app.update();
+		app.update();
+		let mut q = app.world_mut().query::<(&RouteCache, &FlowFieldCache)>();
+		let (route_cache, flow_cache) = q.single(app.world());
+		assert_eq!(1, route_cache.get_requests().len());
+		assert_eq!(2, route_cache.get_queue().len() + flow_cache.get_queue().len());
+		app.world_mut().send_event(EventCancelPathRequest::Requester(player));
+		app.update();
+		app.update();
+		let mut q = app.world_mut().query::<(&RouteCache, &FlowFieldCache)>();
+		let (route_cache, flow_cache) = q.single(app.world());
+		assert!(route_cache.get_requests().is_empty());
+		assert_eq!(1, route_cache.get().len());
+		let remaining: Vec<&RouteMetadata> = flow_cache.get_queue().keys().collect();
+		assert_eq!(vec![route_cache.get_handle_metadata(other_handle).unwrap()], remaining);
+		assert!(route_cache.get_requester_handles(player).is_empty());
+	}
+	#[test]
+	fn fields_freed_with_last_requester() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, FlowFieldTilesPlugin));
+		app.world_mut().spawn(FlowFieldTilesBundle::new(30, 30, 10, 0.5));
+		let first = app.world_mut().spawn_empty().id();
+		let second = app.world_mut().spawn_empty().id();
+		// both routes end in the goal of sector (1, 0)
+		app.world_mut().send_event(EventPathRequest::new(SectorID::new(0, 0), FieldCell::new(4, 4), SectorID::new(1, 0), FieldCell::new(5, 5)).with_requester(first));
+		app.world_mut().send_event(EventPathRequest::new(SectorID::new(2, 0), FieldCell::new(4, 4), SectorID::new(1, 0), FieldCell::new(5, 5)).with_requester(second));
+		for _ in 0..6 {
+			app.update();
+		}
+		let mut q = app.world_mut().query::<&FlowFieldCache>();
+		let flow_cache = q.single(app.world());
+		assert_eq!(3, flow_cache.get().len());
+		assert_eq!(2, flow_cache.get_field_users(SectorID::new(1, 0), FieldCell::new(5, 5)).len());
+		app.world_mut().despawn(first);
+		app.update();
+		let mut q = app.world_mut().query::<(&RouteCache, &FlowFieldCache)>();
+		let (route_cache, flow_cache) = q.single(app.world());
+		assert_eq!(1, route_cache.get().len());
+		let mut sectors: Vec<SectorID> = flow_cache.get().keys().map(|k| k.get_sector_id()).collect();
+		sectors.sort();
+		assert_eq!(vec![SectorID::new(1, 0), SectorID::new(2, 0)], sectors);
+		// the second requester has arrived
+		app.world_mut().send_event(EventCancelPathRequest::Requester(second));
+		app.update();
+		let mut q = app.world_mut().query::<(&RouteCache, &FlowFieldCache)>();
+		let (route_cache, flow_cache) = q.single(app.world());
+		assert!(route_cache.get().is_empty());
+		assert!(flow_cache.get().is_empty());
+	}
+	#[test]
+	fn route_across_layers() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, FlowFieldTilesPlugin));
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let stairs = LayerConnection::new((SectorID::new(2, 0), FieldCell::new(5, 5)), (SectorID::new_layered(0, 2, 1), FieldCell::new(5, 5)), 10);
+		app.world_mut().spawn(FlowFieldTilesBundle::new(30, 30, 10, 0.5).with_layer(SectorCostFields::new(&map_dimensions)).with_layer_connection(stairs));
+		let request = EventPathRequest::new(SectorID::new(0, 0), FieldCell::new(4, 4), SectorID::new(2, 2), FieldCell::new(5, 5)).with_layers(0, 1);
+		let handle = request.get_handle();
+		app.world_mut().send_event(request);
+		for _ in 0..6 {
+			app.update();
+		}
+		let route = get_route(&mut app, handle).unwrap();
+		assert_eq!(SectorID::new(0, 0), route[0].0);
+		assert_eq!((SectorID::new_layered(2, 2, 1), FieldCell::new(5, 5)), *route.last().unwrap());
+		assert!(route.contains(&(SectorID::new(2, 0), FieldCell::new(5, 5))));
+		// every sector of the route has a flow field and the bottom of the stairs is where the actor changes layer
+		let mut q = app.world_mut().query::<&FlowFieldCache>();
+		let flow_cache = q.single(app.world());
+		for (sector, goal) in route.iter() {
+			assert!(flow_cache.get_field(*sector, *goal).is_some());
+		}
+		let field = flow_cache.get_field(SectorID::new(2, 0), FieldCell::new(5, 5)).unwrap();
+		assert!(is_layer_connection_goal(field.get_field_cell_value(FieldCell::new(5, 5))));
+		assert!(is_pathable(field.get_field_cell_value(FieldCell::new(0, 5))));
+	}
 }
@@ -4,8 +4,19 @@
 use crate::prelude::*;
 use bevy::prelude::*;
 
+#[cfg(any(feature = "2d", feature = "3d"))]
+pub mod avoidance;
+#[cfg(feature = "avian")]
+pub mod collider_layer;
 pub mod cost_layer;
+#[cfg(any(feature = "2d", feature = "3d"))]
+pub mod density_layer;
 pub mod flow_layer;
+#[cfg(any(feature = "2d", feature = "3d"))]
+pub mod obstacle_layer;
+pub mod query;
+#[cfg(any(feature = "2d", feature = "3d"))]
+pub mod steering;
 
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
 pub enum OrderingSet {
@@ -27,10 +38,19 @@ impl Plugin for FlowFieldTilesPlugin {
 			.register_type::<SectorID>()
 			.register_type::<FieldCell>()
 			.register_type::<RouteMetadata>()
+			.register_type::<RouteHandle>()
 			.register_type::<FlowFieldMetadata>()
+			.register_type::<SectorDensityFields>()
+			.register_type::<flow_layer::PathRequestBudget>()
+			.init_resource::<flow_layer::PathRequestBudget>()
 			.add_event::<cost_layer::EventUpdateCostfieldsCell>()
+			.add_event::<cost_layer::EventUpdateCostfieldsRegion>()
+			.add_event::<cost_layer::EventAddCostModifier>()
+			.add_event::<cost_layer::EventRemoveCostModifier>()
 			.add_event::<cost_layer::EventCleanCaches>()
 			.add_event::<flow_layer::EventPathRequest>()
+			.add_event::<flow_layer::EventRouteChanged>()
+			.add_event::<flow_layer::EventCancelPathRequest>()
 			.configure_sets(
 				PreUpdate,
 				(OrderingSet::Tidy, OrderingSet::Calculate).chain(),
@@ -41,14 +61,28 @@ impl Plugin for FlowFieldTilesPlugin {
 					(
 						flow_layer::cleanup_old_routes,
 						flow_layer::cleanup_old_flowfields,
+						flow_layer::release_despawned_requesters,
 						(
+							cost_layer::tick_cost_modifiers,
 							cost_layer::process_costfields_updates,
 							cost_layer::clean_cache,
 						)
 							.chain(),
 					)
 						.in_set(OrderingSet::Tidy),
+					#[cfg(any(feature = "2d", feature = "3d"))]
+					density_layer::update_density_fields.in_set(OrderingSet::Tidy),
+					#[cfg(any(feature = "2d", feature = "3d"))]
+					obstacle_layer::stamp_obstacles
+						.before(cost_layer::process_costfields_updates)
+						.in_set(OrderingSet::Tidy),
+					#[cfg(feature = "avian")]
+					collider_layer::stamp_static_colliders
+						.before(cost_layer::process_costfields_updates)
+						.in_set(OrderingSet::Tidy),
 					(
+						flow_layer::event_cancel_path_requests
+							.before(flow_layer::event_insert_route_queue),
 						flow_layer::event_insert_route_queue,
 						flow_layer::process_route_queue,
 						flow_layer::create_queued_integration_fields,
@@ -57,5 +91,10 @@ impl Plugin for FlowFieldTilesPlugin {
 						.in_set(OrderingSet::Calculate),
 				),
 			);
+		#[cfg(feature = "3d")]
+		app.register_type::<ElevationField>();
+		#[cfg(any(feature = "2d", feature = "3d"))]
+		app.add_event::<cost_layer::EventUpdateCostfieldsWorld>()
+			.register_type::<obstacle_layer::FlowFieldObstacle>();
 	}
 }
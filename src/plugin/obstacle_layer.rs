@@ -0,0 +1,374 @@
+//! Entities with a [FlowFieldObstacle] component have their footprint stamped into the [CostField]s of the map. The footprint follows the `GlobalTransform` of the entity and is cleared when the entity is despawned.
+//!
+//! Each obstacle is applied as a cost modifier so that when it moves or is removed the cells it covered are restored to exactly the cost they would have without it
+//!
+
+use crate::prelude::*;
+use bevy::{
+	asset::{Assets, Handle},
+	ecs::system::SystemParam,
+	prelude::*,
+	render::mesh::{Indices, VertexAttributeValues},
+	utils::{HashMap, HashSet},
+};
+
+/// The footprint of a [FlowFieldObstacle] relative to the `GlobalTransform` of its entity. Footprints are measured in the plane of the [SteeringPlane] resource if it exists, otherwise the default plane is used
+#[derive(Clone, Debug, Reflect)]
+pub enum ObstacleShape {
+	/// An axis aligned box, rotation of the `GlobalTransform` is ignored
+	Aabb {
+		/// Half of the width and height (2d) or depth (3d) of the box
+		half_extents: Vec2,
+	},
+	/// A circle
+	Circle {
+		/// Radius of the circle
+		radius: f32,
+	},
+	/// Every triangle of a mesh, the mesh is transformed by the `GlobalTransform` and flattened into the plane
+	Mesh(Handle<Mesh>),
+}
+
+/// Marks an entity whose footprint should be impassable or more costly to path through
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
+pub struct FlowFieldObstacle {
+	/// Footprint of the obstacle
+	shape: ObstacleShape,
+	/// Cost assigned to every [FieldCell] within the footprint
+	cost: u8,
+	/// The sector layer the footprint is stamped onto
+	layer: u32,
+}
+
+impl FlowFieldObstacle {
+	/// Create a new instance of [FlowFieldObstacle] which assigns `cost` to every [FieldCell] of sector layer `0` whose centre lies within its footprint
+	pub fn new(shape: ObstacleShape, cost: u8) -> Self {
+		FlowFieldObstacle {
+			shape,
+			cost,
+			layer: 0,
+		}
+	}
+	/// Stamp the footprint onto another sector `layer`
+	pub fn with_layer(mut self, layer: u32) -> Self {
+		self.layer = layer;
+		self
+	}
+	/// Get the sector layer the footprint is stamped onto
+	pub fn get_layer(&self) -> u32 {
+		self.layer
+	}
+	/// Get the footprint of the obstacle
+	pub fn get_shape(&self) -> &ObstacleShape {
+		&self.shape
+	}
+	/// Get the cost assigned to the footprint
+	pub fn get_cost(&self) -> u8 {
+		self.cost
+	}
+	/// Set the cost assigned to the footprint
+	pub fn set_cost(&mut self, cost: u8) {
+		self.cost = cost;
+	}
+	/// Find the area of the map covered by the obstacle at `transform`. [None] is returned if the obstacle uses a [Mesh] which hasn't been loaded
+	pub fn get_region(
+		&self,
+		transform: &GlobalTransform,
+		plane: SteeringPlane,
+		map_dimensions: &MapDimensions,
+		meshes: Option<&Assets<Mesh>>,
+	) -> Option<CostRegion> {
+		let cell_size = map_dimensions.get_sector_resolution() as f32 / FIELD_RESOLUTION as f32;
+		let centre = plane.get_grid_position(map_dimensions, transform.translation());
+		let scale = transform.compute_transform().scale;
+		match &self.shape {
+			ObstacleShape::Aabb { half_extents } => {
+				let half = *half_extents * plane.project(scale).abs() / cell_size;
+				Some(CostRegion::Polygon(vec![
+					centre - half,
+					Vec2::new(centre.x + half.x, centre.y - half.y),
+					centre + half,
+					Vec2::new(centre.x - half.x, centre.y + half.y),
+				]))
+			}
+			ObstacleShape::Circle { radius } => {
+				let scale = plane.project(scale).abs().max_element();
+				Some(CostRegion::Circle {
+					centre,
+					radius: radius * scale / cell_size,
+				})
+			}
+			ObstacleShape::Mesh(handle) => {
+				let mesh = meshes?.get(handle)?;
+				let Some(VertexAttributeValues::Float32x3(positions)) =
+					mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+				else {
+					warn!("FlowFieldObstacle mesh has no Float32x3 positions");
+					return Some(CostRegion::Cells(vec![]));
+				};
+				let vertices: Vec<Vec2> = positions
+					.iter()
+					.map(|p| {
+						let world = transform.transform_point(Vec3::from_array(*p));
+						plane.get_grid_position(map_dimensions, world)
+					})
+					.collect();
+				let indices: Vec<usize> = match mesh.indices() {
+					Some(Indices::U16(i)) => i.iter().map(|i| *i as usize).collect(),
+					Some(Indices::U32(i)) => i.iter().map(|i| *i as usize).collect(),
+					None => (0..vertices.len()).collect(),
+				};
+				let mut cells = HashSet::new();
+				for triangle in indices.chunks_exact(3) {
+					let polygon = CostRegion::Polygon(vec![
+						vertices[triangle[0]],
+						vertices[triangle[1]],
+						vertices[triangle[2]],
+					]);
+					for (sector_id, field_cell, _) in polygon.get_cell_values(0, map_dimensions) {
+						cells.insert(
+							map_dimensions
+								.get_grid_cell_from_sector_and_field_cell(sector_id, field_cell),
+						);
+					}
+				}
+				let mut cells: Vec<(usize, usize)> = cells.into_iter().collect();
+				cells.sort();
+				Some(CostRegion::Cells(cells))
+			}
+		}
+	}
+}
+
+/// Tracks the cost modifier stamped by each entity, such as a [FlowFieldObstacle], so that it can be replaced when the entity moves and cleared when it is removed
+#[derive(Default)]
+pub struct CostStamps {
+	/// Modifier currently applied by each entity
+	handles: HashMap<Entity, CostModifierHandle>,
+	/// Entities waiting on an asset to be loaded before they can be stamped
+	pending: HashSet<Entity>,
+}
+
+/// The events used to send the modifiers of [CostStamps] to the batched cost update
+#[derive(SystemParam)]
+pub struct CostStampEvents<'w> {
+	/// Applies a new stamp
+	event_add: EventWriter<'w, EventAddCostModifier>,
+	/// Clears a previous stamp
+	event_remove: EventWriter<'w, EventRemoveCostModifier>,
+}
+
+impl CostStamps {
+	/// Stamp `cost` onto every cell of `region` within a sector `layer` on behalf of `entity`, replacing anything it stamped before
+	pub fn stamp(
+		&mut self,
+		entity: Entity,
+		region: CostRegion,
+		cost: u8,
+		layer: u32,
+		events: &mut CostStampEvents,
+	) {
+		self.clear(entity, events);
+		let event =
+			EventAddCostModifier::new(region, CostModifier::Set(cost), None).with_layer(layer);
+		self.handles.insert(entity, event.get_handle());
+		events.event_add.send(event);
+	}
+	/// Clear whatever `entity` has stamped, restoring the cells to the cost they would have without it
+	pub fn clear(&mut self, entity: Entity, events: &mut CostStampEvents) {
+		self.pending.remove(&entity);
+		if let Some(handle) = self.handles.remove(&entity) {
+			events.event_remove.send(EventRemoveCostModifier(handle));
+		}
+	}
+	/// Mark `entity` as waiting on an asset so it is stamped again later
+	pub fn set_pending(&mut self, entity: Entity) {
+		self.pending.insert(entity);
+	}
+	/// Take every entity that is waiting to be stamped
+	pub fn take_pending(&mut self) -> Vec<Entity> {
+		self.pending.drain().collect()
+	}
+}
+
+/// Filter for obstacles which have been added, moved or had their footprint changed
+type ChangedObstacleFilter = (
+	With<FlowFieldObstacle>,
+	Or<(Changed<FlowFieldObstacle>, Changed<GlobalTransform>)>,
+);
+
+/// Stamp the footprint of new or moved [FlowFieldObstacle]s into the [CostField]s and clear the footprint of any that have been despawned. Footprints are tracked by [CostStamps]
+#[cfg(not(tarpaulin_include))]
+#[allow(clippy::too_many_arguments)]
+pub fn stamp_obstacles(
+	obstacles: Query<(&FlowFieldObstacle, &GlobalTransform)>,
+	changed: Query<Entity, ChangedObstacleFilter>,
+	mut removed: RemovedComponents<FlowFieldObstacle>,
+	map_q: Query<&MapDimensions>,
+	plane: Option<Res<SteeringPlane>>,
+	meshes: Option<Res<Assets<Mesh>>>,
+	mut stamps: Local<CostStamps>,
+	mut events: CostStampEvents,
+) {
+	for entity in removed.read() {
+		stamps.clear(entity, &mut events);
+	}
+	let Ok(map_dimensions) = map_q.get_single() else {
+		return;
+	};
+	let plane = plane.map(|p| *p).unwrap_or_default();
+	let mut to_stamp = stamps.take_pending();
+	to_stamp.extend(changed.iter());
+	for entity in to_stamp {
+		let Ok((obstacle, transform)) = obstacles.get(entity) else {
+			continue;
+		};
+		let Some(region) = obstacle.get_region(transform, plane, map_dimensions, meshes.as_deref())
+		else {
+			stamps.set_pending(entity);
+			continue;
+		};
+		stamps.stamp(
+			entity,
+			region,
+			obstacle.get_cost(),
+			obstacle.get_layer(),
+			&mut events,
+		);
+	}
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use super::*;
+
+	/// Position of an obstacle where `x` and `w` are the world coordinates in the default plane
+	fn position(x: f32, w: f32) -> Vec3 {
+		match SteeringPlane::default() {
+			#[cfg(feature = "2d")]
+			SteeringPlane::XY => Vec3::new(x, w, 0.0),
+			#[cfg(feature = "3d")]
+			SteeringPlane::XZ => Vec3::new(x, 0.0, w),
+		}
+	}
+	/// Create an [App] with a 30x30 map
+	pub(crate) fn setup_app() -> App {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, TransformPlugin, FlowFieldTilesPlugin));
+		app.world_mut()
+			.spawn(FlowFieldTilesBundle::new(30, 30, 10, 0.5));
+		app
+	}
+	/// Get the effective cost of `field_cell` in `sector`, with any stamped modifiers applied
+	pub(crate) fn get_cost(app: &mut App, sector: SectorID, field_cell: FieldCell) -> u8 {
+		let mut q = app.world_mut().query::<&SectorCostFields>();
+		q.single(app.world())
+			.get_effective()
+			.get(&sector)
+			.unwrap()
+			.get_field_cell_value(field_cell)
+	}
+	#[test]
+	fn aabb_region() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let obstacle = FlowFieldObstacle::new(
+			ObstacleShape::Aabb {
+				half_extents: Vec2::new(2.0, 1.0),
+			},
+			255,
+		);
+		let transform = GlobalTransform::from_translation(position(0.0, 0.0));
+		let region = obstacle
+			.get_region(&transform, SteeringPlane::default(), &map_dimensions, None)
+			.unwrap();
+		let cells = region.get_cell_values(0, &map_dimensions);
+		assert_eq!(8, cells.len());
+		assert!(cells
+			.iter()
+			.all(|(sector, _, _)| *sector == SectorID::new(1, 1)));
+	}
+	#[test]
+	#[cfg(feature = "2d")]
+	fn mesh_region() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut meshes = Assets::<Mesh>::default();
+		let handle = meshes.add(Rectangle::new(2.0, 4.0));
+		let obstacle = FlowFieldObstacle::new(ObstacleShape::Mesh(handle), 255);
+		let transform = GlobalTransform::from_xyz(-10.0, 10.0, 0.0);
+		let region = obstacle
+			.get_region(
+				&transform,
+				SteeringPlane::XY,
+				&map_dimensions,
+				Some(&meshes),
+			)
+			.unwrap();
+		let mut cells: Vec<(SectorID, FieldCell)> = region
+			.get_cell_values(0, &map_dimensions)
+			.into_iter()
+			.map(|(s, c, _)| (s, c))
+			.collect();
+		cells.sort();
+		let sector = SectorID::new(0, 0);
+		let mut actual = Vec::new();
+		for column in 4..6 {
+			for row in 3..7 {
+				actual.push((sector, FieldCell::new(column, row)));
+			}
+		}
+		assert_eq!(actual, cells);
+	}
+	#[test]
+	fn mesh_waits_for_asset() {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let obstacle = FlowFieldObstacle::new(ObstacleShape::Mesh(Handle::default()), 255);
+		let meshes = Assets::<Mesh>::default();
+		let region = obstacle.get_region(
+			&GlobalTransform::default(),
+			SteeringPlane::default(),
+			&map_dimensions,
+			Some(&meshes),
+		);
+		assert!(region.is_none());
+	}
+	#[test]
+	fn obstacle_stamped_moved_and_cleared() {
+		let mut app = setup_app();
+		let obstacle = app
+			.world_mut()
+			.spawn((
+				FlowFieldObstacle::new(ObstacleShape::Circle { radius: 1.0 }, 255),
+				TransformBundle::from_transform(Transform::from_translation(position(0.0, 0.0))),
+			))
+			.id();
+		// the `GlobalTransform` is propagated at the end of the first frame and stamped in the next
+		app.update();
+		app.update();
+		let sector = SectorID::new(1, 1);
+		for cell in [(4, 4), (4, 5), (5, 4), (5, 5)] {
+			assert_eq!(
+				255,
+				get_cost(&mut app, sector, FieldCell::new(cell.0, cell.1))
+			);
+		}
+		// move to the centre of sector (0, 0)
+		app.world_mut()
+			.get_mut::<Transform>(obstacle)
+			.unwrap()
+			.translation = position(-10.0, if cfg!(feature = "2d") { 10.0 } else { -10.0 });
+		app.update();
+		app.update();
+		assert_eq!(1, get_cost(&mut app, sector, FieldCell::new(4, 4)));
+		assert_eq!(
+			255,
+			get_cost(&mut app, SectorID::new(0, 0), FieldCell::new(5, 5))
+		);
+		app.world_mut().despawn(obstacle);
+		app.update();
+		assert_eq!(
+			1,
+			get_cost(&mut app, SectorID::new(0, 0), FieldCell::new(5, 5))
+		);
+	}
+}
@@ -0,0 +1,649 @@
+//! Synchronous queries against the [FlowFieldTilesBundle] which can be used from any system, either to find a path immediately or to read the [FlowField]s an actor is following
+//!
+
+use crate::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+/// Allows a system to immediately query for a path between two world positions rather than waiting on the [FlowField] pipeline. This is useful for AI planning, previewing a path or checking reachability before committing an actor to move.
+///
+/// The path is calculated from the [PortalGraph] and then refined into a series of field cells by searching across each sector that the [PortalGraph] route passes through.
+///
+/// A single [FlowFieldTilesBundle] is expected to exist.
+#[derive(SystemParam)]
+pub struct PathQuery<'w, 's> {
+	/// The data from the [FlowFieldTilesBundle] required to calculate a path
+	fields: Query<
+		'w,
+		's,
+		(
+			&'static PortalGraph,
+			&'static SectorPortals,
+			&'static SectorCostFields,
+			&'static MapDimensions,
+		),
+	>,
+	/// Height of the terrain, positions are flat if it doesn't exist
+	#[cfg(feature = "3d")]
+	elevation: Query<'w, 's, &'static ElevationField>,
+}
+
+impl<'w, 's> PathQuery<'w, 's> {
+	/// Find a path of field cells from `source` to `target`. Returns [None] if either point lies outside of the world or no path exists
+	pub fn find_path_cells(
+		&self,
+		source: (SectorID, FieldCell),
+		target: (SectorID, FieldCell),
+	) -> Option<Vec<(SectorID, FieldCell)>> {
+		let (graph, sector_portals, sector_cost_fields, _) = self.fields.get_single().ok()?;
+		graph.find_best_cell_path(source, target, sector_portals, sector_cost_fields)
+	}
+	/// Find a path of 2d world positions from `start` to `goal`, which are either a `Vec2` on the ground layer or a `(Vec2, u32)` on a given sector layer. Each intermediate point is the centre of a field cell, the first and last points are the exact `start` and `goal`. Returns [None] if either point lies outside of the world or no path exists
+	#[cfg(feature = "2d")]
+	pub fn find_path_xy<P: FlowFieldPosition<Vector = Vec2>>(
+		&self,
+		start: P,
+		goal: P,
+	) -> Option<Vec<Vec2>> {
+		let (graph, sector_portals, sector_cost_fields, map_dimensions) =
+			self.fields.get_single().ok()?;
+		let source = start.get_sector_and_field_cell(map_dimensions)?;
+		let target = goal.get_sector_and_field_cell(map_dimensions)?;
+		let cells =
+			graph.find_best_cell_path(source, target, sector_portals, sector_cost_fields)?;
+		let mut path = Vec::with_capacity(cells.len());
+		for (sector, cell) in cells.iter() {
+			path.push(map_dimensions.get_xy_from_field_sector(*sector, *cell)?);
+		}
+		replace_endpoints(&mut path, start.get_position(), goal.get_position());
+		Some(path)
+	}
+	/// Find a path of 3d world positions from `start` to `goal` along the x-z plane, which are either a `Vec3` on the ground layer or a `(Vec3, u32)` on a given sector layer. Each intermediate point is the centre of a field cell at the height of its [ElevationField] (`0.0` without one), the first and last points are the exact `start` and `goal`. Returns [None] if either point lies outside of the world or no path exists
+	#[cfg(feature = "3d")]
+	pub fn find_path_xyz<P: FlowFieldPosition<Vector = Vec3>>(
+		&self,
+		start: P,
+		goal: P,
+	) -> Option<Vec<Vec3>> {
+		let (graph, sector_portals, sector_cost_fields, map_dimensions) =
+			self.fields.get_single().ok()?;
+		let source = start.get_sector_and_field_cell(map_dimensions)?;
+		let target = goal.get_sector_and_field_cell(map_dimensions)?;
+		let cells =
+			graph.find_best_cell_path(source, target, sector_portals, sector_cost_fields)?;
+		let mut path = Vec::with_capacity(cells.len());
+		for (sector, cell) in cells.iter() {
+			path.push(match self.elevation.get_single() {
+				Ok(elevation) => {
+					elevation.get_xyz_from_field_sector(map_dimensions, *sector, *cell)?
+				}
+				Err(_) => map_dimensions.get_xyz_from_field_sector(*sector, *cell)?,
+			});
+		}
+		replace_endpoints(&mut path, start.get_position(), goal.get_position());
+		Some(path)
+	}
+}
+
+/// A world position which a [FlowFieldQuery] can resolve into a [FieldCell] of the map. With the `2d` feature `Vec2` positions are supported and with the `3d` feature `Vec3` positions along the x-z plane. A bare vector lies on the ground layer, pairing it with a sector layer as `(Vec2, u32)` or `(Vec3, u32)` places it on that layer, i.e the floor of a building
+pub trait FlowFieldPosition: Copy {
+	/// Type of the position and of the direction an actor should move in from it
+	type Vector: Copy;
+	/// Get the world position without its layer
+	fn get_position(&self) -> Self::Vector;
+	/// Find the sector and field cell containing the position. Returns [None] if it lies outside of the world
+	fn get_sector_and_field_cell(
+		&self,
+		map_dimensions: &MapDimensions,
+	) -> Option<(SectorID, FieldCell)>;
+	/// Get the unit direction an actor at the position should move in to follow a `route`. Returns [None] if the [FlowField] for the current sector hasn't been generated (yet)
+	fn get_direction(&self, query: &FlowFieldQuery, route: &RouteMetadata) -> Option<Self::Vector>;
+}
+
+#[cfg(feature = "2d")]
+impl FlowFieldPosition for Vec2 {
+	type Vector = Vec2;
+	fn get_position(&self) -> Vec2 {
+		*self
+	}
+	fn get_sector_and_field_cell(
+		&self,
+		map_dimensions: &MapDimensions,
+	) -> Option<(SectorID, FieldCell)> {
+		map_dimensions.get_sector_and_field_cell_from_xy(*self)
+	}
+	fn get_direction(&self, query: &FlowFieldQuery, route: &RouteMetadata) -> Option<Vec2> {
+		let cell = self.get_sector_and_field_cell(query.get_map_dimensions()?)?;
+		query.get_direction_xy(*self, cell, route)
+	}
+}
+
+#[cfg(feature = "3d")]
+impl FlowFieldPosition for Vec3 {
+	type Vector = Vec3;
+	fn get_position(&self) -> Vec3 {
+		*self
+	}
+	fn get_sector_and_field_cell(
+		&self,
+		map_dimensions: &MapDimensions,
+	) -> Option<(SectorID, FieldCell)> {
+		map_dimensions.get_sector_and_field_cell_from_xyz(*self)
+	}
+	fn get_direction(&self, query: &FlowFieldQuery, route: &RouteMetadata) -> Option<Vec3> {
+		let cell = self.get_sector_and_field_cell(query.get_map_dimensions()?)?;
+		query.get_direction_xyz(*self, cell, route)
+	}
+}
+
+#[cfg(feature = "2d")]
+impl FlowFieldPosition for (Vec2, u32) {
+	type Vector = Vec2;
+	fn get_position(&self) -> Vec2 {
+		self.0
+	}
+	fn get_sector_and_field_cell(
+		&self,
+		map_dimensions: &MapDimensions,
+	) -> Option<(SectorID, FieldCell)> {
+		let (sector, field_cell) = self.0.get_sector_and_field_cell(map_dimensions)?;
+		Some((sector.with_layer(self.1), field_cell))
+	}
+	fn get_direction(&self, query: &FlowFieldQuery, route: &RouteMetadata) -> Option<Vec2> {
+		let cell = self.get_sector_and_field_cell(query.get_map_dimensions()?)?;
+		query.get_direction_xy(self.0, cell, route)
+	}
+}
+
+#[cfg(feature = "3d")]
+impl FlowFieldPosition for (Vec3, u32) {
+	type Vector = Vec3;
+	fn get_position(&self) -> Vec3 {
+		self.0
+	}
+	fn get_sector_and_field_cell(
+		&self,
+		map_dimensions: &MapDimensions,
+	) -> Option<(SectorID, FieldCell)> {
+		let (sector, field_cell) = self.0.get_sector_and_field_cell(map_dimensions)?;
+		Some((sector.with_layer(self.1), field_cell))
+	}
+	fn get_direction(&self, query: &FlowFieldQuery, route: &RouteMetadata) -> Option<Vec3> {
+		let cell = self.get_sector_and_field_cell(query.get_map_dimensions()?)?;
+		query.get_direction_xyz(self.0, cell, route)
+	}
+}
+
+/// Provides lookups of the [RouteCache] and [FlowFieldCache] from world positions so that steering systems don't have to convert positions into sectors and field cells, match them against a route and decode the [FlowField] bits themselves.
+///
+/// An actor follows a route by the [RouteHandle] of its [EventPathRequest], the handle keeps referring to the route when it is regenerated after a [CostField] change. Positions are any [FlowFieldPosition].
+///
+/// A single [FlowFieldTilesBundle] is expected to exist.
+#[derive(SystemParam)]
+pub struct FlowFieldQuery<'w, 's> {
+	/// The data from the [FlowFieldTilesBundle] required to read routes and fields
+	fields: Query<
+		'w,
+		's,
+		(
+			&'static RouteCache,
+			&'static FlowFieldCache,
+			&'static SectorCostFields,
+			&'static MapDimensions,
+		),
+	>,
+	/// Height of the terrain, positions are flat if it doesn't exist
+	#[cfg(feature = "3d")]
+	elevation: Query<'w, 's, &'static ElevationField>,
+}
+
+impl<'w, 's> FlowFieldQuery<'w, 's> {
+	/// Get the [MapDimensions] of the world. Returns [None] if the [FlowFieldTilesBundle] doesn't exist
+	pub fn get_map_dimensions(&self) -> Option<&MapDimensions> {
+		self.fields
+			.get_single()
+			.ok()
+			.map(|(_, _, _, dimensions)| dimensions)
+	}
+	/// Get the route between a `source` and `target` position if it has been generated. Returns [None] if it hasn't been generated (yet) or either position is outside of the world
+	pub fn route_for<P: FlowFieldPosition>(&self, source: P, target: P) -> Option<RouteMetadata> {
+		let map_dimensions = self.get_map_dimensions()?;
+		let source = source.get_sector_and_field_cell(map_dimensions)?;
+		let target = target.get_sector_and_field_cell(map_dimensions)?;
+		self.route_for_cells(source, target)
+	}
+	/// Get the route between a `source` and `target` cell if it has been generated. Returns [None] if it hasn't been generated (yet)
+	pub fn route_for_cells(
+		&self,
+		source: (SectorID, FieldCell),
+		target: (SectorID, FieldCell),
+	) -> Option<RouteMetadata> {
+		let (route_cache, _, _, _) = self.fields.get_single().ok()?;
+		route_cache
+			.get_route_with_metadata(source.0, source.1, target.0, target.1)
+			.map(|(metadata, _)| *metadata)
+	}
+	/// Get the route a [RouteHandle] refers to. Returns [None] if the route hasn't been generated (yet) or is being regenerated
+	pub fn route_for_handle(&self, handle: RouteHandle) -> Option<RouteMetadata> {
+		let (route_cache, _, _, _) = self.fields.get_single().ok()?;
+		route_cache
+			.get_route_by_handle(handle)
+			.map(|(metadata, _)| *metadata)
+	}
+	/// Get the unit direction an actor at `position` should move in to follow the route of a [RouteHandle]. When the actor has line-of-sight to the goal the direction points straight at the centre of the goal cell, in 3d with a non-empty [ElevationField] the direction follows the slope of the terrain. Returns [None] if the route or the [FlowField] for the current sector hasn't been generated (yet)
+	pub fn direction_at<P: FlowFieldPosition>(
+		&self,
+		position: P,
+		route_handle: &RouteHandle,
+	) -> Option<P::Vector> {
+		let route = self.route_for_handle(*route_handle)?;
+		position.get_direction(self, &route)
+	}
+	/// Is `position` within the world and on a pathable [CostField] cell
+	pub fn is_pathable<P: FlowFieldPosition>(&self, position: P) -> bool {
+		self.get_map_dimensions()
+			.and_then(|map_dimensions| position.get_sector_and_field_cell(map_dimensions))
+			.is_some_and(|(sector, field_cell)| self.is_cell_pathable(sector, field_cell))
+	}
+	/// Get the [FlowField] cell value that an actor in `sector` and `field_cell` should be following for a `route`. The first sector-goal of the route that matches `sector` is used. Returns [None] if the route or the [FlowField] doesn't exist
+	pub fn get_flow_field_cell_value(
+		&self,
+		sector: SectorID,
+		field_cell: FieldCell,
+		route: &RouteMetadata,
+	) -> Option<u8> {
+		let (route_cache, flow_cache, _, _) = self.fields.get_single().ok()?;
+		let portal_route = route_cache.get().get(route)?;
+		let (_, goal) = portal_route.iter().find(|(s, _)| *s == sector)?;
+		let field = flow_cache.get_field(sector, *goal)?;
+		Some(field.get_field_cell_value(field_cell))
+	}
+	/// Is the [CostField] cell at `sector` and `field_cell` pathable
+	pub fn is_cell_pathable(&self, sector: SectorID, field_cell: FieldCell) -> bool {
+		if let Ok((_, _, sector_cost_fields, _)) = self.fields.get_single() {
+			if let Some(cost_field) = sector_cost_fields.get_scaled().get(&sector) {
+				return cost_field.get_field_cell_value(field_cell) != 255;
+			}
+		}
+		false
+	}
+	/// Get the unit direction an actor at the 2d `position`, which lies in `cell`, should move in to follow a `route`
+	#[cfg(feature = "2d")]
+	fn get_direction_xy(
+		&self,
+		position: Vec2,
+		(sector, field_cell): (SectorID, FieldCell),
+		route: &RouteMetadata,
+	) -> Option<Vec2> {
+		let map_dimensions = self.get_map_dimensions()?;
+		let cell_value = self.get_flow_field_cell_value(sector, field_cell, route)?;
+		if has_line_of_sight(cell_value) {
+			let goal = map_dimensions
+				.get_xy_from_field_sector(route.get_target_sector(), route.get_target_goal())?;
+			return Some((goal - position).normalize_or_zero());
+		}
+		Some(get_2d_direction_unit_vector_from_bits(cell_value))
+	}
+	/// Get the unit direction an actor at the 3d `position`, which lies in `cell`, should move in to follow a `route`. With a non-empty [ElevationField] the direction follows the slope of the terrain, otherwise it lies along the x-z plane
+	#[cfg(feature = "3d")]
+	fn get_direction_xyz(
+		&self,
+		position: Vec3,
+		(sector, field_cell): (SectorID, FieldCell),
+		route: &RouteMetadata,
+	) -> Option<Vec3> {
+		let map_dimensions = self.get_map_dimensions()?;
+		let cell_value = self.get_flow_field_cell_value(sector, field_cell, route)?;
+		let elevation = self
+			.elevation
+			.get_single()
+			.ok()
+			.filter(|elevation| !elevation.is_empty());
+		if has_line_of_sight(cell_value) {
+			let goal = map_dimensions
+				.get_xyz_from_field_sector(route.get_target_sector(), route.get_target_goal())?;
+			let rise = elevation.map_or(0.0, |elevation| {
+				elevation.get_height_at_xyz(map_dimensions, goal)
+					- elevation.get_height_at_xyz(map_dimensions, position)
+			});
+			let dir = goal - position;
+			return Some(Vec3::new(dir.x, rise, dir.z).normalize_or_zero());
+		}
+		match elevation {
+			Some(elevation) => Some(get_3d_sloped_direction_unit_vector_from_bits(
+				cell_value,
+				sector,
+				field_cell,
+				elevation,
+				map_dimensions,
+			)),
+			None => Some(get_3d_direction_unit_vector_from_bits(cell_value)),
+		}
+	}
+}
+
+/// Swap the cell centres at either end of a path for the exact positions that were requested
+#[cfg(any(feature = "2d", feature = "3d"))]
+fn replace_endpoints<T: Copy>(path: &mut [T], start: T, goal: T) {
+	if let Some(first) = path.first_mut() {
+		*first = start;
+	}
+	if let Some(last) = path.last_mut() {
+		*last = goal;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bevy::{ecs::system::SystemState, utils::Duration};
+
+	/// Create the fields of a 30x30 map where the middle column of sectors is walled off apart from the bottom row of cells
+	fn setup_fields() -> (PortalGraph, SectorPortals, SectorCostFields, MapDimensions) {
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		for sector_row in 0..3 {
+			for row in 0..FIELD_RESOLUTION {
+				if sector_row == 2 && row == FIELD_RESOLUTION - 1 {
+					continue;
+				}
+				sector_cost_fields.set_field_cell_value(
+					SectorID::new(1, sector_row),
+					255,
+					FieldCell::new(5, row),
+					&map_dimensions,
+				);
+			}
+		}
+		let mut sector_portals = SectorPortals::new(30, 30, 10);
+		for sector_id in sector_cost_fields.get_scaled().keys() {
+			sector_portals.update_portals(*sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		let graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		(graph, sector_portals, sector_cost_fields, map_dimensions)
+	}
+	/// Create a [World] containing the fields from [setup_fields]
+	fn setup_world() -> World {
+		let mut world = World::new();
+		world.spawn((
+			setup_fields(),
+			RouteCache::default(),
+			FlowFieldCache::default(),
+		));
+		world
+	}
+	#[test]
+	fn cell_path_detours_around_wall() {
+		let mut world = setup_world();
+		let mut state: SystemState<PathQuery> = SystemState::new(&mut world);
+		let query = state.get(&world);
+		let source = (SectorID::new(0, 0), FieldCell::new(5, 0));
+		let target = (SectorID::new(2, 0), FieldCell::new(5, 0));
+		let path = query.find_path_cells(source, target).unwrap();
+		assert_eq!(source, path[0]);
+		assert_eq!(target, *path.last().unwrap());
+		// the only gap in the wall is the bottom row of the map
+		assert!(path.contains(&(SectorID::new(1, 2), FieldCell::new(5, 9))));
+	}
+	#[test]
+	#[cfg(feature = "2d")]
+	fn world_path_xy() {
+		let mut world = setup_world();
+		let mut state: SystemState<PathQuery> = SystemState::new(&mut world);
+		let query = state.get(&world);
+		let start = Vec2::new(-10.0, 10.0);
+		let goal = Vec2::new(10.0, 10.0);
+		let path = query.find_path_xy(start, goal).unwrap();
+		assert_eq!(start, path[0]);
+		assert_eq!(goal, *path.last().unwrap());
+		// dips to the bottom of the map to get around the wall
+		assert!(path.iter().any(|p| p.y < -13.0));
+	}
+	#[test]
+	#[cfg(feature = "2d")]
+	fn world_path_xy_layered() {
+		let (_, mut sector_portals, mut sector_cost_fields, map_dimensions) = setup_fields();
+		// an open floor above the walled off ground
+		sector_cost_fields.insert_sector_layer(1, &SectorCostFields::new(&map_dimensions));
+		sector_portals.add_sector_layer(1, &sector_cost_fields, &map_dimensions);
+		let graph = PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		let mut world = World::new();
+		world.spawn((
+			(graph, sector_portals, sector_cost_fields, map_dimensions),
+			RouteCache::default(),
+			FlowFieldCache::default(),
+		));
+		let mut state: SystemState<PathQuery> = SystemState::new(&mut world);
+		let query = state.get(&world);
+		let start = Vec2::new(-10.0, 10.0);
+		let goal = Vec2::new(10.0, 10.0);
+		let path = query.find_path_xy((start, 1), (goal, 1)).unwrap();
+		assert_eq!(start, path[0]);
+		assert_eq!(goal, *path.last().unwrap());
+		// nothing is in the way on the upper floor
+		assert!(path.iter().all(|p| p.y > 5.0));
+		let path = query.find_path_xy(start, goal).unwrap();
+		assert!(path.iter().any(|p| p.y < -13.0));
+	}
+	#[test]
+	#[cfg(feature = "2d")]
+	fn world_path_xy_outside_map() {
+		let mut world = setup_world();
+		let mut state: SystemState<PathQuery> = SystemState::new(&mut world);
+		let query = state.get(&world);
+		let result = query.find_path_xy(Vec2::new(-100.0, 0.0), Vec2::new(10.0, 10.0));
+		assert!(result.is_none());
+	}
+	#[test]
+	#[cfg(feature = "3d")]
+	fn world_path_xyz() {
+		let mut world = setup_world();
+		let mut state: SystemState<PathQuery> = SystemState::new(&mut world);
+		let query = state.get(&world);
+		let start = Vec3::new(-10.0, 0.0, -10.0);
+		let goal = Vec3::new(10.0, 0.0, -10.0);
+		let path = query.find_path_xyz(start, goal).unwrap();
+		assert_eq!(start, path[0]);
+		assert_eq!(goal, *path.last().unwrap());
+		// dips to the bottom of the map to get around the wall
+		assert!(path.iter().any(|p| p.z > 13.0));
+	}
+	/// Create a [World] containing a 30x30 map with a generated route and [FlowField]s from the left side of the map to the right, along with a [RouteHandle] referring to the route
+	fn setup_world_with_route() -> (World, RouteMetadata, RouteHandle) {
+		let (graph, sector_portals, sector_cost_fields, map_dimensions) = setup_fields();
+		let source = (SectorID::new(0, 0), FieldCell::new(5, 5));
+		let target = (SectorID::new(2, 0), FieldCell::new(5, 5));
+		let mut path = graph
+			.find_best_path(source, target, &sector_portals, &sector_cost_fields)
+			.unwrap();
+		filter_path(&mut path, target.1);
+		let mut path_from_goal = path.clone();
+		path_from_goal.reverse();
+		// build the flow fields in the same manner as the plugin
+		let mut flow_cache = FlowFieldCache::default();
+		let mut int_fields: Vec<(SectorID, Vec<FieldCell>, IntegrationField)> = Vec::new();
+		for (i, (sector_id, goal)) in path_from_goal.iter().enumerate() {
+			let goals = if i == 0 {
+				vec![*goal]
+			} else {
+				sector_portals
+					.get()
+					.get(sector_id)
+					.unwrap()
+					.expand_portal_into_goals(
+						&sector_cost_fields,
+						sector_id,
+						goal,
+						&path_from_goal[i - 1].0,
+						&map_dimensions,
+					)
+			};
+			let mut int_field = IntegrationField::new(&goals);
+			int_field.calculate_field(
+				&goals,
+				sector_cost_fields.get_scaled().get(sector_id).unwrap(),
+			);
+			let mut flow_field = FlowField::default();
+			if i == 0 {
+				flow_field.calculate(&goals, None, &int_field);
+			} else {
+				let dir =
+					Ordinal::sector_to_sector_direction(int_fields[i - 1].0, *sector_id).unwrap();
+				flow_field.calculate(&goals, Some((dir, &int_fields[i - 1].2)), &int_field);
+			}
+			flow_cache.insert_field(*sector_id, *goal, Duration::default(), flow_field);
+			int_fields.push((*sector_id, goals, int_field));
+		}
+		let mut route_cache = RouteCache::default();
+		route_cache.insert_route(
+			source.0,
+			source.1,
+			target.0,
+			target.1,
+			Duration::default(),
+			path,
+		);
+		let metadata =
+			RouteMetadata::new(source.0, source.1, target.0, target.1, Duration::default());
+		let handle = RouteHandle::new();
+		route_cache.insert_handle(handle, metadata);
+		let mut world = World::new();
+		world.spawn((
+			graph,
+			sector_portals,
+			sector_cost_fields,
+			map_dimensions,
+			route_cache,
+			flow_cache,
+		));
+		(world, metadata, handle)
+	}
+	#[test]
+	fn route_lookup() {
+		let (mut world, metadata, handle) = setup_world_with_route();
+		let mut state: SystemState<FlowFieldQuery> = SystemState::new(&mut world);
+		let query = state.get(&world);
+		let result = query.route_for_cells(
+			(SectorID::new(0, 0), FieldCell::new(5, 5)),
+			(SectorID::new(2, 0), FieldCell::new(5, 5)),
+		);
+		assert_eq!(Some(metadata), result);
+		let result = query.route_for_cells(
+			(SectorID::new(0, 1), FieldCell::new(5, 5)),
+			(SectorID::new(2, 0), FieldCell::new(5, 5)),
+		);
+		assert!(result.is_none());
+		assert_eq!(Some(metadata), query.route_for_handle(handle));
+		assert!(query.route_for_handle(RouteHandle::new()).is_none());
+	}
+	#[test]
+	#[cfg(feature = "2d")]
+	fn route_lookup_xy() {
+		let (mut world, metadata, _) = setup_world_with_route();
+		let mut state: SystemState<FlowFieldQuery> = SystemState::new(&mut world);
+		let query = state.get(&world);
+		let result = query.route_for(Vec2::new(-9.5, 9.5), Vec2::new(10.5, 9.5));
+		assert_eq!(Some(metadata), result);
+		let result = query.route_for(Vec2::new(-9.5, -0.5), Vec2::new(10.5, 9.5));
+		assert!(result.is_none());
+		// the same positions on another layer
+		let result = query.route_for((Vec2::new(-9.5, 9.5), 1), (Vec2::new(10.5, 9.5), 1));
+		assert!(result.is_none());
+		let result = query.route_for((Vec2::new(-9.5, 9.5), 0), (Vec2::new(10.5, 9.5), 0));
+		assert_eq!(Some(metadata), result);
+	}
+	#[test]
+	fn cell_pathable() {
+		let mut world = setup_world();
+		let mut state: SystemState<FlowFieldQuery> = SystemState::new(&mut world);
+		let query = state.get(&world);
+		assert!(query.is_cell_pathable(SectorID::new(0, 0), FieldCell::new(5, 5)));
+		assert!(!query.is_cell_pathable(SectorID::new(1, 0), FieldCell::new(5, 5)));
+	}
+	#[test]
+	#[cfg(feature = "2d")]
+	fn direction_xy() {
+		let (mut world, metadata, handle) = setup_world_with_route();
+		let unknown = RouteHandle::new();
+		// a route which hasn't been generated
+		let missing = RouteMetadata::new(
+			SectorID::new(0, 2),
+			FieldCell::new(5, 5),
+			SectorID::new(2, 0),
+			FieldCell::new(5, 5),
+			Duration::default(),
+		);
+		world
+			.query::<&mut RouteCache>()
+			.single_mut(&mut world)
+			.insert_handle(unknown, missing);
+		let mut state: SystemState<FlowFieldQuery> = SystemState::new(&mut world);
+		let query = state.get(&world);
+		// route passes under the wall so an actor in the start sector heads downwards
+		let direction = query.direction_at(Vec2::new(-10.0, 10.0), &handle).unwrap();
+		assert!(direction.y < 0.0);
+		assert_eq!(Some(metadata), query.route_for_handle(handle));
+		let direction = query.direction_at(Vec2::new(-10.0, 10.0), &unknown);
+		assert!(direction.is_none());
+	}
+	#[test]
+	#[cfg(feature = "2d")]
+	fn pathable_xy() {
+		let mut world = setup_world();
+		let mut state: SystemState<FlowFieldQuery> = SystemState::new(&mut world);
+		let query = state.get(&world);
+		assert!(query.is_pathable(Vec2::new(-10.0, 10.0)));
+		// middle of the wall
+		assert!(!query.is_pathable(Vec2::new(0.5, 10.0)));
+		// outside of the world
+		assert!(!query.is_pathable(Vec2::new(-100.0, 10.0)));
+	}
+	#[test]
+	#[cfg(feature = "3d")]
+	fn direction_xyz() {
+		let (mut world, _, handle) = setup_world_with_route();
+		let mut state: SystemState<FlowFieldQuery> = SystemState::new(&mut world);
+		let query = state.get(&world);
+		// route passes under the wall so an actor in the start sector heads towards +z
+		let direction = query
+			.direction_at(Vec3::new(-10.0, 0.0, -10.0), &handle)
+			.unwrap();
+		assert!(direction.z > 0.0);
+	}
+	#[test]
+	#[cfg(feature = "3d")]
+	fn direction_and_path_xyz_follow_elevation() {
+		let (mut world, _, handle) = setup_world_with_route();
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		// terrain rising towards +z
+		let elevation = ElevationField::from_map_cells(&map_dimensions, |_, row| row as f32 * 0.5);
+		let entity = world
+			.query_filtered::<Entity, With<MapDimensions>>()
+			.single(&world);
+		world.entity_mut(entity).insert(elevation);
+		let mut state: SystemState<(FlowFieldQuery, PathQuery)> = SystemState::new(&mut world);
+		let (flow_query, path_query) = state.get(&world);
+		let direction = flow_query
+			.direction_at(Vec3::new(-10.0, 0.0, -10.0), &handle)
+			.unwrap();
+		assert!(direction.z > 0.0);
+		assert!(direction.y > 0.0);
+		assert!((1.0 - direction.length()).abs() < 0.001);
+		let path = path_query
+			.find_path_xyz(Vec3::new(-10.0, 0.0, -10.0), Vec3::new(10.0, 0.0, -10.0))
+			.unwrap();
+		// each intermediate point sits on the terrain
+		for point in path[1..path.len() - 1].iter() {
+			let row = (point.z + 15.0).floor();
+			assert_eq!(row * 0.5, point.y);
+		}
+	}
+	#[test]
+	#[cfg(feature = "3d")]
+	fn pathable_xyz() {
+		let mut world = setup_world();
+		let mut state: SystemState<FlowFieldQuery> = SystemState::new(&mut world);
+		let query = state.get(&world);
+		assert!(query.is_pathable(Vec3::new(-10.0, 0.0, -10.0)));
+		assert!(!query.is_pathable(Vec3::new(0.5, 0.0, -10.0)));
+	}
+}
@@ -0,0 +1,696 @@
+//! An optional steering layer which drives [FlowFieldAgent]s along the routes and [FlowField]s produced by the [FlowFieldTilesPlugin].
+//!
+//! The steering plugin does not move anything itself, instead each [FlowFieldAgent] is given a desired velocity every frame which can be applied to a `Transform`, a physics body or a character controller in whatever way suits the game.
+//!
+
+use std::collections::BTreeSet;
+
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Label for the steering systems, they run in [PreUpdate] after [OrderingSet::Calculate]
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct SteeringSet;
+
+/// The plane in which [FlowFieldAgent]s move
+///
+/// Defaults to [SteeringPlane::XY] when the `2d` feature is enabled
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SteeringPlane {
+	/// A 2d world where positions are read from the `x` and `y` of a `Transform`
+	#[cfg(feature = "2d")]
+	#[default]
+	XY,
+	/// A 3d world where positions are read from the `x` and `z` of a `Transform`
+	#[cfg(feature = "3d")]
+	#[cfg_attr(not(feature = "2d"), default)]
+	XZ,
+}
+
+impl SteeringPlane {
+	/// Find the sector and field cell of a world `position`
+	pub fn get_sector_and_field_cell(
+		&self,
+		map_dimensions: &MapDimensions,
+		position: Vec3,
+	) -> Option<(SectorID, FieldCell)> {
+		match self {
+			#[cfg(feature = "2d")]
+			SteeringPlane::XY => map_dimensions.get_sector_and_field_cell_from_xy(position.truncate()),
+			#[cfg(feature = "3d")]
+			SteeringPlane::XZ => map_dimensions.get_sector_and_field_cell_from_xyz(position),
+		}
+	}
+	/// Find the sector of a sector `layer` and the field cell of a world `position`
+	pub fn get_sector_and_field_cell_on_layer(
+		&self,
+		map_dimensions: &MapDimensions,
+		position: Vec3,
+		layer: u32,
+	) -> Option<(SectorID, FieldCell)> {
+		self.get_sector_and_field_cell(map_dimensions, position)
+			.map(|(sector, field_cell)| (sector.with_layer(layer), field_cell))
+	}
+	/// Find the world position of the centre of a field cell
+	pub fn get_position(
+		&self,
+		map_dimensions: &MapDimensions,
+		sector: SectorID,
+		field_cell: FieldCell,
+	) -> Option<Vec3> {
+		match self {
+			#[cfg(feature = "2d")]
+			SteeringPlane::XY => map_dimensions
+				.get_xy_from_field_sector(sector, field_cell)
+				.map(|p| p.extend(0.0)),
+			#[cfg(feature = "3d")]
+			SteeringPlane::XZ => map_dimensions.get_xyz_from_field_sector(sector, field_cell),
+		}
+	}
+	/// Decode a [FlowField] cell value into a unit direction within the plane
+	fn get_direction_from_bits(&self, cell_value: u8) -> Vec3 {
+		match self {
+			#[cfg(feature = "2d")]
+			SteeringPlane::XY => get_2d_direction_unit_vector_from_bits(cell_value).extend(0.0),
+			#[cfg(feature = "3d")]
+			SteeringPlane::XZ => get_3d_direction_unit_vector_from_bits(cell_value),
+		}
+	}
+	/// Find the continuous position of a world `position` within the grid of [FieldCell]s spanning the whole map, see [MapDimensions::get_grid_position_from_xy] and [MapDimensions::get_grid_position_from_xyz]
+	pub fn get_grid_position(&self, map_dimensions: &MapDimensions, position: Vec3) -> Vec2 {
+		match self {
+			#[cfg(feature = "2d")]
+			SteeringPlane::XY => map_dimensions.get_grid_position_from_xy(position.truncate()),
+			#[cfg(feature = "3d")]
+			SteeringPlane::XZ => map_dimensions.get_grid_position_from_xyz(position),
+		}
+	}
+	/// Get the two components of a vector which lie in the plane, `(x, y)` or `(x, z)`
+	pub fn project(&self, v: Vec3) -> Vec2 {
+		match self {
+			#[cfg(feature = "2d")]
+			SteeringPlane::XY => Vec2::new(v.x, v.y),
+			#[cfg(feature = "3d")]
+			SteeringPlane::XZ => Vec2::new(v.x, v.z),
+		}
+	}
+	/// Remove the component of a vector which doesn't lie in the plane
+	pub fn flatten(&self, v: Vec3) -> Vec3 {
+		match self {
+			#[cfg(feature = "2d")]
+			SteeringPlane::XY => Vec3::new(v.x, v.y, 0.0),
+			#[cfg(feature = "3d")]
+			SteeringPlane::XZ => Vec3::new(v.x, 0.0, v.z),
+		}
+	}
+}
+
+/// Attach to any entity with a `Transform` that should be steered by [FlowField]s. Set a target with [FlowFieldAgent::set_target] and read [FlowFieldAgent::get_desired_velocity] each frame to move the entity.
+///
+/// In a map with several sector layers (such as the floors of a building) the agent is on the ground layer unless [FlowFieldAgent::set_layer] says otherwise, and it moves onto the next layer of its route when it reaches the end of a [LayerConnection]
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct FlowFieldAgent {
+	/// Where the agent is trying to get to
+	target: Option<Vec3>,
+	/// The sector layer the target is on
+	target_layer: u32,
+	/// The sector layer the agent is on
+	layer: u32,
+	/// The speed an agent would like to move at
+	max_speed: f32,
+	/// Distance from the target where the agent is considered to have arrived
+	arrival_radius: f32,
+	/// Handle of the route requested for the current target, it keeps referring to the route while it is regenerated after a [CostField] change
+	route_handle: Option<RouteHandle>,
+	/// The route being followed
+	#[reflect(ignore)]
+	route: Option<RouteMetadata>,
+	/// The sector-goals of the route which haven't been passed through yet
+	portal_route: Vec<(SectorID, FieldCell)>,
+	/// Velocity the agent should move with to follow the route
+	desired_velocity: Vec3,
+	/// Whether the agent has reached its last target
+	has_arrived: bool,
+}
+
+impl Default for FlowFieldAgent {
+	fn default() -> Self {
+		FlowFieldAgent {
+			target: None,
+			target_layer: 0,
+			layer: 0,
+			max_speed: 1.0,
+			arrival_radius: 1.0,
+			route_handle: None,
+			route: None,
+			portal_route: Vec::new(),
+			desired_velocity: Vec3::ZERO,
+			has_arrived: false,
+		}
+	}
+}
+
+impl FlowFieldAgent {
+	/// Create a new [FlowFieldAgent] which will move at `max_speed` and consider itself arrived when within `arrival_radius` of its target
+	pub fn new(max_speed: f32, arrival_radius: f32) -> Self {
+		FlowFieldAgent {
+			max_speed,
+			arrival_radius,
+			..default()
+		}
+	}
+	/// Get the target the agent is moving towards
+	pub fn get_target(&self) -> Option<Vec3> {
+		self.target
+	}
+	/// Give the agent a new target on the sector layer it is currently on, any existing route is discarded. In a 2d world the `z` of `target` is ignored and in a 3d world the `y`
+	pub fn set_target(&mut self, target: Vec3) {
+		self.set_target_on_layer(target, self.layer);
+	}
+	/// Give the agent a new target on a sector `layer`, any existing route is discarded
+	pub fn set_target_on_layer(&mut self, target: Vec3, layer: u32) {
+		self.target = Some(target);
+		self.target_layer = layer;
+		self.has_arrived = false;
+		self.clear_route();
+	}
+	/// Get the sector layer of the target
+	pub fn get_target_layer(&self) -> u32 {
+		self.target_layer
+	}
+	/// Get the sector layer the agent is on
+	pub fn get_layer(&self) -> u32 {
+		self.layer
+	}
+	/// Place the agent on a sector `layer`, i.e when it has been moved to another floor by the game. Any existing route is discarded
+	pub fn set_layer(&mut self, layer: u32) {
+		if self.layer != layer {
+			self.layer = layer;
+			self.clear_route();
+		}
+	}
+	/// Stop the agent from pathing
+	pub fn clear_target(&mut self) {
+		self.target = None;
+		self.desired_velocity = Vec3::ZERO;
+		self.clear_route();
+	}
+	/// Get the speed the agent would like to move at
+	pub fn get_max_speed(&self) -> f32 {
+		self.max_speed
+	}
+	/// Set the speed the agent would like to move at
+	pub fn set_max_speed(&mut self, max_speed: f32) {
+		self.max_speed = max_speed;
+	}
+	/// Get the distance from the target where the agent is considered to have arrived
+	pub fn get_arrival_radius(&self) -> f32 {
+		self.arrival_radius
+	}
+	/// Get the route the agent is following
+	pub fn get_route(&self) -> Option<&RouteMetadata> {
+		self.route.as_ref()
+	}
+	/// Get the handle of the route requested for the current target
+	pub fn get_route_handle(&self) -> Option<RouteHandle> {
+		self.route_handle
+	}
+	/// Get the velocity the agent should move with to follow its route
+	pub fn get_desired_velocity(&self) -> Vec3 {
+		self.desired_velocity
+	}
+	/// Override the desired velocity, this is recalculated by [steer_agents] each frame so is only useful for systems that adjust it afterwards (such as local avoidance)
+	pub fn set_desired_velocity(&mut self, velocity: Vec3) {
+		self.desired_velocity = velocity;
+	}
+	/// Whether the agent has reached its last target
+	pub fn has_arrived(&self) -> bool {
+		self.has_arrived
+	}
+	/// Discard the route and its handle so that a fresh one is requested
+	fn clear_route(&mut self) {
+		self.route_handle = None;
+		self.forget_route();
+	}
+	/// Stop following the route while keeping its handle, i.e while it is regenerated
+	fn forget_route(&mut self) {
+		self.route = None;
+		self.portal_route.clear();
+	}
+}
+
+/// Emitted when a [FlowFieldAgent] reaches its target
+#[derive(Event, Debug)]
+pub struct EventAgentArrived(pub Entity);
+
+/// Adds the steering of [FlowFieldAgent]s, requires the [FlowFieldTilesPlugin] to also be added
+#[derive(Default)]
+pub struct FlowFieldSteeringPlugin {
+	/// The plane in which agents move
+	pub plane: SteeringPlane,
+}
+
+impl Plugin for FlowFieldSteeringPlugin {
+	#[cfg(not(tarpaulin_include))]
+	fn build(&self, app: &mut App) {
+		app.register_type::<FlowFieldAgent>()
+			.insert_resource(self.plane)
+			.add_event::<EventAgentArrived>()
+			.configure_sets(PreUpdate, SteeringSet.after(OrderingSet::Calculate))
+			.add_systems(
+				PreUpdate,
+				(
+					detect_agent_arrival,
+					update_agent_routes,
+					request_agent_routes,
+					steer_agents,
+				)
+					.chain()
+					.in_set(SteeringSet),
+			);
+	}
+}
+
+/// Mark agents within their arrival radius as arrived and stop them, the routes they requested are released so that unused [FlowField]s can be freed
+#[cfg(not(tarpaulin_include))]
+pub fn detect_agent_arrival(
+	mut agents: Query<(Entity, &Transform, &mut FlowFieldAgent)>,
+	plane: Res<SteeringPlane>,
+	mut event: EventWriter<EventAgentArrived>,
+	mut event_cancel: EventWriter<EventCancelPathRequest>,
+) {
+	for (entity, tform, mut agent) in &mut agents {
+		if let Some(target) = agent.target.filter(|_| agent.layer == agent.target_layer) {
+			let offset = plane.flatten(target - tform.translation);
+			if offset.length_squared() <= agent.arrival_radius * agent.arrival_radius {
+				agent.clear_target();
+				agent.has_arrived = true;
+				event.send(EventAgentArrived(entity));
+				event_cancel.send(EventCancelPathRequest::Requester(entity));
+			}
+		}
+	}
+}
+
+/// Keep the route of each agent up to date from the [EventRouteChanged]s of the [RouteHandle] it requested. An agent picks up its route once it has been generated, or regenerated after a [CostField] change, and has its target cleared when no route to the target exists.
+///
+/// While its route is being regenerated an agent waits without one, if the route has been removed for any other reason a new one is requested from the current position of the agent
+#[cfg(not(tarpaulin_include))]
+pub fn update_agent_routes(
+	mut agents: Query<&mut FlowFieldAgent>,
+	route_q: Query<&RouteCache>,
+	mut events: EventReader<EventRouteChanged>,
+) {
+	let changed: BTreeSet<RouteHandle> = events.read().map(|event| event.0).collect();
+	let Ok(route_cache) = route_q.get_single() else {
+		return;
+	};
+	for mut agent in &mut agents {
+		let Some(handle) = agent.route_handle else {
+			continue;
+		};
+		if let Some((metadata, route)) = route_cache.get_route_by_handle(handle) {
+			if changed.contains(&handle) || agent.route.is_none() {
+				agent.route = Some(*metadata);
+				agent.portal_route = route.clone();
+			}
+			continue;
+		}
+		match route_cache.get_handle_metadata(handle) {
+			Some(metadata) if route_cache.is_pending(metadata) => {
+				if agent.route.is_some() {
+					debug!("Agent route is being regenerated");
+					agent.forget_route();
+				}
+			}
+			Some(_) => {
+				debug!("Agent route has been removed, requesting a new one");
+				agent.clear_route();
+			}
+			None if changed.contains(&handle) => {
+				warn!("No route exists to the agent target {:?}", agent.target);
+				agent.clear_target();
+			}
+			None => {
+				debug!("Agent route has been released, requesting a new one");
+				agent.clear_route();
+			}
+		}
+	}
+}
+
+/// Agents with a target but no [RouteHandle] request a route, recorded with the agent as the requester so that the route is kept while the agent uses it. Any route the agent previously requested is released
+#[cfg(not(tarpaulin_include))]
+pub fn request_agent_routes(
+	mut agents: Query<(Entity, &Transform, &mut FlowFieldAgent)>,
+	map_q: Query<&MapDimensions>,
+	plane: Res<SteeringPlane>,
+	mut event: EventWriter<EventPathRequest>,
+	mut event_cancel: EventWriter<EventCancelPathRequest>,
+) {
+	let Ok(map_dimensions) = map_q.get_single() else {
+		return;
+	};
+	for (entity, tform, mut agent) in &mut agents {
+		let Some(target) = agent.target else {
+			continue;
+		};
+		if agent.route_handle.is_some() {
+			continue;
+		}
+		let Some((source_sector, source_field)) = plane.get_sector_and_field_cell_on_layer(
+			map_dimensions,
+			tform.translation,
+			agent.layer,
+		) else {
+			continue;
+		};
+		let Some((target_sector, target_goal)) =
+			plane.get_sector_and_field_cell_on_layer(map_dimensions, target, agent.target_layer)
+		else {
+			warn!("Agent target {:?} is outside of the world", target);
+			agent.clear_target();
+			continue;
+		};
+		let request =
+			EventPathRequest::new(source_sector, source_field, target_sector, target_goal)
+				.with_requester(entity);
+		agent.route_handle = Some(request.get_handle());
+		// cancellations are processed before requests so the new route is kept
+		event_cancel.send(EventCancelPathRequest::Requester(entity));
+		event.send(request);
+	}
+}
+
+/// Calculate the desired velocity of each agent. The [FlowField] of the current sector is used when it has been generated, otherwise the agent heads towards the portal (or goal) of its route in the current sector until the [FlowField] is ready
+#[cfg(not(tarpaulin_include))]
+pub fn steer_agents(
+	mut agents: Query<(&Transform, &mut FlowFieldAgent)>,
+	cache_q: Query<(&FlowFieldCache, &MapDimensions)>,
+	plane: Res<SteeringPlane>,
+) {
+	let Ok((flow_cache, map_dimensions)) = cache_q.get_single() else {
+		return;
+	};
+	for (tform, mut agent) in &mut agents {
+		let (Some(target), Some(_)) = (agent.target, agent.route) else {
+			agent.desired_velocity = Vec3::ZERO;
+			continue;
+		};
+		let Some((sector, field_cell)) = plane.get_sector_and_field_cell_on_layer(
+			map_dimensions,
+			tform.translation,
+			agent.layer,
+		) else {
+			agent.desired_velocity = Vec3::ZERO;
+			continue;
+		};
+		// trim the route as the agent makes progress so that a sector which is
+		// visited more than once uses the correct goal
+		while agent.portal_route.len() > 1 && agent.portal_route[0].0 != sector {
+			agent.portal_route.remove(0);
+		}
+		let Some(index) = agent.portal_route.iter().position(|(s, _)| *s == sector) else {
+			// the agent has left its route, perhaps knocked into a different sector
+			debug!("Agent has left its route, requesting a new one");
+			agent.desired_velocity = Vec3::ZERO;
+			agent.clear_route();
+			continue;
+		};
+		let goal = agent.portal_route[index].1;
+		// reaching the end of a layer connection moves the agent onto the layer the route continues on
+		if let Some(&(next_sector, _)) = agent.portal_route.get(index + 1) {
+			if field_cell == goal && next_sector.get_layer() != sector.get_layer() {
+				agent.layer = next_sector.get_layer();
+				agent.portal_route.remove(index);
+				agent.desired_velocity = Vec3::ZERO;
+				continue;
+			}
+		}
+		let direction = if let Some(field) = flow_cache.get_field(sector, goal) {
+			let cell_value = field.get_field_cell_value(field_cell);
+			if has_line_of_sight(cell_value) {
+				plane
+					.flatten(target - tform.translation)
+					.normalize_or_zero()
+			} else {
+				plane.get_direction_from_bits(cell_value)
+			}
+		} else if let Some(goal_position) = plane.get_position(map_dimensions, sector, goal) {
+			// flow field isn't ready yet, head towards the portal of the route
+			plane
+				.flatten(goal_position - tform.translation)
+				.normalize_or_zero()
+		} else {
+			Vec3::ZERO
+		};
+		agent.desired_velocity = direction * agent.max_speed;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Create an [App] with a 30x30 map where the middle column of sectors is walled off apart from the bottom row of cells
+	fn setup_app() -> App {
+		let mut app = App::new();
+		app.add_plugins((
+			MinimalPlugins,
+			FlowFieldTilesPlugin,
+			FlowFieldSteeringPlugin::default(),
+		));
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5);
+		let mut sector_cost_fields = SectorCostFields::new(&map_dimensions);
+		for sector_row in 0..3 {
+			for row in 0..FIELD_RESOLUTION {
+				if sector_row == 2 && row == FIELD_RESOLUTION - 1 {
+					continue;
+				}
+				sector_cost_fields.set_field_cell_value(
+					SectorID::new(1, sector_row),
+					255,
+					FieldCell::new(5, row),
+					&map_dimensions,
+				);
+			}
+		}
+		let mut sector_portals = SectorPortals::new(30, 30, 10);
+		for sector_id in sector_cost_fields.get_scaled().keys() {
+			sector_portals.update_portals(*sector_id, &sector_cost_fields, &map_dimensions);
+		}
+		bundle.portal_graph =
+			PortalGraph::new(&sector_portals, &sector_cost_fields, &map_dimensions);
+		bundle.sector_cost_fields = sector_cost_fields;
+		bundle.sector_portals = sector_portals;
+		app.world_mut().spawn(bundle);
+		app
+	}
+	/// Position of an agent in the top left sector
+	fn start_position(plane: SteeringPlane) -> Vec3 {
+		match plane {
+			#[cfg(feature = "2d")]
+			SteeringPlane::XY => Vec3::new(-10.0, 10.0, 0.0),
+			#[cfg(feature = "3d")]
+			SteeringPlane::XZ => Vec3::new(-10.0, 0.0, -10.0),
+		}
+	}
+	/// Position of a target in the top right sector
+	fn target_position(plane: SteeringPlane) -> Vec3 {
+		match plane {
+			#[cfg(feature = "2d")]
+			SteeringPlane::XY => Vec3::new(10.0, 10.0, 0.0),
+			#[cfg(feature = "3d")]
+			SteeringPlane::XZ => Vec3::new(10.0, 0.0, -10.0),
+		}
+	}
+	#[test]
+	fn agent_receives_route_and_velocity() {
+		let mut app = setup_app();
+		let plane = SteeringPlane::default();
+		let mut agent = FlowFieldAgent::new(5.0, 1.0);
+		agent.set_target(target_position(plane));
+		let entity = app
+			.world_mut()
+			.spawn((Transform::from_translation(start_position(plane)), agent))
+			.id();
+		for _ in 0..10 {
+			app.update();
+		}
+		let agent = app.world().get::<FlowFieldAgent>(entity).unwrap();
+		assert!(agent.get_route().is_some());
+		let velocity = agent.get_desired_velocity();
+		assert!((velocity.length() - 5.0).abs() < 0.001);
+		// wall is in the way so the agent must first head away from the target
+		let towards_target = target_position(plane) - start_position(plane);
+		assert!(velocity.dot(towards_target) <= 0.0);
+	}
+	#[test]
+	fn agent_arrives() {
+		let mut app = setup_app();
+		let plane = SteeringPlane::default();
+		let mut agent = FlowFieldAgent::new(5.0, 1.0);
+		agent.set_target(target_position(plane));
+		let entity = app
+			.world_mut()
+			.spawn((Transform::from_translation(start_position(plane)), agent))
+			.id();
+		for _ in 0..5 {
+			app.update();
+		}
+		app.world_mut()
+			.get_mut::<Transform>(entity)
+			.unwrap()
+			.translation = target_position(plane);
+		app.update();
+		let agent = app.world().get::<FlowFieldAgent>(entity).unwrap();
+		assert!(agent.has_arrived());
+		assert!(agent.get_target().is_none());
+		assert_eq!(Vec3::ZERO, agent.get_desired_velocity());
+		let events = app.world().resource::<Events<EventAgentArrived>>();
+		assert_eq!(1, events.len());
+		// the route and its fields are released once the agent has arrived
+		app.update();
+		let mut cache_q = app.world_mut().query::<(&RouteCache, &FlowFieldCache)>();
+		let (route_cache, flow_cache) = cache_q.single(app.world());
+		assert!(route_cache.get().is_empty());
+		assert!(flow_cache.get().is_empty());
+	}
+	#[test]
+	fn agent_rerequests_invalidated_route() {
+		let mut app = setup_app();
+		let plane = SteeringPlane::default();
+		let mut agent = FlowFieldAgent::new(5.0, 1.0);
+		agent.set_target(target_position(plane));
+		let entity = app
+			.world_mut()
+			.spawn((Transform::from_translation(start_position(plane)), agent))
+			.id();
+		for _ in 0..5 {
+			app.update();
+		}
+		let route = *app
+			.world()
+			.get::<FlowFieldAgent>(entity)
+			.unwrap()
+			.get_route()
+			.unwrap();
+		// move the agent and then invalidate the route it was following
+		app.world_mut()
+			.get_mut::<Transform>(entity)
+			.unwrap()
+			.translation = start_position(plane) + plane.flatten(Vec3::new(1.0, 1.0, 1.0));
+		let mut route_q = app.world_mut().query::<&mut RouteCache>();
+		route_q.single_mut(app.world_mut()).remove_route(route);
+		for _ in 0..5 {
+			app.update();
+		}
+		let agent = app.world().get::<FlowFieldAgent>(entity).unwrap();
+		let new_route = agent.get_route().unwrap();
+		// the new route starts from where the agent is now
+		assert_ne!(
+			route.get_source_field_cell(),
+			new_route.get_source_field_cell()
+		);
+	}
+	#[test]
+	fn agent_moves_between_layers() {
+		let mut app = App::new();
+		app.add_plugins((
+			MinimalPlugins,
+			FlowFieldTilesPlugin,
+			FlowFieldSteeringPlugin::default(),
+		));
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let stairs = (SectorID::new(0, 0), FieldCell::new(2, 2));
+		let bundle = FlowFieldTilesBundle::new(30, 30, 10, 0.5)
+			.with_layer(SectorCostFields::new(&map_dimensions))
+			.with_layer_connection(LayerConnection::new(
+				stairs,
+				(SectorID::new_layered(0, 0, 1), stairs.1),
+				10,
+			));
+		app.world_mut().spawn(bundle);
+		let plane = SteeringPlane::default();
+		let mut agent = FlowFieldAgent::new(5.0, 1.0);
+		agent.set_target_on_layer(target_position(plane), 1);
+		let entity = app
+			.world_mut()
+			.spawn((Transform::from_translation(start_position(plane)), agent))
+			.id();
+		for _ in 0..5 {
+			app.update();
+		}
+		let stairs_position = plane
+			.get_position(&map_dimensions, stairs.0, stairs.1)
+			.unwrap();
+		let agent = app.world().get::<FlowFieldAgent>(entity).unwrap();
+		assert_eq!(0, agent.get_layer());
+		assert_eq!(
+			1,
+			agent.get_route().unwrap().get_target_sector().get_layer()
+		);
+		// heads for the stairs rather than the target
+		let velocity = agent.get_desired_velocity();
+		assert!(velocity.dot(stairs_position - start_position(plane)) > 0.0);
+		assert!(velocity.dot(target_position(plane) - start_position(plane)) <= 0.0);
+		// reaching the stairs puts the agent on the upper floor
+		app.world_mut()
+			.get_mut::<Transform>(entity)
+			.unwrap()
+			.translation = stairs_position;
+		for _ in 0..2 {
+			app.update();
+		}
+		let agent = app.world().get::<FlowFieldAgent>(entity).unwrap();
+		assert_eq!(1, agent.get_layer());
+		assert!(!agent.has_arrived());
+		let velocity = agent.get_desired_velocity();
+		assert!(velocity.dot(target_position(plane) - stairs_position) > 0.0);
+	}
+	#[test]
+	fn agent_target_cleared_when_unreachable() {
+		let mut app = setup_app();
+		let plane = SteeringPlane::default();
+		// wall in a cell of the starting sector and target it
+		let map_dimensions = MapDimensions::new(30, 30, 10, 0.5);
+		let mut cost_q = app.world_mut().query::<&mut SectorCostFields>();
+		let mut sector_cost_fields = cost_q.single_mut(app.world_mut());
+		for column in 1..4 {
+			for row in 1..4 {
+				if column == 2 && row == 2 {
+					continue;
+				}
+				sector_cost_fields.set_field_cell_value(
+					SectorID::new(0, 0),
+					255,
+					FieldCell::new(column, row),
+					&map_dimensions,
+				);
+			}
+		}
+		let target = match plane {
+			#[cfg(feature = "2d")]
+			SteeringPlane::XY => Vec3::new(-12.5, 12.5, 0.0),
+			#[cfg(feature = "3d")]
+			SteeringPlane::XZ => Vec3::new(-12.5, 0.0, -12.5),
+		};
+		let mut agent = FlowFieldAgent::new(5.0, 1.0);
+		agent.set_target(target);
+		let entity = app
+			.world_mut()
+			.spawn((Transform::from_translation(start_position(plane)), agent))
+			.id();
+		for _ in 0..5 {
+			app.update();
+		}
+		let agent = app.world().get::<FlowFieldAgent>(entity).unwrap();
+		assert!(agent.get_target().is_none());
+		assert!(agent.get_route_handle().is_none());
+		assert!(!agent.has_arrived());
+		let mut route_q = app.world_mut().query::<&RouteCache>();
+		assert!(route_q.single(app.world()).get_requests().is_empty());
+	}
+}
@@ -3,16 +3,42 @@
 
 #[doc(hidden)]
 pub use crate::flowfields::{
-	fields::{cost_field::*, flow_field::*, integration_field::*, *},
+	fields::{cost_field::*, density_field::*, flow_field::*, integration_field::*, *},
 	portal::portal_graph::*,
 	portal::portals::*,
-	sectors::{sector_cost::*, sector_portals::*, *},
+	sectors::{
+		ascii::*, sector_cost::*, sector_density::*, sector_modifiers::*, sector_portals::*, *,
+	},
 	utilities::*,
 	*,
 };
 
+#[doc(hidden)]
+#[cfg(feature = "3d")]
+pub use crate::flowfields::sectors::{elevation::*, mesh_3d::*};
+
+#[doc(hidden)]
+#[cfg(feature = "heightmap")]
+pub use crate::flowfields::sectors::heightmap::*;
+
+#[doc(hidden)]
+#[cfg(feature = "tiled")]
+pub use crate::flowfields::sectors::tiled::*;
+
+#[doc(hidden)]
+#[cfg(feature = "ldtk")]
+pub use crate::flowfields::sectors::ldtk::*;
+
 #[doc(hidden)]
 pub use crate::{
 	bundle::*,
-	plugin::{cost_layer::*, flow_layer::*, *},
+	plugin::{cost_layer::*, flow_layer::*, query::*, *},
 };
+
+#[doc(hidden)]
+#[cfg(any(feature = "2d", feature = "3d"))]
+pub use crate::plugin::{avoidance::*, density_layer::*, obstacle_layer::*, steering::*};
+
+#[doc(hidden)]
+#[cfg(feature = "avian")]
+pub use crate::plugin::collider_layer::*;